@@ -61,7 +61,12 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     });
 
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
-    let url = format!("http://{}:3000/verify-token", auth_hostname);
+    // tokens issued for other apps are rejected
+    let audience = env::var("AUTH_AUDIENCE").unwrap_or("app-service".to_owned());
+    let url = format!(
+        "http://{}:3000/verify-token?audience={}",
+        auth_hostname, audience
+    );
 
     let response = match api_client.post(&url).json(&verify_token_body).send().await {
         Ok(response) => response,
@@ -112,6 +112,9 @@ signupButton.addEventListener("click", (e) => {
             response.json().then(data => {
                 let error_msg = data.error;
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    if (Array.isArray(data.reasons) && data.reasons.length > 0) {
+                        error_msg += ": " + data.reasons.map(reason => reason.message).join("; ");
+                    }
                     signupErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
                     signupErrAlter.style.display = "block";
                 } else {
@@ -0,0 +1,3 @@
+-- Tokens issued without a jti no longer pass validation, so bans keyed by the full token are moot
+DELETE FROM banned_tokens;
+ALTER TABLE banned_tokens RENAME COLUMN token TO jti;
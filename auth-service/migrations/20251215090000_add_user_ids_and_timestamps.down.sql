@@ -0,0 +1,9 @@
+ALTER TABLE users DROP CONSTRAINT users_email_key;
+ALTER TABLE users DROP CONSTRAINT users_pkey;
+ALTER TABLE users ADD PRIMARY KEY (email);
+
+ALTER TABLE users
+   DROP COLUMN last_login_at,
+   DROP COLUMN updated_at,
+   DROP COLUMN created_at,
+   DROP COLUMN id;
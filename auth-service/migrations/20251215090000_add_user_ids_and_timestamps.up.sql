@@ -0,0 +1,10 @@
+-- existing users get a random id each; their creation time is unknown, so it is the migration time
+ALTER TABLE users
+   ADD COLUMN id UUID NOT NULL DEFAULT gen_random_uuid(),
+   ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   ADD COLUMN last_login_at TIMESTAMPTZ;
+
+ALTER TABLE users DROP CONSTRAINT users_pkey;
+ALTER TABLE users ADD PRIMARY KEY (id);
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
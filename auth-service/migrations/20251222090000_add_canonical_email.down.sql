@@ -0,0 +1,3 @@
+ALTER TABLE users DROP CONSTRAINT users_canonical_email_key;
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+ALTER TABLE users DROP COLUMN canonical_email;
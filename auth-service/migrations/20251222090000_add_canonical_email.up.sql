@@ -0,0 +1,10 @@
+-- Accounts are identified by the canonical form of their email; `email` keeps the address as
+-- the user typed it. Existing addresses are canonicalized by lowercasing, which matches the
+-- default normalization of ASCII addresses. Accounts whose addresses only differ in case make
+-- this migration fail and have to be merged by hand first.
+ALTER TABLE users ADD COLUMN canonical_email TEXT;
+UPDATE users SET canonical_email = lower(email);
+ALTER TABLE users ALTER COLUMN canonical_email SET NOT NULL;
+
+ALTER TABLE users DROP CONSTRAINT users_email_key;
+ALTER TABLE users ADD CONSTRAINT users_canonical_email_key UNIQUE (canonical_email);
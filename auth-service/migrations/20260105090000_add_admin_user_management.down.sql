@@ -0,0 +1,4 @@
+ALTER TABLE users
+   DROP COLUMN sessions_revoked_at,
+   DROP COLUMN password_reset_required,
+   DROP COLUMN role;
@@ -0,0 +1,6 @@
+-- The first admin has to be promoted by hand, e.g.
+-- UPDATE users SET role = 'admin' WHERE canonical_email = 'admin@example.com';
+ALTER TABLE users
+   ADD COLUMN role TEXT NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'admin')),
+   ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT FALSE,
+   ADD COLUMN sessions_revoked_at TIMESTAMPTZ;
@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY,
+   description TEXT NOT NULL DEFAULT ''
+);
+
+CREATE TABLE IF NOT EXISTS permissions(
+   name TEXT NOT NULL PRIMARY KEY,
+   description TEXT NOT NULL DEFAULT ''
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions(
+   role TEXT NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+   permission TEXT NOT NULL REFERENCES permissions (name) ON DELETE CASCADE,
+   PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE IF NOT EXISTS user_roles(
+   user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+   assigned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   PRIMARY KEY (user_id, role)
+);
+
+CREATE INDEX IF NOT EXISTS user_roles_role_idx ON user_roles (role);
+
+-- The admin role replaces the `users.role` column
+INSERT INTO roles (name, description) VALUES ('admin', 'Manages users, roles and permissions');
+INSERT INTO user_roles (user_id, role) SELECT id, 'admin' FROM users WHERE role = 'admin';
+ALTER TABLE users DROP COLUMN role;
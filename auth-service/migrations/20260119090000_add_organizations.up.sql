@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS organizations(
+   id UUID NOT NULL PRIMARY KEY,
+   name TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS org_members(
+   org_id UUID NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+   user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   role TEXT NOT NULL CHECK (role IN ('member', 'admin', 'owner')),
+   joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   PRIMARY KEY (org_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS org_members_user_id_idx ON org_members (user_id);
+
+CREATE TABLE IF NOT EXISTS org_invitations(
+   id UUID NOT NULL PRIMARY KEY,
+   org_id UUID NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+   email TEXT NOT NULL,
+   canonical_email TEXT NOT NULL,
+   role TEXT NOT NULL CHECK (role IN ('member', 'admin', 'owner')),
+   invited_by UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   UNIQUE (org_id, canonical_email)
+);
+
+CREATE INDEX IF NOT EXISTS org_invitations_canonical_email_idx
+   ON org_invitations (canonical_email);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS signup_invitations(
+   id UUID NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   org_id UUID REFERENCES organizations (id) ON DELETE CASCADE,
+   token_hash TEXT NOT NULL UNIQUE,
+   invited_by UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS signup_invitations_org_id_idx ON signup_invitations (org_id);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS personal_access_tokens(
+   id UUID NOT NULL PRIMARY KEY,
+   user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   name TEXT NOT NULL,
+   scope TEXT NOT NULL,
+   token_hash TEXT NOT NULL UNIQUE,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   expires_at TIMESTAMPTZ NOT NULL,
+   last_used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS personal_access_tokens_user_id_idx ON personal_access_tokens (user_id);
@@ -0,0 +1,3 @@
+ALTER TABLE users
+   DROP COLUMN password_reset_expires_at,
+   DROP COLUMN password_reset_token_hash;
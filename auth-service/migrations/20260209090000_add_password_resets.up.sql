@@ -0,0 +1,4 @@
+-- Only the hash of the emailed token is kept; a user has at most one pending reset
+ALTER TABLE users
+   ADD COLUMN password_reset_token_hash TEXT,
+   ADD COLUMN password_reset_expires_at TIMESTAMPTZ;
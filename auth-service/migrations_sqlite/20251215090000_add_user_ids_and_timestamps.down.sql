@@ -0,0 +1,11 @@
+CREATE TABLE users_old(
+   email TEXT NOT NULL PRIMARY KEY,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO users_old (email, password_hash, requires_2fa)
+SELECT email, password_hash, requires_2fa FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_old RENAME TO users;
@@ -0,0 +1,26 @@
+-- SQLite cannot change a primary key in place, so the table is rebuilt.
+-- Existing users get a random version 4 UUID each; their creation time is unknown, so it is the
+-- migration time.
+CREATE TABLE users_new(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL UNIQUE,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   last_login_at TEXT
+);
+
+INSERT INTO users_new (id, email, password_hash, requires_2fa)
+SELECT
+   lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4'
+      || substr(lower(hex(randomblob(2))), 2) || '-'
+      || substr('89ab', 1 + abs(random()) % 4, 1) || substr(lower(hex(randomblob(2))), 2) || '-'
+      || lower(hex(randomblob(6))),
+   email,
+   password_hash,
+   requires_2fa
+FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
@@ -0,0 +1,18 @@
+CREATE TABLE users_old(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL UNIQUE,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   last_login_at TEXT
+);
+
+INSERT INTO users_old (
+   id, email, password_hash, requires_2fa, created_at, updated_at, last_login_at
+)
+SELECT id, email, password_hash, requires_2fa, created_at, updated_at, last_login_at
+FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_old RENAME TO users;
@@ -0,0 +1,25 @@
+-- Accounts are identified by the canonical form of their email; `email` keeps the address as
+-- the user typed it. Existing addresses are canonicalized by lowercasing, which matches the
+-- default normalization of ASCII addresses. Accounts whose addresses only differ in case make
+-- this migration fail and have to be merged by hand first.
+-- The table is rebuilt to move the unique constraint off `email`.
+CREATE TABLE users_new(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   canonical_email TEXT NOT NULL UNIQUE,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   last_login_at TEXT
+);
+
+INSERT INTO users_new (
+   id, email, canonical_email, password_hash, requires_2fa, created_at, updated_at, last_login_at
+)
+SELECT
+   id, email, lower(email), password_hash, requires_2fa, created_at, updated_at, last_login_at
+FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
@@ -0,0 +1,16 @@
+ALTER TABLE users
+   ADD COLUMN status TEXT NOT NULL DEFAULT 'active'
+   CHECK (status IN ('pending', 'active', 'locked', 'disabled'));
+
+CREATE TABLE IF NOT EXISTS account_status_changes(
+   id INTEGER PRIMARY KEY AUTOINCREMENT,
+   user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   from_status TEXT NOT NULL,
+   to_status TEXT NOT NULL,
+   reason TEXT NOT NULL,
+   actor TEXT NOT NULL,
+   changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS account_status_changes_user_id_idx
+   ON account_status_changes (user_id, changed_at);
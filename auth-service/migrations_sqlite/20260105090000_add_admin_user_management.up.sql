@@ -0,0 +1,6 @@
+-- The first admin has to be promoted by hand, e.g.
+-- UPDATE users SET role = 'admin' WHERE canonical_email = 'admin@example.com';
+ALTER TABLE users
+   ADD COLUMN role TEXT NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'admin'));
+ALTER TABLE users ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN sessions_revoked_at TEXT;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS signup_invitations(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   org_id TEXT REFERENCES organizations (id) ON DELETE CASCADE,
+   token_hash TEXT NOT NULL UNIQUE,
+   invited_by TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS signup_invitations_org_id_idx ON signup_invitations (org_id);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS personal_access_tokens(
+   id TEXT NOT NULL PRIMARY KEY,
+   user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+   name TEXT NOT NULL,
+   scope TEXT NOT NULL,
+   token_hash TEXT NOT NULL UNIQUE,
+   created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+   expires_at TEXT NOT NULL,
+   last_used_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS personal_access_tokens_user_id_idx ON personal_access_tokens (user_id);
@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN password_reset_expires_at;
+ALTER TABLE users DROP COLUMN password_reset_token_hash;
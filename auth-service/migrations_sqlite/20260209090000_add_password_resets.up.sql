@@ -0,0 +1,3 @@
+-- Only the hash of the emailed token is kept; a user has at most one pending reset
+ALTER TABLE users ADD COLUMN password_reset_token_hash TEXT;
+ALTER TABLE users ADD COLUMN password_reset_expires_at TEXT;
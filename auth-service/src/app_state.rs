@@ -1,33 +1,144 @@
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use chrono::Duration;
 
-pub type UserStoreType = Arc<RwLock<dyn UserStore>>;
-pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore>>;
-pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore>>;
+use crate::{
+    domain::{
+        BannedTokenStore, BreachedPasswordChecker, EmailClient, EmailDomainPolicy, OrgStore,
+        PasswordPolicy, PersonalAccessTokenStore, RoleStore, SignupInvitationStore, SignupMode,
+        TwoFACodeStore, UserStore,
+    },
+    utils::{auth::TokenSettings, auth_token::TokenSources},
+};
+
+const DEFAULT_SIGNUP_INVITATION_TTL: Duration = Duration::days(7);
+const DEFAULT_PASSWORD_RESET_TTL: Duration = Duration::hours(1);
+const DEFAULT_PERSONAL_ACCESS_TOKEN_MAX_TTL: Duration = Duration::days(365);
+const DEFAULT_STEP_UP_MAX_AGE: Duration = Duration::minutes(5);
+
+// Stores synchronise internally, so handlers can use them concurrently
+pub type UserStoreType = Arc<dyn UserStore>;
+pub type RoleStoreType = Arc<dyn RoleStore>;
+pub type OrgStoreType = Arc<dyn OrgStore>;
+pub type SignupInvitationStoreType = Arc<dyn SignupInvitationStore>;
+pub type PersonalAccessTokenStoreType = Arc<dyn PersonalAccessTokenStore>;
+pub type BannedTokenStoreType = Arc<dyn BannedTokenStore>;
+pub type TwoFACodeStoreType = Arc<dyn TwoFACodeStore>;
 pub type EmailClientType = Arc<dyn EmailClient>;
+pub type BreachedPasswordCheckerType = Arc<dyn BreachedPasswordChecker>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
+    pub role_store: RoleStoreType,
+    pub org_store: OrgStoreType,
+    pub signup_invitation_store: SignupInvitationStoreType,
+    pub personal_access_token_store: PersonalAccessTokenStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub password_policy: Arc<PasswordPolicy>,
+    pub email_domain_policy: Arc<EmailDomainPolicy>,
+    pub breached_password_checker: Option<BreachedPasswordCheckerType>,
+    pub signup_mode: SignupMode,
+    pub signup_invitation_ttl: Duration,
+    pub password_reset_ttl: Duration,
+    // The longest a personal access token may be valid for
+    pub personal_access_token_max_ttl: Duration,
+    pub token_sources: TokenSources,
+    pub token_settings: TokenSettings,
+    // How recently the user must have authenticated for sensitive operations
+    pub step_up_max_age: Duration,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
+        role_store: RoleStoreType,
+        org_store: OrgStoreType,
+        signup_invitation_store: SignupInvitationStoreType,
+        personal_access_token_store: PersonalAccessTokenStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
     ) -> Self {
         Self {
             user_store,
+            role_store,
+            org_store,
+            signup_invitation_store,
+            personal_access_token_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            password_policy: Arc::new(PasswordPolicy::default()),
+            email_domain_policy: Arc::new(EmailDomainPolicy::default()),
+            breached_password_checker: None,
+            signup_mode: SignupMode::default(),
+            signup_invitation_ttl: DEFAULT_SIGNUP_INVITATION_TTL,
+            password_reset_ttl: DEFAULT_PASSWORD_RESET_TTL,
+            personal_access_token_max_ttl: DEFAULT_PERSONAL_ACCESS_TOKEN_MAX_TTL,
+            token_sources: TokenSources::default(),
+            token_settings: TokenSettings::default(),
+            step_up_max_age: DEFAULT_STEP_UP_MAX_AGE,
         }
     }
+
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = Arc::new(password_policy);
+        self
+    }
+
+    pub fn with_email_domain_policy(mut self, email_domain_policy: EmailDomainPolicy) -> Self {
+        self.email_domain_policy = Arc::new(email_domain_policy);
+        self
+    }
+
+    pub fn with_breached_password_checker(
+        mut self,
+        breached_password_checker: BreachedPasswordCheckerType,
+    ) -> Self {
+        self.breached_password_checker = Some(breached_password_checker);
+        self
+    }
+
+    pub fn with_signup_mode(mut self, signup_mode: SignupMode) -> Self {
+        self.signup_mode = signup_mode;
+        self
+    }
+
+    pub fn with_signup_invitation_ttl(mut self, signup_invitation_ttl: Duration) -> Self {
+        self.signup_invitation_ttl = signup_invitation_ttl;
+        self
+    }
+
+    pub fn with_password_reset_ttl(mut self, password_reset_ttl: Duration) -> Self {
+        self.password_reset_ttl = password_reset_ttl;
+        self
+    }
+
+    pub fn with_personal_access_token_max_ttl(
+        mut self,
+        personal_access_token_max_ttl: Duration,
+    ) -> Self {
+        self.personal_access_token_max_ttl = personal_access_token_max_ttl;
+        self
+    }
+
+    // Where requests' auth tokens are looked for, and in which order
+    pub fn with_token_sources(mut self, token_sources: TokenSources) -> Self {
+        self.token_sources = token_sources;
+        self
+    }
+
+    pub fn with_token_settings(mut self, token_settings: TokenSettings) -> Self {
+        self.token_settings = token_settings;
+        self
+    }
+
+    pub fn with_step_up_max_age(mut self, step_up_max_age: Duration) -> Self {
+        self.step_up_max_age = step_up_max_age;
+        self
+    }
 }
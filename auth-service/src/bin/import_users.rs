@@ -0,0 +1,109 @@
+// Bulk-imports users migrated from another system, keeping their existing password hashes.
+//
+// Usage: import_users <users.jsonl | users.csv>
+//
+// Users are written to the database DATABASE_URL points to (PostgreSQL or SQLite).
+//
+// Each record holds `email`, `hash` and optionally `requires_2fa` (defaults to false). Supported
+// hashes are Argon2, bcrypt, PBKDF2-SHA256 (PHC or Django format) and scrypt; they are upgraded
+// to Argon2id on each user's next successful login. Users that already exist are skipped.
+
+use std::{fs::File, path::PathBuf};
+
+use auth_service::{
+    domain::UserStoreError,
+    services::user_import::{read_users, ImportFormat, ImportedUser},
+    utils::{constants::DATABASE_URL, tracing::init_tracing},
+};
+#[cfg(feature = "postgres")]
+use auth_service::{get_postgres_pool, services::data_stores::PostgresUserStore};
+#[cfg(feature = "sqlite")]
+use auth_service::{get_sqlite_pool, services::data_stores::SqliteUserStore};
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::ExposeSecret;
+
+enum Store {
+    #[cfg(feature = "postgres")]
+    Postgres(PostgresUserStore),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteUserStore),
+}
+
+impl Store {
+    async fn connect() -> Result<Self> {
+        match DATABASE_URL.expose_secret().split(':').next() {
+            #[cfg(feature = "postgres")]
+            Some("postgres" | "postgresql") => {
+                let pool = get_postgres_pool(&DATABASE_URL)
+                    .await
+                    .wrap_err("failed to create Postgres connection pool")?;
+                sqlx::migrate!()
+                    .run(&pool)
+                    .await
+                    .wrap_err("failed to run migrations")?;
+
+                Ok(Self::Postgres(PostgresUserStore::new(pool)))
+            }
+            #[cfg(feature = "sqlite")]
+            Some("sqlite") => {
+                let pool = get_sqlite_pool(&DATABASE_URL)
+                    .await
+                    .wrap_err("failed to create SQLite connection pool")?;
+                sqlx::migrate!("./migrations_sqlite")
+                    .run(&pool)
+                    .await
+                    .wrap_err("failed to run migrations")?;
+
+                Ok(Self::Sqlite(SqliteUserStore::new(pool)))
+            }
+            scheme => Err(eyre!(
+                "unsupported DATABASE_URL scheme: {}",
+                scheme.unwrap_or_default()
+            )),
+        }
+    }
+
+    async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::Postgres(store) => store.import_user(user).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.import_user(user).await,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install()?;
+    init_tracing()?;
+
+    let path: PathBuf = std::env::args()
+        .nth(1)
+        .ok_or_else(|| eyre!("usage: import_users <users.jsonl | users.csv>"))?
+        .into();
+
+    let format = ImportFormat::from_path(&path)?;
+    let file = File::open(&path).wrap_err(format!("failed to open {}", path.display()))?;
+    let users = read_users(file, format)?;
+
+    let user_store = Store::connect().await?;
+
+    let total = users.len();
+    let mut skipped = 0;
+    for user in users {
+        let email = user.email.as_ref().expose_secret().clone();
+        match user_store.import_user(user).await {
+            Ok(()) => {}
+            Err(UserStoreError::UserAlreadyExists) => {
+                tracing::warn!("Skipping existing user {email}");
+                skipped += 1;
+            }
+            Err(e) => return Err(e).wrap_err(format!("failed to import {email}")),
+        }
+    }
+
+    tracing::info!("Imported {} users, skipped {skipped}", total - skipped);
+
+    Ok(())
+}
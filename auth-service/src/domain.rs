@@ -1,13 +1,37 @@
+mod account_status;
+mod authentication;
+mod breached_password_checker;
+mod clock;
 mod data_stores;
 mod email;
 mod email_client;
+mod email_domain_policy;
 mod error;
+mod organization;
 mod password;
+mod password_reset;
+mod password_strength;
+mod personal_access_token;
+mod rbac;
+mod signup_invitation;
 mod user;
+mod user_id;
 
+pub use account_status::*;
+pub use authentication::*;
+pub use breached_password_checker::*;
+pub use clock::*;
 pub use data_stores::*;
 pub use email::*;
 pub use email_client::*;
+pub use email_domain_policy::*;
 pub(crate) use error::*;
-pub(crate) use password::*;
+pub use organization::*;
+pub use password::*;
+pub use password_reset::*;
+pub use password_strength::*;
+pub use personal_access_token::*;
+pub use rbac::*;
+pub use signup_invitation::*;
 pub(crate) use user::*;
+pub use user_id::*;
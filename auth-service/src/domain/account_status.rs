@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use super::AuthAPIError;
+
+// Only active accounts can log in or use their tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    // Signed up, but not allowed in yet
+    Pending,
+    Active,
+    // Temporarily suspended, e.g. after suspicious activity
+    Locked,
+    Disabled,
+}
+
+impl AccountStatus {
+    pub fn parse(status: &str) -> Result<Self> {
+        match status {
+            "pending" => Ok(Self::Pending),
+            "active" => Ok(Self::Active),
+            "locked" => Ok(Self::Locked),
+            "disabled" => Ok(Self::Disabled),
+            status => Err(eyre!("Invalid account status: {status}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Active => "active",
+            Self::Locked => "locked",
+            Self::Disabled => "disabled",
+        }
+    }
+
+    pub fn ensure_active(&self) -> Result<(), AuthAPIError> {
+        match self {
+            Self::Active => Ok(()),
+            Self::Pending => Err(AuthAPIError::AccountPending),
+            Self::Locked => Err(AuthAPIError::AccountLocked),
+            Self::Disabled => Err(AuthAPIError::AccountDisabled),
+        }
+    }
+}
+
+// A requested status change; `actor` identifies who made it, e.g. an admin's user id
+#[derive(Debug, Clone)]
+pub struct StatusChange {
+    pub status: AccountStatus,
+    pub reason: String,
+    pub actor: String,
+}
+
+// A status change as recorded in a user's history
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusTransition {
+    pub from: AccountStatus,
+    pub to: AccountStatus,
+    pub reason: String,
+    pub actor: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_round_trips_through_its_string_form() {
+        for status in [
+            AccountStatus::Pending,
+            AccountStatus::Active,
+            AccountStatus::Locked,
+            AccountStatus::Disabled,
+        ] {
+            assert_eq!(AccountStatus::parse(status.as_str()).unwrap(), status);
+        }
+        assert!(AccountStatus::parse("banned").is_err());
+    }
+
+    #[test]
+    fn only_active_accounts_are_allowed_in() {
+        assert!(AccountStatus::Active.ensure_active().is_ok());
+        assert!(matches!(
+            AccountStatus::Pending.ensure_active(),
+            Err(AuthAPIError::AccountPending)
+        ));
+        assert!(matches!(
+            AccountStatus::Locked.ensure_active(),
+            Err(AuthAPIError::AccountLocked)
+        ));
+        assert!(matches!(
+            AccountStatus::Disabled.ensure_active(),
+            Err(AuthAPIError::AccountDisabled)
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+// A way the user proved who they are, as named in the `amr` claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    Password,
+    EmailOtp,
+    Totp,
+}
+
+impl AuthMethod {
+    pub fn parse(method: &str) -> Result<Self> {
+        match method {
+            "password" => Ok(Self::Password),
+            "email-otp" => Ok(Self::EmailOtp),
+            "totp" => Ok(Self::Totp),
+            method => Err(eyre!("Invalid authentication method: {method}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Password => "password",
+            Self::EmailOtp => "email-otp",
+            Self::Totp => "totp",
+        }
+    }
+
+    fn is_second_factor(&self) -> bool {
+        matches!(self, Self::EmailOtp | Self::Totp)
+    }
+}
+
+// When and how the user last authenticated. Tokens re-issued without asking for credentials
+// carry it over, so only logging in or reauthenticating makes it more recent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authentication {
+    pub time: DateTime<Utc>,
+    pub methods: Vec<AuthMethod>,
+}
+
+impl Authentication {
+    pub const SINGLE_FACTOR: &'static str = "aal1";
+    pub const MULTI_FACTOR: &'static str = "aal2";
+    // Personal access tokens are not an authentication of the user
+    pub const NONE: &'static str = "aal0";
+
+    pub fn now(methods: Vec<AuthMethod>) -> Self {
+        Self {
+            time: Utc::now(),
+            methods,
+        }
+    }
+
+    // Unknown methods are dropped, so they can't make an authentication look stronger
+    pub fn from_claims(auth_time: usize, amr: &[String]) -> Self {
+        Self {
+            time: DateTime::from_timestamp(auth_time as i64, 0).unwrap_or_default(),
+            methods: amr
+                .iter()
+                .filter_map(|method| AuthMethod::parse(method).ok())
+                .collect(),
+        }
+    }
+
+    pub fn is_multi_factor(&self) -> bool {
+        self.methods.contains(&AuthMethod::Password)
+            && self.methods.iter().any(AuthMethod::is_second_factor)
+    }
+
+    // The `acr` claim, as an assurance level in the NIST SP 800-63 sense
+    pub fn acr(&self) -> &'static str {
+        if self.is_multi_factor() {
+            Self::MULTI_FACTOR
+        } else if self.methods.is_empty() {
+            Self::NONE
+        } else {
+            Self::SINGLE_FACTOR
+        }
+    }
+
+    pub fn amr(&self) -> Vec<String> {
+        self.methods
+            .iter()
+            .map(|method| method.as_str().to_owned())
+            .collect()
+    }
+
+    pub fn is_older_than(&self, max_age: Duration) -> bool {
+        Utc::now() - self.time > max_age
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_factors_raise_the_assurance_level() {
+        assert_eq!(Authentication::now(Vec::new()).acr(), "aal0");
+        assert_eq!(
+            Authentication::now(vec![AuthMethod::Password]).acr(),
+            "aal1"
+        );
+        assert_eq!(
+            Authentication::now(vec![AuthMethod::Password, AuthMethod::EmailOtp]).acr(),
+            "aal2"
+        );
+        assert_eq!(
+            Authentication::now(vec![AuthMethod::Password, AuthMethod::Totp]).acr(),
+            "aal2"
+        );
+        // a code alone is not two factors
+        assert_eq!(
+            Authentication::now(vec![AuthMethod::EmailOtp]).acr(),
+            "aal1"
+        );
+    }
+
+    #[test]
+    fn claims_round_trip_without_unknown_methods() {
+        let authentication = Authentication::now(vec![AuthMethod::Password, AuthMethod::Totp]);
+        let mut amr = authentication.amr();
+        assert_eq!(amr, ["password", "totp"]);
+        amr.push("hwk".to_owned());
+
+        let parsed = Authentication::from_claims(authentication.time.timestamp() as usize, &amr);
+        assert_eq!(parsed.methods, authentication.methods);
+        assert_eq!(parsed.time.timestamp(), authentication.time.timestamp());
+        assert!(!parsed.is_older_than(Duration::minutes(1)));
+        assert!(parsed.is_older_than(Duration::seconds(-1)));
+    }
+}
@@ -0,0 +1,9 @@
+use color_eyre::eyre;
+use secrecy::Secret;
+
+// This trait represents the interface all concrete breached password checkers should implement
+#[async_trait::async_trait]
+pub trait BreachedPasswordChecker: Send + Sync + 'static {
+    // Returns how many times the password has been seen in known breaches (0 if never)
+    async fn breach_count(&self, password: &Secret<String>) -> eyre::Result<u64>;
+}
@@ -0,0 +1,6 @@
+use std::time::Instant;
+
+// Source of the current time for anything that expires, so tests can move time forward
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> Instant;
+}
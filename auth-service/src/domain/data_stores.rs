@@ -1,7 +1,15 @@
-use crate::domain::{Email, Password};
+use std::time::Duration;
+
+use crate::domain::{
+    Email, Grants, Invitation, InvitationId, Membership, OrgId, OrgRole, Organization, Password,
+    PasswordReset, PasswordResetToken, Permission, PermissionDefinition, PersonalAccessToken,
+    PersonalAccessTokenId, RoleDefinition, RoleName, SignupInvitation, SignupInvitationId,
+    StatusChange, StatusTransition, UserId,
+};
 
 use super::User;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Report, Result};
 use rand::Rng;
 use secrecy::{ExposeSecret, Secret};
@@ -9,10 +17,65 @@ use thiserror::Error;
 
 #[async_trait]
 pub trait UserStore: Send + Sync + 'static {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    // Called whenever the user is issued an auth token
+    async fn record_login(&self, id: &UserId) -> Result<(), UserStoreError>;
+    // Changes the account status and records the transition in the user's status history
+    async fn set_status(&self, id: &UserId, change: StatusChange) -> Result<(), UserStoreError>;
+    // Oldest transition first
+    async fn get_status_history(
+        &self,
+        id: &UserId,
+    ) -> Result<Vec<StatusTransition>, UserStoreError>;
+    // Oldest account first
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+    // Only the fields that are set are changed
+    async fn update_user(&self, id: &UserId, update: UserUpdate) -> Result<(), UserStoreError>;
+    // Replaces any reset already pending for the user
+    async fn start_password_reset(
+        &self,
+        id: &UserId,
+        reset: &PasswordReset,
+    ) -> Result<(), UserStoreError>;
+    // Sets the new password if the token matches the user's pending, unexpired reset, which is
+    // then used up; also lifts a forced reset and ends the user's sessions. Fails with
+    // `InvalidCredentials` otherwise
+    async fn complete_password_reset(
+        &self,
+        email: &Email,
+        token: &PasswordResetToken,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    // Also lifts a forced reset and drops any pending one
+    async fn set_password(&self, id: &UserId, password: Password) -> Result<(), UserStoreError>;
+    // Everything else belonging to the user goes with it
+    async fn delete_user(&self, id: &UserId) -> Result<(), UserStoreError>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserQuery {
+    // Matched case-insensitively against any part of the address
+    pub email: Option<String>,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+#[derive(Debug)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    // Number of users matching the query, across all pages
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserUpdate {
+    pub requires_2fa: Option<bool>,
+    pub password_reset_required: Option<bool>,
+    pub sessions_revoked_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Error)]
@@ -23,6 +86,8 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Too many concurrent password hashing requests")]
+    Overloaded,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -34,15 +99,260 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::Overloaded, Self::Overloaded)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Roles group permissions; users are assigned roles, and tokens carry both
+#[async_trait]
+pub trait RoleStore: Send + Sync + 'static {
+    async fn add_role(&self, name: &RoleName, description: &str) -> Result<(), RoleStoreError>;
+    // Also unassigns the role from every user
+    async fn delete_role(&self, name: &RoleName) -> Result<(), RoleStoreError>;
+    // Sorted by name
+    async fn list_roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError>;
+    async fn add_permission(
+        &self,
+        name: &Permission,
+        description: &str,
+    ) -> Result<(), RoleStoreError>;
+    // Also revokes the permission from every role
+    async fn delete_permission(&self, name: &Permission) -> Result<(), RoleStoreError>;
+    // Sorted by name
+    async fn list_permissions(&self) -> Result<Vec<PermissionDefinition>, RoleStoreError>;
+    // Granting, revoking, assigning and unassigning are idempotent
+    async fn grant_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError>;
+    async fn revoke_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError>;
+    async fn assign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError>;
+    async fn unassign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError>;
+    async fn get_grants(&self, user_id: &UserId) -> Result<Grants, RoleStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RoleStoreError {
+    #[error("Role already exists")]
+    RoleAlreadyExists,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("Permission already exists")]
+    PermissionAlreadyExists,
+    #[error("Permission not found")]
+    PermissionNotFound,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RoleStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RoleAlreadyExists, Self::RoleAlreadyExists)
+                | (Self::RoleNotFound, Self::RoleNotFound)
+                | (Self::PermissionAlreadyExists, Self::PermissionAlreadyExists)
+                | (Self::PermissionNotFound, Self::PermissionNotFound)
+                | (Self::UserNotFound, Self::UserNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Every query on members and invitations is scoped to one organization, or to one invitee,
+// so a tenant never sees another's data
+#[async_trait]
+pub trait OrgStore: Send + Sync + 'static {
+    // The creator becomes the organization's first owner
+    async fn create_org(&self, org: &Organization, owner: &UserId) -> Result<(), OrgStoreError>;
+    async fn get_org(&self, id: &OrgId) -> Result<Organization, OrgStoreError>;
+    // Organizations the user is a member of, with their role in each; oldest membership first
+    async fn list_user_orgs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<(Organization, OrgRole)>, OrgStoreError>;
+    // Fails with `NotMember` for users outside the organization
+    async fn get_role(&self, org_id: &OrgId, user_id: &UserId) -> Result<OrgRole, OrgStoreError>;
+    // Oldest membership first
+    async fn list_members(&self, org_id: &OrgId) -> Result<Vec<Membership>, OrgStoreError>;
+    // Changing or removing the last owner fails with `LastOwner`
+    async fn set_role(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError>;
+    async fn remove_member(&self, org_id: &OrgId, user_id: &UserId) -> Result<(), OrgStoreError>;
+    // Does nothing for users who already are members
+    async fn add_member(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError>;
+    // An email can have only one pending invitation per organization
+    async fn add_invitation(&self, invitation: &Invitation) -> Result<(), OrgStoreError>;
+    // Oldest invitation first
+    async fn list_invitations(&self, org_id: &OrgId) -> Result<Vec<Invitation>, OrgStoreError>;
+    async fn revoke_invitation(
+        &self,
+        org_id: &OrgId,
+        id: &InvitationId,
+    ) -> Result<(), OrgStoreError>;
+    // Pending invitations addressed to the email, across organizations
+    async fn list_invitations_for(&self, email: &Email) -> Result<Vec<Invitation>, OrgStoreError>;
+    // Only the invitee can accept; makes them a member, unless they already were, and
+    // returns the organization they joined
+    async fn accept_invitation(
+        &self,
+        id: &InvitationId,
+        email: &Email,
+        user_id: &UserId,
+    ) -> Result<OrgId, OrgStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OrgStoreError {
+    #[error("Organization not found")]
+    OrgNotFound,
+    #[error("Not a member of the organization")]
+    NotMember,
+    #[error("The organization needs at least one owner")]
+    LastOwner,
+    #[error("Invitation already exists")]
+    InvitationAlreadyExists,
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OrgStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::OrgNotFound, Self::OrgNotFound)
+                | (Self::NotMember, Self::NotMember)
+                | (Self::LastOwner, Self::LastOwner)
+                | (Self::InvitationAlreadyExists, Self::InvitationAlreadyExists)
+                | (Self::InvitationNotFound, Self::InvitationNotFound)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
 }
 
+// Invitations are looked up by the hash of their token, and removed once used
+#[async_trait]
+pub trait SignupInvitationStore: Send + Sync + 'static {
+    async fn add_invitation(
+        &self,
+        invitation: &SignupInvitation,
+    ) -> Result<(), SignupInvitationStoreError>;
+    // All invitations with `None`, otherwise those made for the organization; oldest first
+    async fn list_invitations(
+        &self,
+        org_id: Option<&OrgId>,
+    ) -> Result<Vec<SignupInvitation>, SignupInvitationStoreError>;
+    // With `Some`, only revokes an invitation made for that organization
+    async fn revoke_invitation(
+        &self,
+        id: &SignupInvitationId,
+        org_id: Option<&OrgId>,
+    ) -> Result<(), SignupInvitationStoreError>;
+    // Expired invitations are returned as well; callers check `is_expired`
+    async fn get_invitation_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<SignupInvitation, SignupInvitationStoreError>;
+    // Fails with `InvitationNotFound` if it was used or revoked in the meantime
+    async fn consume_invitation(
+        &self,
+        id: &SignupInvitationId,
+    ) -> Result<(), SignupInvitationStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SignupInvitationStoreError {
+    #[error("Organization not found")]
+    OrgNotFound,
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SignupInvitationStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::OrgNotFound, Self::OrgNotFound)
+                | (Self::InvitationNotFound, Self::InvitationNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Tokens are looked up by the hash of their secret, and only ever act for their owner
+#[async_trait]
+pub trait PersonalAccessTokenStore: Send + Sync + 'static {
+    async fn add_token(
+        &self,
+        token: &PersonalAccessToken,
+    ) -> Result<(), PersonalAccessTokenStoreError>;
+    // Oldest first, expired ones included
+    async fn list_tokens(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PersonalAccessToken>, PersonalAccessTokenStoreError>;
+    async fn revoke_token(
+        &self,
+        user_id: &UserId,
+        id: &PersonalAccessTokenId,
+    ) -> Result<(), PersonalAccessTokenStoreError>;
+    // Expired tokens are returned as well; callers check `is_expired`
+    async fn get_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<PersonalAccessToken, PersonalAccessTokenStoreError>;
+    async fn record_use(
+        &self,
+        id: &PersonalAccessTokenId,
+        used_at: DateTime<Utc>,
+    ) -> Result<(), PersonalAccessTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PersonalAccessTokenStoreError {
+    #[error("Personal access token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PersonalAccessTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Tokens are banned by their `jti` claim, for as long as the token could still be accepted
 #[async_trait]
 pub trait BannedTokenStore: Send + Sync + 'static {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    async fn add_token(&self, jti: &str, ttl: Duration) -> Result<(), BannedTokenStoreError>;
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -57,12 +367,13 @@ pub enum BannedTokenStoreError {
 #[async_trait]
 pub trait TwoFACodeStore: Send + Sync + 'static {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    // Fails with `LoginAttemptIdNotFound` if there was no code to remove
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
         email: &Email,
@@ -1,20 +1,28 @@
 use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, Secret};
 use std::hash::Hash;
+use unicode_normalization::UnicodeNormalization;
 use validator::validate_email;
 
+use crate::utils::constants::EMAIL_LOWERCASE_LOCAL_PART;
+
+// Keeps the address as the user typed it, for display and for sending mail, alongside its
+// canonical form, which is what identifies the account
 #[derive(Debug, Clone)]
-pub struct Email(Secret<String>);
+pub struct Email {
+    address: Secret<String>,
+    canonical: Secret<String>,
+}
 
 impl PartialEq for Email {
     fn eq(&self, other: &Self) -> bool {
-        self.0.expose_secret() == other.0.expose_secret()
+        self.canonical.expose_secret() == other.canonical.expose_secret()
     }
 }
 
 impl Hash for Email {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.expose_secret().hash(state);
+        self.canonical.expose_secret().hash(state);
     }
 }
 
@@ -22,30 +30,69 @@ impl Eq for Email {}
 
 impl Email {
     pub fn parse(s: Secret<String>) -> Result<Email> {
-        if validate_email(s.expose_secret()) {
-            Ok(Self(s))
-        } else {
-            Err(eyre!(format!(
+        if !validate_email(s.expose_secret()) {
+            return Err(eyre!(format!(
                 "{} is not a valid email.",
                 s.expose_secret()
-            )))
+            )));
         }
+
+        let canonical = canonicalize(
+            s.expose_secret(),
+            EMAIL_LOWERCASE_LOCAL_PART.unwrap_or(true),
+        )?;
+
+        Ok(Self {
+            address: s,
+            canonical: Secret::new(canonical),
+        })
+    }
+
+    pub fn canonical(&self) -> &Secret<String> {
+        &self.canonical
+    }
+
+    // From the canonical form: lowercase, and punycode for internationalized domains
+    pub fn domain(&self) -> &str {
+        self.canonical
+            .expose_secret()
+            .rsplit_once('@')
+            .map_or("", |(_, domain)| domain)
     }
 }
 
 impl AsRef<Secret<String>> for Email {
     fn as_ref(&self) -> &Secret<String> {
-        &self.0
+        &self.address
     }
 }
 
+// The domain is converted to lowercase ASCII (punycode for internationalized domains). The local
+// part is only NFC-normalized, and lowercased unless the deployment treats it as case-sensitive,
+// as RFC 5321 allows.
+fn canonicalize(address: &str, lowercase_local_part: bool) -> Result<String> {
+    let (local_part, domain) = address
+        .rsplit_once('@')
+        .ok_or_else(|| eyre!("email is missing an @"))?;
+
+    let domain = idna::domain_to_ascii(domain).map_err(|_| eyre!("invalid email domain"))?;
+    let local_part: String = local_part.nfc().collect();
+    let local_part = if lowercase_local_part {
+        local_part.to_lowercase()
+    } else {
+        local_part
+    };
+
+    Ok(format!("{local_part}@{domain}"))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Email;
+    use super::{canonicalize, Email};
 
     use fake::faker::internet::en::SafeEmail;
     use fake::Fake;
-    use secrecy::Secret;
+    use secrecy::{ExposeSecret, Secret};
 
     #[test]
     fn empty_string_is_rejected() {
@@ -63,6 +110,41 @@ mod tests {
         assert!(Email::parse(email).is_err());
     }
 
+    #[test]
+    fn differently_cased_addresses_are_the_same_email() {
+        let parse = |s: &str| Email::parse(Secret::new(s.to_owned())).unwrap();
+
+        let email = parse("Bob@Example.COM");
+        assert_eq!(email, parse("bob@example.com"));
+        assert_eq!(email.as_ref().expose_secret(), "Bob@Example.COM");
+        assert_eq!(email.canonical().expose_secret(), "bob@example.com");
+    }
+
+    #[test]
+    fn internationalized_domains_are_converted_to_punycode() {
+        assert_eq!(
+            canonicalize("user@BÜCHER.example", true).unwrap(),
+            "user@xn--bcher-kva.example"
+        );
+        assert_eq!(
+            canonicalize("user@xn--bcher-kva.example", true).unwrap(),
+            "user@xn--bcher-kva.example"
+        );
+    }
+
+    #[test]
+    fn local_part_case_can_be_preserved() {
+        assert_eq!(
+            canonicalize("Bob@Example.com", false).unwrap(),
+            "Bob@example.com"
+        );
+        // composed and decomposed forms of the same characters are equal either way
+        assert_eq!(
+            canonicalize("Jos\u{e9}@example.com", false).unwrap(),
+            canonicalize("Jose\u{301}@example.com", false).unwrap()
+        );
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
@@ -0,0 +1,281 @@
+use std::{collections::HashSet, fs, path::Path, sync::Arc};
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use lazy_static::lazy_static;
+use thiserror::Error;
+
+use super::Email;
+
+lazy_static! {
+    static ref BUNDLED_DISPOSABLE_DOMAINS: Arc<HashSet<String>> = Arc::new(parse_domain_list(
+        include_str!("../../data/disposable_email_domains.txt")
+    ));
+}
+
+// A domain, or a pattern where `*` stands for any run of characters: `*.example.com`
+// matches every subdomain of example.com, but not example.com itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainPattern(String);
+
+impl DomainPattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim().trim_start_matches('@');
+        if pattern.is_empty() || pattern.contains('@') {
+            return Err(eyre!("Invalid domain pattern: {pattern}"));
+        }
+
+        // converted like email domains, with the wildcards left alone
+        let labels = pattern
+            .split('.')
+            .map(|label| {
+                if label.contains('*') {
+                    Ok(label.to_ascii_lowercase())
+                } else {
+                    idna::domain_to_ascii(label)
+                        .map_err(|_| eyre!("Invalid domain pattern: {pattern}"))
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self(labels.join(".")))
+    }
+
+    // Comma-separated, as in the environment variables
+    pub fn parse_list(patterns: &str) -> Result<Vec<Self>> {
+        patterns
+            .split(',')
+            .filter(|pattern| !pattern.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    pub fn matches(&self, domain: &str) -> bool {
+        wildcard_match(self.0.as_bytes(), domain.as_bytes())
+    }
+}
+
+// Which email domains may sign up. The blocklist wins over the allowlist, and domains the
+// allowlist names are trusted even when they look disposable
+#[derive(Debug, Clone)]
+pub struct EmailDomainPolicy {
+    // Empty allows every domain
+    pub allowed_domains: Vec<DomainPattern>,
+    pub blocked_domains: Vec<DomainPattern>,
+    pub reject_disposable: bool,
+    pub disposable_domains: Arc<HashSet<String>>,
+}
+
+impl Default for EmailDomainPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_domains: Vec::new(),
+            blocked_domains: Vec::new(),
+            reject_disposable: true,
+            disposable_domains: BUNDLED_DISPOSABLE_DOMAINS.clone(),
+        }
+    }
+}
+
+impl EmailDomainPolicy {
+    // Replaces the bundled disposable domains with the ones listed in the file
+    pub fn with_disposable_domains_from(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let list = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read disposable domains from {path:?}"))?;
+
+        self.disposable_domains = Arc::new(parse_domain_list(&list));
+        Ok(self)
+    }
+
+    pub fn check(&self, email: &Email) -> Result<(), EmailDomainViolation> {
+        let domain = email.domain();
+
+        if self
+            .blocked_domains
+            .iter()
+            .any(|pattern| pattern.matches(domain))
+        {
+            return Err(EmailDomainViolation::Blocked);
+        }
+
+        if !self.allowed_domains.is_empty() {
+            if self
+                .allowed_domains
+                .iter()
+                .any(|pattern| pattern.matches(domain))
+            {
+                return Ok(());
+            }
+            return Err(EmailDomainViolation::NotAllowed);
+        }
+
+        if self.reject_disposable && self.is_disposable(domain) {
+            return Err(EmailDomainViolation::Disposable);
+        }
+
+        Ok(())
+    }
+
+    // Subdomains of a listed domain are disposable too
+    fn is_disposable(&self, domain: &str) -> bool {
+        let mut domain = domain;
+        loop {
+            if self.disposable_domains.contains(domain) {
+                return true;
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => domain = parent,
+                _ => return false,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EmailDomainViolation {
+    #[error("Email domain is not on the list of allowed domains")]
+    NotAllowed,
+    #[error("Email domain is blocked")]
+    Blocked,
+    #[error("Email domain belongs to a disposable email provider")]
+    Disposable,
+}
+
+impl EmailDomainViolation {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotAllowed => "email_domain_not_allowed",
+            Self::Blocked => "email_domain_blocked",
+            Self::Disposable => "email_domain_disposable",
+        }
+    }
+}
+
+// One domain per line; blank lines and `#` comments are skipped
+fn parse_domain_list(list: &str) -> HashSet<String> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|domain| idna::domain_to_ascii(domain).ok())
+        .collect()
+}
+
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some((c, rest)) => text.first() == Some(c) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned().into()).unwrap()
+    }
+
+    fn patterns(patterns: &str) -> Vec<DomainPattern> {
+        DomainPattern::parse_list(patterns).unwrap()
+    }
+
+    #[test]
+    fn wildcards_match_any_run_of_characters() {
+        let pattern = DomainPattern::parse("*.Example.com").unwrap();
+        assert!(pattern.matches("mail.example.com"));
+        assert!(pattern.matches("a.b.example.com"));
+        assert!(!pattern.matches("example.com"));
+        assert!(!pattern.matches("notexample.com"));
+
+        assert!(DomainPattern::parse("mail*.example.com")
+            .unwrap()
+            .matches("mail2.example.com"));
+        assert!(DomainPattern::parse("@bücher.de")
+            .unwrap()
+            .matches("xn--bcher-kva.de"));
+        assert!(DomainPattern::parse("user@example.com").is_err());
+    }
+
+    #[test]
+    fn allowlist_admits_only_matching_domains() {
+        let policy = EmailDomainPolicy {
+            allowed_domains: patterns("example.com, *.example.com"),
+            ..Default::default()
+        };
+
+        assert_eq!(policy.check(&email("john@example.com")), Ok(()));
+        assert_eq!(policy.check(&email("john@EU.Example.com")), Ok(()));
+        assert_eq!(
+            policy.check(&email("john@example.org")),
+            Err(EmailDomainViolation::NotAllowed)
+        );
+    }
+
+    #[test]
+    fn blocklist_wins_over_allowlist() {
+        let policy = EmailDomainPolicy {
+            allowed_domains: patterns("*.example.com"),
+            blocked_domains: patterns("legacy.example.com,spam*.net"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.check(&email("john@legacy.example.com")),
+            Err(EmailDomainViolation::Blocked)
+        );
+        assert_eq!(
+            policy.check(&email("john@spammer.net")),
+            Err(EmailDomainViolation::Blocked)
+        );
+        assert_eq!(policy.check(&email("john@eu.example.com")), Ok(()));
+    }
+
+    #[test]
+    fn disposable_domains_and_their_subdomains_are_rejected() {
+        let policy = EmailDomainPolicy::default();
+
+        assert_eq!(
+            policy.check(&email("john@mailinator.com")),
+            Err(EmailDomainViolation::Disposable)
+        );
+        assert_eq!(
+            policy.check(&email("john@eu.mailinator.com")),
+            Err(EmailDomainViolation::Disposable)
+        );
+        assert_eq!(policy.check(&email("john@example.com")), Ok(()));
+
+        let lenient = EmailDomainPolicy {
+            reject_disposable: false,
+            ..Default::default()
+        };
+        assert_eq!(lenient.check(&email("john@mailinator.com")), Ok(()));
+
+        let trusting = EmailDomainPolicy {
+            allowed_domains: patterns("mailinator.com"),
+            ..Default::default()
+        };
+        assert_eq!(trusting.check(&email("john@mailinator.com")), Ok(()));
+    }
+
+    #[test]
+    fn disposable_domains_can_be_loaded_from_a_file() {
+        let path =
+            std::env::temp_dir().join(format!("disposable_domains_{}.txt", uuid::Uuid::new_v4()));
+        fs::write(&path, "# updated list\nthrowaway.test\n\n").unwrap();
+
+        let policy = EmailDomainPolicy::default()
+            .with_disposable_domains_from(&path)
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            policy.check(&email("john@throwaway.test")),
+            Err(EmailDomainViolation::Disposable)
+        );
+        assert_eq!(policy.check(&email("john@mailinator.com")), Ok(()));
+        assert!(EmailDomainPolicy::default()
+            .with_disposable_domains_from(&path)
+            .is_err());
+    }
+}
@@ -1,12 +1,18 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+use super::{EmailDomainViolation, PasswordPolicyViolation};
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
     UserAlreadyExists,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Password does not meet the password policy")]
+    WeakPassword(Vec<PasswordPolicyViolation>),
+    #[error("Email domain is not accepted")]
+    EmailDomainRejected(EmailDomainViolation),
     #[error("Incorrect credentials")]
     IncorrectCredentials,
     #[error("Missing token")]
@@ -15,6 +21,58 @@ pub enum AuthAPIError {
     InvalidToken,
     #[error("Token already invalidated")]
     TokenAlreadyInvalidated,
+    #[error("Account pending activation")]
+    AccountPending,
+    #[error("Account locked")]
+    AccountLocked,
+    #[error("Account disabled")]
+    AccountDisabled,
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Missing permission")]
+    MissingPermission,
+    #[error("Invalid role or permission name")]
+    InvalidRoleOrPermission,
+    #[error("Role already exists")]
+    RoleAlreadyExists,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("Permission already exists")]
+    PermissionAlreadyExists,
+    #[error("Permission not found")]
+    PermissionNotFound,
+    #[error("Not a member of the organization")]
+    NotOrgMember,
+    #[error("Invalid organization name or role")]
+    InvalidOrgInput,
+    #[error("The organization needs at least one owner")]
+    LastOrgOwner,
+    #[error("Invitation already exists")]
+    InvitationAlreadyExists,
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    #[error("An invitation is required to sign up")]
+    InvitationRequired,
+    #[error("Invalid or expired invitation")]
+    InvalidInvitation,
+    #[error("Invalid or expired password reset token")]
+    InvalidPasswordReset,
+    #[error("Invalid personal access token name, scopes or expiry")]
+    InvalidPersonalAccessTokenInput,
+    #[error("Personal access token not found")]
+    PersonalAccessTokenNotFound,
+    #[error("Unknown token audience")]
+    InvalidAudience,
+    #[error("Missing token audience")]
+    MissingAudience,
+    #[error("Reauthentication required")]
+    ReauthenticationRequired,
+    #[error("Service unavailable")]
+    ServiceUnavailable,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -0,0 +1,184 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use uuid::Uuid;
+
+use super::{Email, UserId};
+
+// Identifies a customer company; users see only the organizations they are members of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OrgId(Uuid);
+
+impl OrgId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid organization id"))
+    }
+}
+
+impl Default for OrgId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for OrgId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for OrgId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for OrgId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Organization {
+    pub id: OrgId,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Organization {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: OrgId::default(),
+            name,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+// A member's role within one organization, independent of their global roles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OrgRole {
+    Member,
+    Admin,
+    // Every organization keeps at least one
+    Owner,
+}
+
+impl OrgRole {
+    pub fn parse(role: &str) -> Result<Self> {
+        match role {
+            "member" => Ok(Self::Member),
+            "admin" => Ok(Self::Admin),
+            "owner" => Ok(Self::Owner),
+            role => Err(eyre!("Invalid organization role: {role}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+
+    // Admins manage members and invitations, but only owners may touch other owners
+    pub fn can_manage(&self, role: OrgRole) -> bool {
+        match self {
+            Self::Owner => true,
+            Self::Admin => role != Self::Owner,
+            Self::Member => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Membership {
+    pub user_id: UserId,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvitationId(Uuid);
+
+impl InvitationId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid invitation id"))
+    }
+}
+
+impl Default for InvitationId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for InvitationId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for InvitationId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for InvitationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Pending until the user with the invited email accepts it, which makes them a member
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invitation {
+    pub id: InvitationId,
+    pub org_id: OrgId,
+    pub email: Email,
+    pub role: OrgRole,
+    pub invited_by: UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Invitation {
+    pub fn new(org_id: OrgId, email: Email, role: OrgRole, invited_by: UserId) -> Self {
+        Self {
+            id: InvitationId::default(),
+            org_id,
+            email,
+            role,
+            invited_by,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_round_trips_through_its_string_form() {
+        for role in [OrgRole::Member, OrgRole::Admin, OrgRole::Owner] {
+            assert_eq!(OrgRole::parse(role.as_str()).unwrap(), role);
+        }
+        assert!(OrgRole::parse("guest").is_err());
+    }
+
+    #[test]
+    fn only_owners_manage_owners() {
+        assert!(OrgRole::Owner.can_manage(OrgRole::Owner));
+        assert!(OrgRole::Admin.can_manage(OrgRole::Admin));
+        assert!(!OrgRole::Admin.can_manage(OrgRole::Owner));
+        assert!(!OrgRole::Member.can_manage(OrgRole::Member));
+    }
+}
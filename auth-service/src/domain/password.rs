@@ -1,5 +1,8 @@
 use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, Secret};
+use thiserror::Error;
+
+use super::{estimate_strength, is_common_password, Email, PasswordStrength};
 
 #[derive(Debug, Clone)]
 pub struct Password(Secret<String>);
@@ -11,8 +14,11 @@ impl PartialEq for Password {
 }
 
 impl Password {
+    // Absolute lower bound shared by login and signup; stricter rules live in `PasswordPolicy`
+    pub const MIN_LENGTH: usize = 8;
+
     pub fn parse(s: Secret<String>) -> Result<Password> {
-        if validate_password(&s) {
+        if s.expose_secret().chars().count() >= Self::MIN_LENGTH {
             Ok(Self(s))
         } else {
             Err(eyre!("Failed to parse string to a Password type"))
@@ -20,20 +26,125 @@ impl Password {
     }
 }
 
-fn validate_password(s: &Secret<String>) -> bool {
-    s.expose_secret().len() >= 8
-}
-
 impl AsRef<Secret<String>> for Password {
     fn as_ref(&self) -> &Secret<String> {
         &self.0
     }
 }
 
+// Rules a new password has to satisfy (on signup and whenever a password is changed)
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_strength: PasswordStrength,
+    pub reject_common_passwords: bool,
+    pub reject_email_local_part: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: Password::MIN_LENGTH,
+            max_length: 128,
+            min_strength: PasswordStrength::Strong,
+            reject_common_passwords: true,
+            reject_email_local_part: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn parse(
+        &self,
+        password: Secret<String>,
+        email: &Email,
+    ) -> Result<Password, Vec<PasswordPolicyViolation>> {
+        let violations = self.check(password.expose_secret(), email);
+        if violations.is_empty() {
+            Ok(Password(password))
+        } else {
+            Err(violations)
+        }
+    }
+
+    fn check(&self, password: &str, email: &Email) -> Vec<PasswordPolicyViolation> {
+        let mut violations = Vec::new();
+
+        let min_length = self.min_length.max(Password::MIN_LENGTH);
+        let length = password.chars().count();
+        if length < min_length {
+            violations.push(PasswordPolicyViolation::TooShort(min_length));
+        }
+        // the other checks take time that grows with the length, so an overly long password
+        // is turned away before them
+        if length > self.max_length {
+            violations.push(PasswordPolicyViolation::TooLong(self.max_length));
+            return violations;
+        }
+
+        if self.reject_common_passwords && is_common_password(password) {
+            violations.push(PasswordPolicyViolation::TooCommon);
+        }
+
+        let local_part = email_local_part(email);
+        if self.reject_email_local_part
+            && local_part.chars().count() >= 3
+            && password.to_lowercase().contains(&local_part)
+        {
+            violations.push(PasswordPolicyViolation::ContainsEmail);
+        }
+
+        if estimate_strength(password, &[&local_part]) < self.min_strength {
+            violations.push(PasswordPolicyViolation::TooWeak);
+        }
+
+        violations
+    }
+}
+
+fn email_local_part(email: &Email) -> String {
+    let address = email.as_ref().expose_secret();
+    address
+        .rsplit_once('@')
+        .map_or(address.as_str(), |(local_part, _)| local_part)
+        .to_lowercase()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PasswordPolicyViolation {
+    #[error("Password must be at least {0} characters long")]
+    TooShort(usize),
+    #[error("Password must be at most {0} characters long")]
+    TooLong(usize),
+    #[error("Password is too common")]
+    TooCommon,
+    #[error("Password must not contain the email address")]
+    ContainsEmail,
+    #[error("Password is too easy to guess")]
+    TooWeak,
+    #[error("Password has appeared in a known data breach")]
+    Breached,
+}
+
+impl PasswordPolicyViolation {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::TooShort(_) => "password_too_short",
+            Self::TooLong(_) => "password_too_long",
+            Self::TooCommon => "password_too_common",
+            Self::ContainsEmail => "password_contains_email",
+            Self::TooWeak => "password_too_weak",
+            Self::Breached => "password_breached",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Password;
+    use super::{Password, PasswordPolicy, PasswordPolicyViolation};
 
+    use crate::domain::{Email, PasswordStrength};
     use fake::faker::internet::en::Password as FakePassword;
     use fake::Fake;
     use secrecy::Secret;
@@ -43,7 +154,7 @@ mod tests {
         let password = Secret::new("".to_string());
         assert!(Password::parse(password).is_err());
     }
-    
+
     #[test]
     fn string_less_than_8_characters_is_rejected() {
         let password = Secret::new("1234567".to_string());
@@ -59,9 +170,91 @@ mod tests {
             Self(Secret::new(password))
         }
     }
-    
+
     #[quickcheck_macros::quickcheck]
     fn valid_passwords_are_parsed_successfully(valid_password: ValidPasswordFixture) -> bool {
         Password::parse(valid_password.0).is_ok()
     }
+
+    fn email() -> Email {
+        Email::parse("john.smith@example.com".to_owned().into()).unwrap()
+    }
+
+    fn violations(policy: &PasswordPolicy, password: &str) -> Vec<PasswordPolicyViolation> {
+        policy
+            .parse(password.to_owned().into(), &email())
+            .err()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn policy_accepts_strong_password() {
+        assert!(violations(&PasswordPolicy::default(), "x7Kp#2vLq9!w").is_empty());
+    }
+
+    #[test]
+    fn policy_rejects_short_and_long_passwords() {
+        let policy = PasswordPolicy {
+            min_length: 12,
+            max_length: 16,
+            ..Default::default()
+        };
+
+        assert!(violations(&policy, "x7Kp#2vLq9").contains(&PasswordPolicyViolation::TooShort(12)));
+        assert_eq!(
+            violations(&policy, "x7Kp#2vLq9!wx7Kp#2vLq9!w"),
+            [PasswordPolicyViolation::TooLong(16)]
+        );
+    }
+
+    #[test]
+    fn huge_passwords_are_rejected_for_length_only() {
+        let password = "password".repeat(100_000);
+        assert_eq!(
+            violations(&PasswordPolicy::default(), &password),
+            [PasswordPolicyViolation::TooLong(128)]
+        );
+    }
+
+    #[test]
+    fn policy_never_goes_below_password_min_length() {
+        let policy = PasswordPolicy {
+            min_length: 4,
+            min_strength: PasswordStrength::VeryWeak,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            violations(&policy, "x7K#2v"),
+            vec![PasswordPolicyViolation::TooShort(Password::MIN_LENGTH)]
+        );
+    }
+
+    #[test]
+    fn policy_rejects_common_passwords() {
+        let v = violations(&PasswordPolicy::default(), "Password123");
+        assert!(v.contains(&PasswordPolicyViolation::TooCommon));
+        assert!(v.contains(&PasswordPolicyViolation::TooWeak));
+    }
+
+    #[test]
+    fn policy_rejects_passwords_containing_email_local_part() {
+        assert!(
+            violations(&PasswordPolicy::default(), "Xq9!John.Smith#2024")
+                .contains(&PasswordPolicyViolation::ContainsEmail)
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_not_enforced() {
+        let policy = PasswordPolicy {
+            min_strength: PasswordStrength::VeryWeak,
+            reject_common_passwords: false,
+            reject_email_local_part: false,
+            ..Default::default()
+        };
+
+        assert!(violations(&policy, "password123").is_empty());
+        assert!(violations(&policy, "john.smith123").is_empty());
+    }
 }
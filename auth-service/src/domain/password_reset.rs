@@ -0,0 +1,84 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+// Emailed to the user and never stored; the stores only keep its hash
+#[derive(Debug, Clone)]
+pub struct PasswordResetToken(Secret<String>);
+
+impl PasswordResetToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        match URL_SAFE_NO_PAD.decode(token.expose_secret()) {
+            Ok(bytes) if bytes.len() == 32 => Ok(Self(token)),
+            _ => Err(eyre!("Invalid password reset token")),
+        }
+    }
+
+    pub fn hash(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.expose_secret().as_bytes()))
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(Secret::new(URL_SAFE_NO_PAD.encode(bytes)))
+    }
+}
+
+impl AsRef<Secret<String>> for PasswordResetToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// Lets the holder of its token set a new password once, before it expires. A user has at
+// most one pending reset; starting another replaces it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordReset {
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PasswordReset {
+    pub fn new(ttl: Duration) -> (Self, PasswordResetToken) {
+        let token = PasswordResetToken::default();
+        let reset = Self {
+            token_hash: token.hash(),
+            expires_at: Utc::now() + ttl,
+        };
+        (reset, token)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_random_and_hash_consistently() {
+        let token = PasswordResetToken::default();
+        let parsed = PasswordResetToken::parse(token.as_ref().clone()).unwrap();
+
+        assert_eq!(parsed.hash(), token.hash());
+        assert_ne!(PasswordResetToken::default().hash(), token.hash());
+        assert!(PasswordResetToken::parse("not-a-token".to_owned().into()).is_err());
+    }
+
+    #[test]
+    fn reset_expires_after_its_ttl() {
+        let (reset, token) = PasswordReset::new(Duration::hours(1));
+
+        assert_eq!(reset.token_hash, token.hash());
+        assert!(!reset.is_expired(Utc::now()));
+        assert!(reset.is_expired(reset.expires_at));
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+// A small zxcvbn-style estimator: the password is split into the cheapest sequence of
+// known patterns (dictionary words, sequences, repeats, keyboard walks, years) and
+// brute-forced characters, and the resulting entropy is bucketed into a 0..=4 score.
+
+lazy_static! {
+    static ref COMMON_PASSWORDS: HashMap<&'static str, usize> =
+        include_str!("../../data/common_passwords.txt")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(index, password)| (password, index + 1))
+            .collect();
+    static ref LONGEST_COMMON_PASSWORD: usize = COMMON_PASSWORDS
+        .keys()
+        .map(|password| password.chars().count())
+        .max()
+        .unwrap_or(0);
+}
+
+const KEYBOARD_ROWS: [&str; 4] = [
+    "`1234567890-=",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PasswordStrength {
+    VeryWeak = 0,
+    Weak = 1,
+    Fair = 2,
+    Strong = 3,
+    VeryStrong = 4,
+}
+
+impl PasswordStrength {
+    pub fn from_score(score: u8) -> Option<Self> {
+        match score {
+            0 => Some(Self::VeryWeak),
+            1 => Some(Self::Weak),
+            2 => Some(Self::Fair),
+            3 => Some(Self::Strong),
+            4 => Some(Self::VeryStrong),
+            _ => None,
+        }
+    }
+
+    // Same guess thresholds as zxcvbn (10^3, 10^6, 10^8, 10^10), expressed in bits
+    fn from_entropy(bits: f64) -> Self {
+        match bits {
+            b if b < 10.0 => Self::VeryWeak,
+            b if b < 20.0 => Self::Weak,
+            b if b < 26.6 => Self::Fair,
+            b if b < 33.3 => Self::Strong,
+            _ => Self::VeryStrong,
+        }
+    }
+}
+
+pub fn is_common_password(password: &str) -> bool {
+    COMMON_PASSWORDS.contains_key(password.to_lowercase().as_str())
+}
+
+// `user_inputs` are treated as the most likely dictionary words (e.g. the email local part)
+pub fn estimate_strength(password: &str, user_inputs: &[&str]) -> PasswordStrength {
+    PasswordStrength::from_entropy(estimate_entropy(password, user_inputs))
+}
+
+fn estimate_entropy(password: &str, user_inputs: &[&str]) -> f64 {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.is_empty() {
+        return 0.0;
+    }
+
+    let user_inputs: Vec<String> = user_inputs
+        .iter()
+        .map(|input| input.to_lowercase())
+        .filter(|input| input.chars().count() >= 3)
+        .collect();
+
+    let mut matches = Vec::new();
+    dictionary_matches(&chars, &user_inputs, &mut matches);
+    sequence_matches(&chars, &mut matches);
+    repeat_matches(&chars, &mut matches);
+    keyboard_matches(&chars, &mut matches);
+    year_matches(&chars, &mut matches);
+
+    let brute_force_bits = cardinality(&chars).log2();
+
+    // best[k] holds the minimal entropy needed to cover the first k characters
+    let mut best = vec![f64::INFINITY; chars.len() + 1];
+    best[0] = 0.0;
+    for end in 1..=chars.len() {
+        best[end] = best[end - 1] + brute_force_bits;
+        for m in matches.iter().filter(|m| m.end == end) {
+            best[end] = best[end].min(best[m.start] + m.bits);
+        }
+    }
+
+    best[chars.len()]
+}
+
+struct PatternMatch {
+    start: usize,
+    end: usize,
+    bits: f64,
+}
+
+fn dictionary_matches(chars: &[char], user_inputs: &[String], matches: &mut Vec<PatternMatch>) {
+    // no longer substring can be a dictionary word; lowercasing never shortens one
+    let longest_word = user_inputs
+        .iter()
+        .map(|input| input.chars().count())
+        .fold(*LONGEST_COMMON_PASSWORD, usize::max);
+
+    for start in 0..chars.len() {
+        for end in (start + 3)..=chars.len().min(start + longest_word) {
+            let token: String = chars[start..end].iter().collect();
+            let lowercase = token.to_lowercase();
+            let unleeted: String = lowercase.chars().map(unleet).collect();
+            let reversed: String = lowercase.chars().rev().collect();
+
+            let candidates = [
+                (lowercase.as_str(), 0.0_f64),
+                (unleeted.as_str(), 1.0),
+                (reversed.as_str(), 1.0),
+            ];
+
+            let rank = candidates
+                .iter()
+                .filter_map(|(word, extra_bits)| {
+                    dictionary_rank(word, user_inputs).map(|rank| (rank, *extra_bits))
+                })
+                .min_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+            if let Some((rank, extra_bits)) = rank {
+                matches.push(PatternMatch {
+                    start,
+                    end,
+                    bits: (rank as f64).log2() + uppercase_bits(&token) + extra_bits,
+                });
+            }
+        }
+    }
+}
+
+fn dictionary_rank(word: &str, user_inputs: &[String]) -> Option<usize> {
+    if user_inputs.iter().any(|input| input == word) {
+        return Some(1);
+    }
+    COMMON_PASSWORDS.get(word).copied()
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '8' => 'b',
+        '(' => 'c',
+        '3' => 'e',
+        '6' | '9' => 'g',
+        '1' | '!' | '|' => 'i',
+        '0' => 'o',
+        '5' | '$' => 's',
+        '7' | '+' => 't',
+        '2' => 'z',
+        c => c,
+    }
+}
+
+fn uppercase_bits(token: &str) -> f64 {
+    let uppercase = token.chars().filter(|c| c.is_uppercase()).count();
+    let lowercase = token.chars().filter(|c| c.is_lowercase()).count();
+
+    match (uppercase, lowercase) {
+        (0, _) | (_, 0) => 0.0,
+        _ if token.chars().next().is_some_and(char::is_uppercase) && uppercase == 1 => 1.0,
+        _ => uppercase.min(lowercase) as f64 + 1.0,
+    }
+}
+
+fn sequence_matches(chars: &[char], matches: &mut Vec<PatternMatch>) {
+    for_each_run(
+        chars,
+        3,
+        |a, b| {
+            let delta = b as i64 - a as i64;
+            delta == 1 || delta == -1
+        },
+        |start, end| {
+            let first = chars[start];
+            let base_bits = if matches!(first, 'a' | 'A' | 'z' | 'Z' | '0' | '1' | '9') {
+                2.0
+            } else if first.is_ascii_digit() {
+                10f64.log2()
+            } else {
+                26f64.log2()
+            };
+            let descending = (chars[start + 1] as i64) < (first as i64);
+            let bits = base_bits + ((end - start) as f64).log2() + f64::from(u8::from(descending));
+            matches.push(PatternMatch { start, end, bits });
+        },
+    );
+}
+
+fn repeat_matches(chars: &[char], matches: &mut Vec<PatternMatch>) {
+    for_each_run(
+        chars,
+        3,
+        |a, b| a == b,
+        |start, end| {
+            let bits = cardinality(&chars[start..start + 1]).log2() + ((end - start) as f64).log2();
+            matches.push(PatternMatch { start, end, bits });
+        },
+    );
+}
+
+fn keyboard_matches(chars: &[char], matches: &mut Vec<PatternMatch>) {
+    let starting_keys: usize = KEYBOARD_ROWS.iter().map(|row| row.len()).sum();
+
+    for_each_run(chars, 4, keyboard_neighbours, |start, end| {
+        let bits = (starting_keys as f64).log2()
+            + ((end - start) as f64).log2()
+            + uppercase_bits(&chars[start..end].iter().collect::<String>());
+        matches.push(PatternMatch { start, end, bits });
+    });
+}
+
+fn keyboard_neighbours(a: char, b: char) -> bool {
+    let (a, b) = (a.to_ascii_lowercase(), b.to_ascii_lowercase());
+    KEYBOARD_ROWS
+        .iter()
+        .any(|row| match (row.find(a), row.find(b)) {
+            (Some(i), Some(j)) => i.abs_diff(j) == 1,
+            _ => false,
+        })
+}
+
+fn year_matches(chars: &[char], matches: &mut Vec<PatternMatch>) {
+    for start in 0..chars.len().saturating_sub(3) {
+        let token: String = chars[start..start + 4].iter().collect();
+        if let Ok(year) = token.parse::<u16>() {
+            if (1900..=2099).contains(&year) {
+                matches.push(PatternMatch {
+                    start,
+                    end: start + 4,
+                    bits: 200f64.log2(),
+                });
+            }
+        }
+    }
+}
+
+// Calls `on_run` for every maximal run (of at least `min_len` chars) where `linked` holds
+// between each pair of neighbouring characters
+fn for_each_run(
+    chars: &[char],
+    min_len: usize,
+    linked: impl Fn(char, char) -> bool,
+    mut on_run: impl FnMut(usize, usize),
+) {
+    let mut start = 0;
+    for end in 1..=chars.len() {
+        if end == chars.len() || !linked(chars[end - 1], chars[end]) {
+            if end - start >= min_len {
+                on_run(start, end);
+            }
+            start = end;
+        }
+    }
+}
+
+fn cardinality(chars: &[char]) -> f64 {
+    let mut cardinality = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        cardinality += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        cardinality += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        cardinality += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        cardinality += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        cardinality += 100;
+    }
+    f64::from(cardinality.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_passwords_are_very_weak() {
+        for password in ["password", "123456", "qwerty", "Password123", "letmein"] {
+            assert_eq!(
+                estimate_strength(password, &[]),
+                PasswordStrength::VeryWeak,
+                "Failed for {password}"
+            );
+        }
+    }
+
+    #[test]
+    fn leetspeak_variants_are_weak() {
+        assert!(estimate_strength("P@55w0rd", &[]) <= PasswordStrength::Weak);
+    }
+
+    #[test]
+    fn patterns_are_weak() {
+        for password in [
+            "abcdefgh",
+            "aaaaaaaaaaaa",
+            "asdfghjkl;",
+            "98765432",
+            "qwertyuiop1",
+        ] {
+            assert!(
+                estimate_strength(password, &[]) <= PasswordStrength::Weak,
+                "Failed for {password}"
+            );
+        }
+    }
+
+    #[test]
+    fn user_inputs_are_treated_as_dictionary_words() {
+        let without_inputs = estimate_entropy("johnsmith", &[]);
+        let with_inputs = estimate_entropy("johnsmith", &["johnsmith"]);
+        assert!(with_inputs < without_inputs);
+    }
+
+    #[test]
+    fn user_inputs_longer_than_any_common_password_are_matched() {
+        let input = "x".repeat(*LONGEST_COMMON_PASSWORD) + "johnsmith";
+        assert!(estimate_entropy(&input, &[&input]) < estimate_entropy(&input, &[]));
+    }
+
+    #[test]
+    fn random_passwords_are_strong() {
+        for password in ["x7Kp#2vLq9!w", "tqbv-rnwe-plmz-hxkd", "Fj3$kL9@mQ2z"] {
+            assert!(
+                estimate_strength(password, &[]) >= PasswordStrength::Strong,
+                "Failed for {password}"
+            );
+        }
+    }
+
+    #[test]
+    fn is_common_password_ignores_case() {
+        assert!(is_common_password("PassWord"));
+        assert!(!is_common_password("x7Kp#2vLq9!w"));
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{Permission, UserId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PersonalAccessTokenId(Uuid);
+
+impl PersonalAccessTokenId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid personal access token id"))
+    }
+}
+
+impl Default for PersonalAccessTokenId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for PersonalAccessTokenId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for PersonalAccessTokenId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for PersonalAccessTokenId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Shown to its owner once and never stored; the stores only keep its hash. The prefix tells
+// it apart from the JWTs issued at login
+#[derive(Debug, Clone)]
+pub struct PersonalAccessTokenSecret(Secret<String>);
+
+impl PersonalAccessTokenSecret {
+    pub const PREFIX: &'static str = "pat_";
+
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        let encoded = token
+            .expose_secret()
+            .strip_prefix(Self::PREFIX)
+            .ok_or(eyre!("Invalid personal access token"))?;
+
+        match URL_SAFE_NO_PAD.decode(encoded) {
+            Ok(bytes) if bytes.len() == 32 => Ok(Self(token)),
+            _ => Err(eyre!("Invalid personal access token")),
+        }
+    }
+
+    pub fn is_personal_access_token(token: &Secret<String>) -> bool {
+        token.expose_secret().starts_with(Self::PREFIX)
+    }
+
+    pub fn hash(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.expose_secret().as_bytes()))
+    }
+}
+
+impl Default for PersonalAccessTokenSecret {
+    fn default() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(Secret::new(format!(
+            "{}{}",
+            Self::PREFIX,
+            URL_SAFE_NO_PAD.encode(bytes)
+        )))
+    }
+}
+
+impl AsRef<Secret<String>> for PersonalAccessTokenSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// A long-lived credential for scripts. It grants at most its scopes, and only those its
+// owner still holds when it is used
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersonalAccessToken {
+    pub id: PersonalAccessTokenId,
+    pub user_id: UserId,
+    pub name: String,
+    pub scopes: Vec<Permission>,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl PersonalAccessToken {
+    pub fn new(
+        user_id: UserId,
+        name: String,
+        scopes: Vec<Permission>,
+        ttl: Duration,
+    ) -> (Self, PersonalAccessTokenSecret) {
+        let secret = PersonalAccessTokenSecret::default();
+        let created_at = Utc::now();
+        let token = Self {
+            id: PersonalAccessTokenId::default(),
+            user_id,
+            name,
+            scopes,
+            token_hash: secret.hash(),
+            created_at,
+            expires_at: created_at + ttl,
+            last_used_at: None,
+        };
+        (token, secret)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    // Space-separated, like the `scope` claim
+    pub fn scope(&self) -> String {
+        self.scopes
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    pub fn parse_scope(scope: &str) -> Result<Vec<Permission>> {
+        scope.split_whitespace().map(Permission::parse).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_prefixed_random_and_hash_consistently() {
+        let secret = PersonalAccessTokenSecret::default();
+        assert!(PersonalAccessTokenSecret::is_personal_access_token(
+            secret.as_ref()
+        ));
+
+        let parsed = PersonalAccessTokenSecret::parse(secret.as_ref().clone()).unwrap();
+        assert_eq!(parsed.hash(), secret.hash());
+        assert_ne!(PersonalAccessTokenSecret::default().hash(), secret.hash());
+
+        let unprefixed = secret.as_ref().expose_secret()[4..].to_owned();
+        assert!(PersonalAccessTokenSecret::parse(unprefixed.into()).is_err());
+        assert!(PersonalAccessTokenSecret::parse("pat_short".to_owned().into()).is_err());
+    }
+
+    #[test]
+    fn token_expires_after_its_ttl_and_keeps_its_scope() {
+        let scopes = PersonalAccessToken::parse_scope("reports:read  reports:write").unwrap();
+        let (token, secret) = PersonalAccessToken::new(
+            UserId::default(),
+            "ci".to_owned(),
+            scopes,
+            Duration::days(1),
+        );
+
+        assert_eq!(token.token_hash, secret.hash());
+        assert_eq!(token.scope(), "reports:read reports:write");
+        assert!(!token.is_expired(token.created_at));
+        assert!(token.is_expired(token.created_at + Duration::days(1)));
+        assert!(PersonalAccessToken::parse_scope("Reports").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+use color_eyre::eyre::{eyre, Result};
+
+// Role and permission names end up in the space-separated `scope` claim, so they are kept to
+// a conservative character set
+fn parse_name(kind: &str, name: &str) -> Result<String> {
+    let valid = (1..=MAX_NAME_LENGTH).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_.:-".contains(c));
+
+    if valid {
+        Ok(name.to_owned())
+    } else {
+        Err(eyre!("Invalid {kind} name: {name}"))
+    }
+}
+
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RoleName(String);
+
+impl RoleName {
+    // Built in; holders may use the `/admin` API
+    pub const ADMIN: &'static str = "admin";
+
+    pub fn parse(name: &str) -> Result<Self> {
+        parse_name("role", name).map(Self)
+    }
+
+    pub fn admin() -> Self {
+        Self(Self::ADMIN.to_owned())
+    }
+}
+
+impl AsRef<str> for RoleName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RoleName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Permission(String);
+
+impl Permission {
+    pub fn parse(name: &str) -> Result<Self> {
+        parse_name("permission", name).map(Self)
+    }
+}
+
+impl AsRef<str> for Permission {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleDefinition {
+    pub name: RoleName,
+    pub description: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionDefinition {
+    pub name: Permission,
+    pub description: String,
+}
+
+// What a user's token is issued with: their roles, and every permission those roles grant
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Grants {
+    pub roles: Vec<RoleName>,
+    pub permissions: Vec<Permission>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names_are_accepted() {
+        for name in ["admin", "billing:read", "reports.export", "team-lead_2"] {
+            assert_eq!(RoleName::parse(name).unwrap().as_ref(), name);
+            assert_eq!(Permission::parse(name).unwrap().as_ref(), name);
+        }
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        for name in ["", "Admin", "read write", "a/b", &"a".repeat(65)] {
+            assert!(RoleName::parse(name).is_err());
+            assert!(Permission::parse(name).is_err());
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{Email, OrgId, UserId};
+
+// Whether anyone can sign up, or only the holders of an invitation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignupMode {
+    #[default]
+    Open,
+    InviteOnly,
+}
+
+impl SignupMode {
+    pub fn parse(mode: &str) -> Result<Self> {
+        match mode {
+            "open" => Ok(Self::Open),
+            "invite" => Ok(Self::InviteOnly),
+            mode => Err(eyre!("Invalid signup mode: {mode}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignupInvitationId(Uuid);
+
+impl SignupInvitationId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid invitation id"))
+    }
+}
+
+impl Default for SignupInvitationId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for SignupInvitationId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for SignupInvitationId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for SignupInvitationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Emailed to the invitee and never stored; the stores only keep its hash
+#[derive(Debug, Clone)]
+pub struct SignupToken(Secret<String>);
+
+impl SignupToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        match URL_SAFE_NO_PAD.decode(token.expose_secret()) {
+            Ok(bytes) if bytes.len() == 32 => Ok(Self(token)),
+            _ => Err(eyre!("Invalid invitation token")),
+        }
+    }
+
+    pub fn hash(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.expose_secret().as_bytes()))
+    }
+}
+
+impl Default for SignupToken {
+    fn default() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(Secret::new(URL_SAFE_NO_PAD.encode(bytes)))
+    }
+}
+
+impl AsRef<Secret<String>> for SignupToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// Lets the holder of its token sign up once, with the invited email, before it expires.
+// Invitations made on behalf of an organization also make the new user one of its members
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignupInvitation {
+    pub id: SignupInvitationId,
+    pub email: Email,
+    pub org_id: Option<OrgId>,
+    pub token_hash: String,
+    pub invited_by: UserId,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SignupInvitation {
+    pub fn new(
+        email: Email,
+        org_id: Option<OrgId>,
+        invited_by: UserId,
+        ttl: Duration,
+    ) -> (Self, SignupToken) {
+        let token = SignupToken::default();
+        let created_at = Utc::now();
+        let invitation = Self {
+            id: SignupInvitationId::default(),
+            email,
+            org_id,
+            token_hash: token.hash(),
+            invited_by,
+            created_at,
+            expires_at: created_at + ttl,
+        };
+        (invitation, token)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_random_and_hash_consistently() {
+        let token = SignupToken::default();
+        let parsed = SignupToken::parse(token.as_ref().clone()).unwrap();
+
+        assert_eq!(parsed.hash(), token.hash());
+        assert_ne!(SignupToken::default().hash(), token.hash());
+        assert_ne!(token.hash(), *token.as_ref().expose_secret());
+        assert!(SignupToken::parse("not-a-token".to_owned().into()).is_err());
+    }
+
+    #[test]
+    fn invitation_expires_after_its_ttl() {
+        let email = Email::parse("new@example.com".to_owned().into()).unwrap();
+        let (invitation, token) =
+            SignupInvitation::new(email, None, UserId::default(), Duration::hours(1));
+
+        assert_eq!(invitation.token_hash, token.hash());
+        assert!(!invitation.is_expired(invitation.created_at));
+        assert!(invitation.is_expired(invitation.created_at + Duration::hours(1)));
+    }
+
+    #[test]
+    fn mode_parses_open_and_invite() {
+        assert_eq!(SignupMode::parse("open").unwrap(), SignupMode::Open);
+        assert_eq!(SignupMode::parse("invite").unwrap(), SignupMode::InviteOnly);
+        assert!(SignupMode::parse("closed").is_err());
+    }
+}
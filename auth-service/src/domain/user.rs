@@ -1,18 +1,94 @@
-use crate::domain::{email::Email, password::Password};
+use chrono::{DateTime, Utc};
+
+use crate::domain::{
+    account_status::AccountStatus, email::Email, error::AuthAPIError, password::Password,
+    user_id::UserId,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
+    pub(crate) id: UserId,
     pub(crate) email: Email,
     pub(crate) password: Password,
     pub(crate) requires_2fa: bool,
+    pub(crate) status: AccountStatus,
+    // Set by an admin; the user can't log in until the password is changed
+    pub(crate) password_reset_required: bool,
+    // Tokens issued up to this point are no longer accepted
+    pub(crate) sessions_revoked_at: Option<DateTime<Utc>>,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) updated_at: DateTime<Utc>,
+    pub(crate) last_login_at: Option<DateTime<Utc>>,
 }
 
 impl User {
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
+        let now = Utc::now();
+
         Self {
+            id: UserId::default(),
             email,
             password,
             requires_2fa,
+            status: AccountStatus::Active,
+            password_reset_required: false,
+            sessions_revoked_at: None,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+        }
+    }
+
+    // Checked once the user has proven who they are, before a token is issued
+    pub fn ensure_can_log_in(&self) -> Result<(), AuthAPIError> {
+        self.status.ensure_active()?;
+        if self.password_reset_required {
+            return Err(AuthAPIError::PasswordResetRequired);
         }
+        Ok(())
+    }
+
+    // `iat` only has second precision, so a token issued in the same second as the revocation
+    // is treated as revoked too
+    pub fn is_revoked(&self, issued_at: i64) -> bool {
+        self.sessions_revoked_at
+            .is_some_and(|revoked_at| issued_at <= revoked_at.timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user() -> User {
+        User::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            Password::parse("password123".to_owned().into()).unwrap(),
+            false,
+        )
+    }
+
+    #[test]
+    fn forced_password_reset_blocks_login() {
+        let mut user = user();
+        assert!(user.ensure_can_log_in().is_ok());
+
+        user.password_reset_required = true;
+        assert!(matches!(
+            user.ensure_can_log_in(),
+            Err(AuthAPIError::PasswordResetRequired)
+        ));
+    }
+
+    #[test]
+    fn tokens_issued_before_revocation_are_revoked() {
+        let mut user = user();
+        let now = Utc::now();
+        assert!(!user.is_revoked(now.timestamp()));
+
+        user.sessions_revoked_at = Some(now);
+        assert!(user.is_revoked(now.timestamp() - 60));
+        assert!(user.is_revoked(now.timestamp()));
+        assert!(!user.is_revoked(now.timestamp() + 1));
     }
 }
@@ -0,0 +1,56 @@
+use std::fmt;
+
+use color_eyre::eyre::{eyre, Result};
+use uuid::Uuid;
+
+// Stable identifier of a user, unlike their email; used as the JWT `sub`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserId(Uuid);
+
+impl UserId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid user id"))
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for UserId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for UserId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_round_trips_through_its_string_form() {
+        let id = UserId::default();
+        assert_eq!(UserId::parse(&id.to_string()).unwrap(), id);
+    }
+
+    #[test]
+    fn invalid_id_is_rejected() {
+        assert!(UserId::parse("test@example.com").is_err());
+    }
+}
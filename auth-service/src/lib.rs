@@ -1,3 +1,6 @@
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("at least one of the `postgres` and `sqlite` features must be enabled");
+
 pub mod app_state;
 pub mod domain;
 pub mod routes;
@@ -8,20 +11,28 @@ use std::error::Error;
 
 use axum::{
     http::{Method, StatusCode},
+    middleware,
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
     serve::Serve,
     Json, Router,
 };
-use redis::{Client, RedisResult};
+use redis::RedisResult;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "postgres")]
 use sqlx::{postgres::PgPoolOptions, PgPool};
+#[cfg(feature = "sqlite")]
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    SqlitePool,
+};
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
+    domain::{AuthAPIError, EmailDomainViolation, PasswordPolicyViolation},
+    services::redis_connection::{RedisConfig, RedisConnection},
     utils::tracing::{make_span_with_request_id, on_request, on_response},
 };
 
@@ -40,19 +51,149 @@ impl Application {
             "http://161.35.0.230:8000".parse()?,
         ];
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
             // Allow cookies to be included in requests
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
+        let admin = Router::new()
+            .route("/users", get(routes::list_users))
+            .route("/users/:id", get(routes::get_user))
+            .route("/users/:id/disable", post(routes::disable_user))
+            .route("/users/:id/enable", post(routes::enable_user))
+            .route(
+                "/users/:id/force-password-reset",
+                post(routes::force_password_reset),
+            )
+            .route("/users/:id/reset-2fa", post(routes::reset_2fa))
+            .route("/users/:id/revoke-sessions", post(routes::revoke_sessions))
+            .route(
+                "/users/:id/roles",
+                get(routes::get_user_grants).post(routes::assign_role),
+            )
+            .route("/users/:id/roles/:role", delete(routes::unassign_role))
+            .route("/roles", get(routes::list_roles).post(routes::create_role))
+            .route("/roles/:role", delete(routes::delete_role))
+            .route("/roles/:role/permissions", post(routes::grant_permission))
+            .route(
+                "/roles/:role/permissions/:permission",
+                delete(routes::revoke_permission),
+            )
+            .route(
+                "/permissions",
+                get(routes::list_permissions).post(routes::create_permission),
+            )
+            .route(
+                "/permissions/:permission",
+                delete(routes::delete_permission),
+            )
+            .route(
+                "/signup-invitations",
+                get(routes::list_signup_invitations).post(routes::create_signup_invitation),
+            )
+            .route(
+                "/signup-invitations/:id",
+                delete(routes::revoke_signup_invitation),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                routes::require_admin,
+            ));
+
+        // Everything here acts on behalf of the logged-in caller
+        let orgs = Router::new()
+            .route("/orgs", get(routes::list_orgs).post(routes::create_org))
+            .route("/orgs/switch", post(routes::switch_org))
+            .route("/orgs/:org_id/members", get(routes::list_members))
+            .route(
+                "/orgs/:org_id/members/:user_id",
+                post(routes::set_member_role).delete(routes::remove_member),
+            )
+            .route(
+                "/orgs/:org_id/invitations",
+                get(routes::list_invitations).post(routes::create_invitation),
+            )
+            .route(
+                "/orgs/:org_id/invitations/:invitation_id",
+                delete(routes::revoke_invitation),
+            )
+            .route(
+                "/orgs/:org_id/signup-invitations",
+                get(routes::list_org_signup_invitations).post(routes::create_org_signup_invitation),
+            )
+            .route(
+                "/orgs/:org_id/signup-invitations/:id",
+                delete(routes::revoke_org_signup_invitation),
+            )
+            .route("/invitations", get(routes::list_my_invitations))
+            .route("/invitations/:id/accept", post(routes::accept_invitation))
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                routes::require_auth,
+            ));
+
+        // Minting a token needs a recent authentication, like the account's sensitive operations
+        let personal_access_tokens = Router::new()
+            .route(
+                "/personal-access-tokens",
+                post(routes::create_personal_access_token),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                routes::require_recent_auth,
+            ))
+            .route(
+                "/personal-access-tokens",
+                get(routes::list_personal_access_tokens),
+            )
+            .route(
+                "/personal-access-tokens/:id",
+                delete(routes::revoke_personal_access_token),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                routes::require_auth,
+            ));
+
+        // The caller's own account; the sensitive operations, above the first layer, also need
+        // a recent authentication
+        let account = Router::new()
+            .route("/account", delete(routes::delete_account))
+            .route("/account/password", post(routes::change_password))
+            .route("/account/2fa", delete(routes::disable_2fa))
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                routes::require_recent_auth,
+            ))
+            .route("/account/reauthenticate", post(routes::reauthenticate))
+            .route(
+                "/account/reauthenticate/verify-2fa",
+                post(routes::verify_reauthentication_2fa),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                routes::require_auth,
+            ));
+
         let router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
             .route("/signup", post(routes::signup))
             .route("/login", post(routes::login))
             .route("/verify-2fa", post(routes::verify_2fa))
             .route("/logout", post(routes::logout))
+            .route("/password-reset", post(routes::request_password_reset))
+            .route("/password-reset/confirm", post(routes::reset_password))
             .route("/verify-token", post(routes::verify_token))
+            .route(
+                "/verify-token/permission",
+                post(routes::verify_token_permission),
+            )
+            .route("/introspect", post(routes::introspect_token))
+            .nest("/admin", admin)
+            .merge(orgs)
+            .merge(personal_access_tokens)
+            .merge(account)
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -78,17 +219,61 @@ impl Application {
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasons: Vec<ErrorReason>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ErrorReason {
+    pub code: String,
+    pub message: String,
+}
+
+impl From<&PasswordPolicyViolation> for ErrorReason {
+    fn from(violation: &PasswordPolicyViolation) -> Self {
+        Self {
+            code: violation.code().to_owned(),
+            message: violation.to_string(),
+        }
+    }
+}
+
+impl From<&EmailDomainViolation> for ErrorReason {
+    fn from(violation: &EmailDomainViolation) -> Self {
+        Self {
+            code: violation.code().to_owned(),
+            message: violation.to_string(),
+        }
+    }
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let reasons = match &self {
+            AuthAPIError::WeakPassword(violations) => {
+                violations.iter().map(ErrorReason::from).collect()
+            }
+            AuthAPIError::EmailDomainRejected(violation) => vec![ErrorReason::from(violation)],
+            _ => Vec::new(),
+        };
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
+            AuthAPIError::WeakPassword(_) => (
+                StatusCode::BAD_REQUEST,
+                "Password does not meet the password policy",
+            ),
+            AuthAPIError::EmailDomainRejected(_) => {
+                (StatusCode::BAD_REQUEST, "Email domain is not accepted")
+            }
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
+            AuthAPIError::ServiceUnavailable => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Service temporarily unavailable",
+            ),
             AuthAPIError::IncorrectCredentials => {
                 (StatusCode::UNAUTHORIZED, "Incorrect credentials")
             }
@@ -97,22 +282,94 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::TokenAlreadyInvalidated => {
                 (StatusCode::BAD_REQUEST, "Token already invalidated")
             }
+            AuthAPIError::AccountPending => (StatusCode::FORBIDDEN, "Account pending activation"),
+            AuthAPIError::AccountLocked => (StatusCode::LOCKED, "Account locked"),
+            AuthAPIError::AccountDisabled => (StatusCode::FORBIDDEN, "Account disabled"),
+            AuthAPIError::PasswordResetRequired => {
+                (StatusCode::FORBIDDEN, "Password reset required")
+            }
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::MissingPermission => (StatusCode::FORBIDDEN, "Missing permission"),
+            AuthAPIError::InvalidRoleOrPermission => {
+                (StatusCode::BAD_REQUEST, "Invalid role or permission name")
+            }
+            AuthAPIError::RoleAlreadyExists => (StatusCode::CONFLICT, "Role already exists"),
+            AuthAPIError::RoleNotFound => (StatusCode::NOT_FOUND, "Role not found"),
+            AuthAPIError::PermissionAlreadyExists => {
+                (StatusCode::CONFLICT, "Permission already exists")
+            }
+            AuthAPIError::PermissionNotFound => (StatusCode::NOT_FOUND, "Permission not found"),
+            AuthAPIError::NotOrgMember => {
+                (StatusCode::FORBIDDEN, "Not a member of the organization")
+            }
+            AuthAPIError::InvalidOrgInput => {
+                (StatusCode::BAD_REQUEST, "Invalid organization name or role")
+            }
+            AuthAPIError::LastOrgOwner => (
+                StatusCode::CONFLICT,
+                "The organization needs at least one owner",
+            ),
+            AuthAPIError::InvitationAlreadyExists => {
+                (StatusCode::CONFLICT, "Invitation already exists")
+            }
+            AuthAPIError::InvitationNotFound => (StatusCode::NOT_FOUND, "Invitation not found"),
+            AuthAPIError::InvitationRequired => {
+                (StatusCode::FORBIDDEN, "An invitation is required to sign up")
+            }
+            AuthAPIError::InvalidInvitation => {
+                (StatusCode::BAD_REQUEST, "Invalid or expired invitation")
+            }
+            AuthAPIError::InvalidPasswordReset => (
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired password reset token",
+            ),
+            AuthAPIError::InvalidPersonalAccessTokenInput => (
+                StatusCode::BAD_REQUEST,
+                "Invalid personal access token name, scopes or expiry",
+            ),
+            AuthAPIError::PersonalAccessTokenNotFound => {
+                (StatusCode::NOT_FOUND, "Personal access token not found")
+            }
+            AuthAPIError::InvalidAudience => (StatusCode::BAD_REQUEST, "Unknown token audience"),
+            AuthAPIError::MissingAudience => (StatusCode::BAD_REQUEST, "Missing token audience"),
+            AuthAPIError::ReauthenticationRequired => {
+                (StatusCode::UNAUTHORIZED, "Reauthentication required")
+            }
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
+            reasons,
         });
 
         (status, body).into_response()
     }
 }
 
+#[cfg(feature = "postgres")]
 pub async fn get_postgres_pool(url: &Secret<String>) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new().max_connections(5).connect(url.expose_secret()).await
 }
 
-pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
-    let redis_url = format!("redis://{}/", redis_hostname);
-    redis::Client::open(redis_url)
+#[cfg(feature = "sqlite")]
+pub async fn get_sqlite_pool(url: &Secret<String>) -> Result<SqlitePool, sqlx::Error> {
+    let options = url
+        .expose_secret()
+        .parse::<SqliteConnectOptions>()?
+        .create_if_missing(true)
+        // lets readers proceed while a write is in progress
+        .journal_mode(SqliteJournalMode::Wal);
+
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+}
+
+// A multiplexed async connection that is cheap to clone and reconnects on its own after
+// the connection to Redis is lost, or to the new master after a failover
+pub async fn get_redis_connection(config: &RedisConfig) -> RedisResult<RedisConnection> {
+    config.connect().await
 }
 
 fn log_error_chain(e: &(dyn Error + 'static)) {
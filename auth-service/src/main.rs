@@ -1,43 +1,125 @@
 use std::sync::Arc;
 
-use auth_service::app_state::AppState;
-use auth_service::domain::Email;
+use auth_service::app_state::{
+    AppState, BannedTokenStoreType, OrgStoreType, PersonalAccessTokenStoreType, RoleStoreType,
+    SignupInvitationStoreType, TwoFACodeStoreType, UserStoreType,
+};
+use auth_service::domain::{
+    DomainPattern, Email, EmailDomainPolicy, PasswordPolicy, PasswordStrength, SignupMode,
+};
+#[cfg(feature = "postgres")]
+use auth_service::get_postgres_pool;
+#[cfg(feature = "sqlite")]
+use auth_service::get_sqlite_pool;
 use auth_service::services::data_stores::{
-    PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore,
+    CachedBannedTokenStore, RedisBannedTokenStore, RedisTwoFACodeStore,
 };
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::{
+    PostgresBannedTokenStore, PostgresOrgStore, PostgresPersonalAccessTokenStore,
+    PostgresRoleStore, PostgresSignupInvitationStore, PostgresTwoFACodeStore, PostgresUserStore,
+};
+#[cfg(feature = "sqlite")]
+use auth_service::services::data_stores::{
+    SqliteOrgStore, SqlitePersonalAccessTokenStore, SqliteRoleStore, SqliteSignupInvitationStore,
+    SqliteUserStore,
+};
+use auth_service::services::hashing_pool::HashingPool;
+use auth_service::services::hibp_breached_password_checker::HibpBreachedPasswordChecker;
+use auth_service::services::password_hasher::{HashingParams, PasswordHasher};
 use auth_service::services::postmark_email_client::PostmarkEmailClient;
+use auth_service::services::redis_connection::{RedisConfig, RedisConnection, RedisTopology};
 use auth_service::services::resend_email_client::ResendEmailClient;
+use auth_service::utils::auth::TokenSettings;
+use auth_service::utils::auth_token::TokenSources;
 use auth_service::utils::constants::{
-    prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, RESEND_AUTH_TOKEN,
+    prod, ARGON2_MEMORY_COST, ARGON2_PARALLELISM, ARGON2_TIME_COST, DATABASE_URL,
+    DISPOSABLE_EMAIL_DOMAINS_PATH, EMAIL_ALLOWED_DOMAINS, EMAIL_BLOCKED_DOMAINS,
+    EMAIL_REJECT_DISPOSABLE, HASHING_QUEUE_DEPTH, HASHING_WORKERS, HIBP_DATASET_PATH,
+    JWT_AUDIENCES, JWT_ISSUER, JWT_LEEWAY_SECONDS, PASSWORD_MAX_LENGTH, PASSWORD_MIN_LENGTH,
+    PASSWORD_MIN_STRENGTH, PASSWORD_REJECT_COMMON, PASSWORD_REJECT_EMAIL,
+    PASSWORD_RESET_TTL_MINUTES, PERSONAL_ACCESS_TOKEN_MAX_TTL_DAYS, POSTMARK_AUTH_TOKEN,
+    REDIS_MODE, REDIS_NAMESPACE, REDIS_SENTINEL_MASTER, REDIS_URL, RESEND_AUTH_TOKEN,
+    SIGNUP_INVITATION_TTL_HOURS, SIGNUP_MODE, STEP_UP_MAX_AGE_SECONDS, TOKEN_SOURCES,
+    TOKEN_STORE_BACKEND,
 };
 use auth_service::utils::tracing::init_tracing;
-use auth_service::{get_postgres_pool, get_redis_client, Application};
+use auth_service::{get_redis_connection, Application};
 use reqwest::Client;
+use secrecy::ExposeSecret;
+#[cfg(feature = "postgres")]
 use sqlx::PgPool;
-use tokio::sync::RwLock;
+#[cfg(feature = "sqlite")]
+use sqlx::SqlitePool;
 
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing");
 
-    let pg_pool = configure_postgresql().await;
-    let redis_connection = Arc::new(RwLock::new(configure_redis()));
-
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
-        redis_connection.clone(),
-    )));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+    let token_store_backend = configure_token_store_backend();
+    let database = configure_database().await;
+    let (user_store, role_store, org_store, signup_invitation_store, personal_access_token_store) =
+        configure_user_stores(&database).await;
+    let (banned_token_store, two_fa_code_store) =
+        configure_token_stores(token_store_backend, &database).await;
     let email_client = Arc::new(configure_resend_email_client());
 
-    let app_state = AppState::new(
+    let mut app_state = AppState::new(
         user_store,
+        role_store,
+        org_store,
+        signup_invitation_store,
+        personal_access_token_store,
         banned_token_store,
         two_fa_code_store,
         email_client,
+    )
+    .with_password_policy(configure_password_policy())
+    .with_email_domain_policy(configure_email_domain_policy())
+    .with_token_settings(configure_token_settings())
+    .with_signup_mode(
+        SIGNUP_MODE
+            .as_deref()
+            .map(|mode| SignupMode::parse(mode).expect("Invalid SIGNUP_MODE"))
+            .unwrap_or_default(),
     );
 
+    if let Some(hours) = *SIGNUP_INVITATION_TTL_HOURS {
+        app_state = app_state.with_signup_invitation_ttl(
+            chrono::Duration::try_hours(hours).expect("Invalid SIGNUP_INVITATION_TTL_HOURS"),
+        );
+    }
+
+    if let Some(minutes) = *PASSWORD_RESET_TTL_MINUTES {
+        app_state = app_state.with_password_reset_ttl(
+            chrono::Duration::try_minutes(minutes).expect("Invalid PASSWORD_RESET_TTL_MINUTES"),
+        );
+    }
+
+    if let Some(sources) = TOKEN_SOURCES.as_deref() {
+        app_state = app_state
+            .with_token_sources(TokenSources::parse(sources).expect("Invalid TOKEN_SOURCES"));
+    }
+
+    if let Some(seconds) = *STEP_UP_MAX_AGE_SECONDS {
+        app_state = app_state.with_step_up_max_age(
+            chrono::Duration::try_seconds(seconds).expect("Invalid STEP_UP_MAX_AGE_SECONDS"),
+        );
+    }
+
+    if let Some(days) = *PERSONAL_ACCESS_TOKEN_MAX_TTL_DAYS {
+        app_state = app_state.with_personal_access_token_max_ttl(
+            chrono::Duration::try_days(days).expect("Invalid PERSONAL_ACCESS_TOKEN_MAX_TTL_DAYS"),
+        );
+    }
+
+    if let Some(dataset_path) = HIBP_DATASET_PATH.as_ref() {
+        app_state = app_state.with_breached_password_checker(Arc::new(
+            HibpBreachedPasswordChecker::new(dataset_path),
+        ));
+    }
+
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
@@ -45,6 +127,190 @@ async fn main() {
     app.run().await.expect("Failed to run app");
 }
 
+// The database DATABASE_URL points to, with its migrations run; the backend is picked by the
+// URL scheme, among those enabled as cargo features
+enum Database {
+    #[cfg(feature = "postgres")]
+    Postgres(PgPool),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqlitePool),
+}
+
+impl Database {
+    #[cfg(feature = "postgres")]
+    fn postgres(&self) -> Option<&PgPool> {
+        match self {
+            Self::Postgres(pg_pool) => Some(pg_pool),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => None,
+        }
+    }
+}
+
+fn database_scheme() -> &'static str {
+    DATABASE_URL
+        .expose_secret()
+        .split(':')
+        .next()
+        .unwrap_or_default()
+}
+
+async fn configure_database() -> Database {
+    match database_scheme() {
+        #[cfg(feature = "postgres")]
+        "postgres" | "postgresql" => Database::Postgres(configure_postgresql().await),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Database::Sqlite(configure_sqlite().await),
+        scheme => panic!("Unsupported DATABASE_URL scheme: {scheme}"),
+    }
+}
+
+async fn configure_user_stores(
+    database: &Database,
+) -> (
+    UserStoreType,
+    RoleStoreType,
+    OrgStoreType,
+    SignupInvitationStoreType,
+    PersonalAccessTokenStoreType,
+) {
+    let hasher = configure_password_hasher();
+
+    match database {
+        #[cfg(feature = "postgres")]
+        Database::Postgres(pg_pool) => {
+            let user_store = PostgresUserStore::new(pg_pool.clone()).with_password_hasher(hasher);
+            log_recanonicalized_emails(
+                user_store
+                    .recanonicalize_emails()
+                    .await
+                    .expect("Failed to recanonicalize stored emails"),
+            );
+            (
+                Arc::new(user_store),
+                Arc::new(PostgresRoleStore::new(pg_pool.clone())),
+                Arc::new(PostgresOrgStore::new(pg_pool.clone())),
+                Arc::new(PostgresSignupInvitationStore::new(pg_pool.clone())),
+                Arc::new(PostgresPersonalAccessTokenStore::new(pg_pool.clone())),
+            )
+        }
+        #[cfg(feature = "sqlite")]
+        Database::Sqlite(sqlite_pool) => {
+            let user_store = SqliteUserStore::new(sqlite_pool.clone()).with_password_hasher(hasher);
+            log_recanonicalized_emails(
+                user_store
+                    .recanonicalize_emails()
+                    .await
+                    .expect("Failed to recanonicalize stored emails"),
+            );
+            (
+                Arc::new(user_store),
+                Arc::new(SqliteRoleStore::new(sqlite_pool.clone())),
+                Arc::new(SqliteOrgStore::new(sqlite_pool.clone())),
+                Arc::new(SqliteSignupInvitationStore::new(sqlite_pool.clone())),
+                Arc::new(SqlitePersonalAccessTokenStore::new(sqlite_pool.clone())),
+            )
+        }
+    }
+}
+
+fn log_recanonicalized_emails(recanonicalized: usize) {
+    if recanonicalized > 0 {
+        tracing::info!(recanonicalized, "Recanonicalized stored emails");
+    }
+}
+
+// Where banned tokens and 2FA codes live: Redis unless TOKEN_STORE_BACKEND says otherwise.
+// Read before connecting to anything, so that a misconfiguration fails right away
+enum TokenStoreBackend {
+    Redis,
+    #[cfg(feature = "postgres")]
+    Postgres,
+}
+
+fn configure_token_store_backend() -> TokenStoreBackend {
+    match TOKEN_STORE_BACKEND.as_deref() {
+        None | Some("redis") => TokenStoreBackend::Redis,
+        #[cfg(feature = "postgres")]
+        Some("postgres") if matches!(database_scheme(), "postgres" | "postgresql") => {
+            TokenStoreBackend::Postgres
+        }
+        #[cfg(feature = "postgres")]
+        Some("postgres") => {
+            panic!("TOKEN_STORE_BACKEND=postgres needs DATABASE_URL to point to PostgreSQL")
+        }
+        Some(backend) => panic!("Unsupported TOKEN_STORE_BACKEND: {backend}"),
+    }
+}
+
+async fn configure_token_stores(
+    backend: TokenStoreBackend,
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))] database: &Database,
+) -> (BannedTokenStoreType, TwoFACodeStoreType) {
+    match backend {
+        TokenStoreBackend::Redis => {
+            let redis_config = configure_redis();
+            let redis_connection = connect_redis(&redis_config).await;
+            let redis_banned_token_store = RedisBannedTokenStore::new(redis_connection.clone());
+            let migrated = redis_banned_token_store
+                .migrate_legacy_bans()
+                .await
+                .expect("Failed to migrate legacy banned tokens");
+            if migrated > 0 {
+                tracing::info!(migrated, "Migrated legacy banned tokens");
+            }
+
+            let banned_token_store =
+                CachedBannedTokenStore::new(Arc::new(redis_banned_token_store.clone()));
+            banned_token_store.spawn_sync(redis_banned_token_store, redis_config);
+            banned_token_store.spawn_eviction(prod::EXPIRED_ENTRIES_PURGE_INTERVAL);
+
+            (
+                Arc::new(banned_token_store),
+                Arc::new(RedisTwoFACodeStore::new(redis_connection)),
+            )
+        }
+        #[cfg(feature = "postgres")]
+        TokenStoreBackend::Postgres => {
+            let pg_pool = database
+                .postgres()
+                .expect("TOKEN_STORE_BACKEND=postgres needs DATABASE_URL to point to PostgreSQL")
+                .clone();
+            let banned_token_store = Arc::new(PostgresBannedTokenStore::new(pg_pool.clone()));
+            let two_fa_code_store = Arc::new(PostgresTwoFACodeStore::new(pg_pool));
+
+            spawn_expired_rows_purge(banned_token_store.clone(), two_fa_code_store.clone());
+
+            (banned_token_store, two_fa_code_store)
+        }
+    }
+}
+
+// Unlike Redis keys, expired rows stay around until deleted
+#[cfg(feature = "postgres")]
+fn spawn_expired_rows_purge(
+    banned_token_store: Arc<PostgresBannedTokenStore>,
+    two_fa_code_store: Arc<PostgresTwoFACodeStore>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(prod::EXPIRED_ENTRIES_PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match banned_token_store.purge_expired().await {
+                Ok(purged) => tracing::debug!(purged, "Purged expired banned tokens"),
+                Err(e) => tracing::error!("Failed to purge expired banned tokens: {:?}", e),
+            }
+            match two_fa_code_store.purge_expired().await {
+                Ok(purged) => tracing::debug!(purged, "Purged expired 2FA codes"),
+                Err(e) => tracing::error!("Failed to purge expired 2FA codes: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(feature = "postgres")]
 async fn configure_postgresql() -> PgPool {
     // Create a new database connection pool
     let pg_pool = get_postgres_pool(&DATABASE_URL)
@@ -60,10 +326,110 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+#[cfg(feature = "sqlite")]
+async fn configure_sqlite() -> SqlitePool {
+    let sqlite_pool = get_sqlite_pool(&DATABASE_URL)
+        .await
+        .expect("Failed to create SQLite connection pool!");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&sqlite_pool)
+        .await
+        .expect("Failed to run migrations");
+
+    sqlite_pool
+}
+
+fn configure_password_hasher() -> PasswordHasher {
+    let defaults = HashingParams::default();
+
+    PasswordHasher::new(HashingParams {
+        memory_cost: ARGON2_MEMORY_COST.unwrap_or(defaults.memory_cost),
+        time_cost: ARGON2_TIME_COST.unwrap_or(defaults.time_cost),
+        parallelism: ARGON2_PARALLELISM.unwrap_or(defaults.parallelism),
+    })
+    .expect("Failed to configure password hasher")
+    .with_pool(configure_hashing_pool())
+}
+
+fn configure_hashing_pool() -> HashingPool {
+    let workers = HASHING_WORKERS.unwrap_or_else(HashingPool::default_workers);
+    let queue_depth =
+        HASHING_QUEUE_DEPTH.unwrap_or_else(|| HashingPool::default_queue_depth(workers));
+
+    HashingPool::new(workers, queue_depth)
+}
+
+fn configure_password_policy() -> PasswordPolicy {
+    let default = PasswordPolicy::default();
+
+    PasswordPolicy {
+        min_length: PASSWORD_MIN_LENGTH.unwrap_or(default.min_length),
+        max_length: PASSWORD_MAX_LENGTH.unwrap_or(default.max_length),
+        min_strength: PASSWORD_MIN_STRENGTH
+            .map(|score| {
+                PasswordStrength::from_score(score).expect("PASSWORD_MIN_STRENGTH must be 0..=4")
+            })
+            .unwrap_or(default.min_strength),
+        reject_common_passwords: PASSWORD_REJECT_COMMON.unwrap_or(default.reject_common_passwords),
+        reject_email_local_part: PASSWORD_REJECT_EMAIL.unwrap_or(default.reject_email_local_part),
+    }
+}
+
+fn configure_email_domain_policy() -> EmailDomainPolicy {
+    let domains = |patterns: &Option<String>, name: &str| {
+        patterns
+            .as_deref()
+            .map(|patterns| {
+                DomainPattern::parse_list(patterns).unwrap_or_else(|e| panic!("{name}: {e}"))
+            })
+            .unwrap_or_default()
+    };
+
+    let policy = EmailDomainPolicy {
+        allowed_domains: domains(&EMAIL_ALLOWED_DOMAINS, "EMAIL_ALLOWED_DOMAINS"),
+        blocked_domains: domains(&EMAIL_BLOCKED_DOMAINS, "EMAIL_BLOCKED_DOMAINS"),
+        reject_disposable: EMAIL_REJECT_DISPOSABLE.unwrap_or(true),
+        ..Default::default()
+    };
+
+    match DISPOSABLE_EMAIL_DOMAINS_PATH.as_ref() {
+        Some(path) => policy
+            .with_disposable_domains_from(path)
+            .expect("Failed to load DISPOSABLE_EMAIL_DOMAINS_PATH"),
+        None => policy,
+    }
+}
+
+fn configure_token_settings() -> TokenSettings {
+    let defaults = TokenSettings::default();
+
+    TokenSettings {
+        issuer: JWT_ISSUER.clone().unwrap_or(defaults.issuer),
+        audiences: JWT_AUDIENCES
+            .as_deref()
+            .map(|audiences| {
+                TokenSettings::parse_audiences(audiences).expect("Invalid JWT_AUDIENCES")
+            })
+            .unwrap_or(defaults.audiences),
+        leeway_seconds: JWT_LEEWAY_SECONDS.unwrap_or(defaults.leeway_seconds),
+    }
+}
+
+fn configure_redis() -> RedisConfig {
+    let topology = RedisTopology::parse(
+        REDIS_MODE.as_deref(),
+        &REDIS_URL,
+        REDIS_SENTINEL_MASTER.as_deref(),
+    )
+    .expect("Invalid Redis configuration");
+
+    RedisConfig::new(topology).with_namespace(REDIS_NAMESPACE.clone().unwrap_or_default())
+}
+
+async fn connect_redis(config: &RedisConfig) -> RedisConnection {
+    get_redis_connection(config)
+        .await
         .expect("Failed to get Redis connection")
 }
 
@@ -1,11 +1,25 @@
+mod account;
+mod admin;
 mod signup;
 mod login;
 mod logout;
+mod orgs;
+mod password_reset;
+mod personal_access_tokens;
+mod rbac;
+mod signup_invitations;
 mod verify_2fa;
 mod verify_token;
 
+pub use account::*;
+pub use admin::*;
 pub use signup::*;
 pub use login::*;
 pub use logout::*;
+pub use orgs::*;
+pub use password_reset::*;
+pub use personal_access_tokens::*;
+pub use rbac::*;
+pub use signup_invitations::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
@@ -0,0 +1,258 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    Extension, Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, AuthMethod, Authentication, LoginAttemptId, OrgRole, OrgStoreError, Password,
+        TwoFACode, TwoFACodeStoreError, User, UserStoreError, UserUpdate,
+    },
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME, password::validate_new_password},
+};
+
+use super::{
+    login::{handle_2fa, issue_auth_cookie, LoginResponse},
+    orgs::caller,
+    resolve_org,
+    verify_2fa::redeem_2fa_code,
+};
+
+// Lets through only callers who authenticated recently, and with a second factor if their
+// account has 2FA; the others must go through `/account/reauthenticate` first. Goes behind
+// `require_auth`, whose claims it reads
+pub async fn require_recent_auth(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthAPIError> {
+    let user = caller_user(&state, &claims).await?;
+    let authentication = claims.authentication();
+
+    if authentication.is_older_than(state.step_up_max_age)
+        || (user.requires_2fa && !authentication.is_multi_factor())
+    {
+        return Err(AuthAPIError::ReauthenticationRequired);
+    }
+
+    Ok(next.run(request).await)
+}
+
+// Upgrades the caller's session once they've entered their password again. Users with 2FA
+// get a code instead, to be redeemed at `/account/reauthenticate/verify-2fa`
+#[tracing::instrument(name = "Reauthenticate", skip_all)]
+pub async fn reauthenticate(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    jar: CookieJar,
+    Json(request): Json<ReauthenticateRequest>,
+) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError> {
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = caller_user(&state, &claims).await?;
+
+    state
+        .user_store
+        .validate_user(&user.email, &password)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::Overloaded => AuthAPIError::ServiceUnavailable,
+            _ => AuthAPIError::IncorrectCredentials,
+        })?;
+
+    if user.requires_2fa {
+        return handle_2fa(&user.email, &state, jar).await;
+    }
+
+    let authentication = Authentication::now(vec![AuthMethod::Password]);
+    let jar = upgrade_session(&state, &claims, jar, &authentication).await?;
+
+    Ok((StatusCode::OK, jar, LoginResponse::RegularAuth.into()))
+}
+
+#[tracing::instrument(name = "Verify reauthentication 2FA code", skip_all)]
+pub async fn verify_reauthentication_2fa(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    jar: CookieJar,
+    Json(request): Json<ReauthenticationCodeRequest>,
+) -> Result<(StatusCode, CookieJar), AuthAPIError> {
+    let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id.into())
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let two_fa_code = TwoFACode::parse(request.two_fa_code.into())
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = caller_user(&state, &claims).await?;
+
+    redeem_2fa_code(&state, &user.email, &login_attempt_id, &two_fa_code).await?;
+
+    let authentication = Authentication::now(vec![AuthMethod::Password, AuthMethod::EmailOtp]);
+    let jar = upgrade_session(&state, &claims, jar, &authentication).await?;
+
+    Ok((StatusCode::OK, jar))
+}
+
+#[tracing::instrument(name = "Disable 2FA", skip_all)]
+pub async fn disable_2fa(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AuthAPIError> {
+    let user = caller_user(&state, &claims).await?;
+    let update = UserUpdate {
+        requires_2fa: Some(false),
+        ..Default::default()
+    };
+
+    state
+        .user_store
+        .update_user(&user.id, update)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    match state.two_fa_code_store.remove_code(&user.email).await {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Also lifts a reset forced by an admin. The caller's session stays valid
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let user = caller_user(&state, &claims).await?;
+    let password = validate_new_password(&state, request.new_password, &user.email).await?;
+
+    state
+        .user_store
+        .set_password(&user.id, password)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::Overloaded => AuthAPIError::ServiceUnavailable,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Leaves the caller's organizations first, so one they are the last owner of blocks it
+// rather than being left without an owner
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    jar: CookieJar,
+) -> Result<(StatusCode, CookieJar), AuthAPIError> {
+    let user = caller_user(&state, &claims).await?;
+    let orgs = state
+        .org_store
+        .list_user_orgs(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Checked for every organization before leaving any, so a refusal changes nothing
+    for (org, role) in &orgs {
+        if *role != OrgRole::Owner {
+            continue;
+        }
+        let members = state
+            .org_store
+            .list_members(&org.id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        if members
+            .iter()
+            .all(|m| m.role != OrgRole::Owner || m.user_id == user.id)
+        {
+            return Err(AuthAPIError::LastOrgOwner);
+        }
+    }
+
+    for (org, _) in &orgs {
+        match state.org_store.remove_member(&org.id, &user.id).await {
+            Ok(()) | Err(OrgStoreError::NotMember) => {}
+            Err(OrgStoreError::LastOwner) => return Err(AuthAPIError::LastOrgOwner),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    state
+        .user_store
+        .delete_user(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .banned_token_store
+        .add_token(&claims.jti, claims.ban_ttl(&state.token_settings))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::NO_CONTENT, jar.remove(JWT_COOKIE_NAME)))
+}
+
+// Re-issues the caller's token with a new authentication, for the same organization and
+// audience; the token it replaces is banned
+async fn upgrade_session(
+    state: &AppState,
+    claims: &Claims,
+    jar: CookieJar,
+    authentication: &Authentication,
+) -> Result<CookieJar, AuthAPIError> {
+    let user_id = caller(claims)?;
+    let org_id = resolve_org(state, claims.org_id.as_deref(), &user_id).await?;
+    let audience = state
+        .token_settings
+        .audience(claims.aud.first().map(String::as_str))?;
+    let auth_cookie =
+        issue_auth_cookie(state, &user_id, org_id.as_ref(), audience, authentication).await?;
+
+    state
+        .banned_token_store
+        .add_token(&claims.jti, claims.ban_ttl(&state.token_settings))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(jar.add(auth_cookie))
+}
+
+async fn caller_user(state: &AppState, claims: &Claims) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .get_user_by_id(&caller(claims)?)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })
+}
+
+#[derive(Deserialize)]
+pub struct ReauthenticateRequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReauthenticationCodeRequest {
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: String,
+}
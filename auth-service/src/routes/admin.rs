@@ -0,0 +1,283 @@
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AccountStatus, AuthAPIError, RoleName, StatusChange, TwoFACodeStoreError, User, UserId,
+        UserQuery, UserStoreError, UserUpdate,
+    },
+    utils::{
+        auth::{validate_token, Claims},
+        auth_token::AuthToken,
+    },
+};
+
+use super::{orgs::caller, password_reset::send_password_reset};
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+// Lets through only requests carrying a valid token of a user with the admin role; the handlers
+// behind it get the admin's claims as an `Extension<Claims>`
+pub async fn require_admin(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AuthAPIError> {
+    let AuthToken(token) = AuthToken::from_headers(request.headers(), &state.token_sources)?;
+
+    let claims = validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+        state.org_store.clone(),
+        &state.token_settings,
+    )
+    .await?;
+
+    // The role is looked up rather than read from the token, so demoting an admin takes effect
+    // right away instead of when their tokens expire
+    let grants = state
+        .role_store
+        .get_grants(&caller(&claims)?)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if !grants.roles.contains(&RoleName::admin()) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+#[tracing::instrument(name = "Admin: list users", skip_all)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(params): Query<ListUsersParams>,
+) -> Result<Json<UserListResponse>, AuthAPIError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let query = UserQuery {
+        email: params.email.filter(|email| !email.is_empty()),
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+    let result = state
+        .user_store
+        .list_users(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(UserListResponse {
+        users: result.users.iter().map(AdminUserResponse::from).collect(),
+        page,
+        per_page,
+        total: result.total,
+    }))
+}
+
+#[tracing::instrument(name = "Admin: get user", skip_all)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<AdminUserResponse>, AuthAPIError> {
+    let user = find_user(&state, &id).await?;
+
+    Ok(Json(AdminUserResponse::from(&user)))
+}
+
+#[tracing::instrument(name = "Admin: disable user", skip_all)]
+pub async fn disable_user(
+    State(state): State<AppState>,
+    Extension(admin): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(request): Json<StatusChangeRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    change_status(&state, &admin, &id, AccountStatus::Disabled, request.reason).await
+}
+
+#[tracing::instrument(name = "Admin: enable user", skip_all)]
+pub async fn enable_user(
+    State(state): State<AppState>,
+    Extension(admin): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(request): Json<StatusChangeRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    change_status(&state, &admin, &id, AccountStatus::Active, request.reason).await
+}
+
+// The user can't log in again until they set a new password with the token they're emailed;
+// existing sessions end right away
+#[tracing::instrument(name = "Admin: force password reset", skip_all)]
+pub async fn force_password_reset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let user = find_user(&state, &id).await?;
+    let update = UserUpdate {
+        password_reset_required: Some(true),
+        sessions_revoked_at: Some(Utc::now()),
+        ..Default::default()
+    };
+    update_user(&state, &id, update).await?;
+    send_password_reset(&state, &user).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Turns 2FA off, e.g. for a user who lost access to their inbox, and drops any pending code
+#[tracing::instrument(name = "Admin: reset 2FA", skip_all)]
+pub async fn reset_2fa(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let user = find_user(&state, &id).await?;
+    let update = UserUpdate {
+        requires_2fa: Some(false),
+        ..Default::default()
+    };
+    update_user(&state, &id, update).await?;
+
+    match state.two_fa_code_store.remove_code(&user.email).await {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin: revoke sessions", skip_all)]
+pub async fn revoke_sessions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let update = UserUpdate {
+        sessions_revoked_at: Some(Utc::now()),
+        ..Default::default()
+    };
+    update_user(&state, &id, update).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn find_user(state: &AppState, id: &str) -> Result<User, AuthAPIError> {
+    let id = UserId::parse(id).map_err(|_| AuthAPIError::UserNotFound)?;
+
+    state
+        .user_store
+        .get_user_by_id(&id)
+        .await
+        .map_err(map_user_store_error)
+}
+
+async fn update_user(state: &AppState, id: &str, update: UserUpdate) -> Result<(), AuthAPIError> {
+    let id = UserId::parse(id).map_err(|_| AuthAPIError::UserNotFound)?;
+
+    state
+        .user_store
+        .update_user(&id, update)
+        .await
+        .map_err(map_user_store_error)
+}
+
+async fn change_status(
+    state: &AppState,
+    admin: &Claims,
+    id: &str,
+    status: AccountStatus,
+    reason: String,
+) -> Result<StatusCode, AuthAPIError> {
+    let id = UserId::parse(id).map_err(|_| AuthAPIError::UserNotFound)?;
+    let change = StatusChange {
+        status,
+        reason,
+        actor: admin.sub.clone(),
+    };
+
+    state
+        .user_store
+        .set_status(&id, change)
+        .await
+        .map_err(map_user_store_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersParams {
+    pub page: Option<u32>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u32>,
+    // Part of the email address to search for
+    pub email: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct StatusChangeRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserListResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub page: u32,
+    #[serde(rename = "perPage")]
+    pub per_page: u32,
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AdminUserResponse {
+    pub id: String,
+    pub email: String,
+    pub status: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    #[serde(rename = "lastLoginAt")]
+    pub last_login_at: Option<String>,
+}
+
+impl From<&User> for AdminUserResponse {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id.to_string(),
+            email: user.email.as_ref().expose_secret().clone(),
+            status: user.status.as_str().to_owned(),
+            requires_2fa: user.requires_2fa,
+            password_reset_required: user.password_reset_required,
+            created_at: timestamp(&user.created_at),
+            updated_at: timestamp(&user.updated_at),
+            last_login_at: user.last_login_at.as_ref().map(timestamp),
+        }
+    }
+}
+
+fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339()
+}
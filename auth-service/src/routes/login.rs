@@ -1,14 +1,19 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use axum_extra::extract::CookieJar;
+use axum_extra::extract::{cookie::Cookie, CookieJar};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, AuthMethod, Authentication, Email, LoginAttemptId, OrgId, Password,
+        TwoFACode, User, UserId, UserStoreError,
+    },
+    utils::{auth::generate_auth_cookie, password::is_breached},
 };
 
+use super::resolve_org;
+
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
@@ -19,26 +24,43 @@ pub async fn login(
     let password =
         Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user_store = &state.user_store.read().await;
+    let user_store = &state.user_store;
 
     user_store
         .validate_user(&email, &password)
         .await
-        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+        .map_err(|e| match e {
+            UserStoreError::Overloaded => AuthAPIError::ServiceUnavailable,
+            _ => AuthAPIError::IncorrectCredentials,
+        })?;
 
     let user = user_store
         .get_user(&email)
         .await
         .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    // only revealed once the password is known to be right
+    user.ensure_can_log_in()?;
+
+    if is_breached(&state, &password).await {
+        tracing::warn!(
+            user_id = %user.id,
+            "user logged in with a password that appears in known data breaches"
+        );
+    }
+
+    // checked before any 2FA code goes out; `/verify-2fa` checks them again
+    let org_id = resolve_org(&state, request.org_id.as_deref(), &user.id).await?;
+    let audience = state.token_settings.audience(request.audience.as_deref())?;
 
     match user.requires_2fa {
         true => handle_2fa(&email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        false => handle_no_2fa(&user, org_id.as_ref(), audience, &state, jar).await,
     }
 }
 
+// Sends a code for the second factor, to be redeemed with the returned login attempt id
 #[tracing::instrument(name = "2FA scenario", skip_all)]
-async fn handle_2fa(
+pub(super) async fn handle_2fa(
     email: &Email,
     state: &AppState,
     jar: CookieJar,
@@ -48,8 +70,6 @@ async fn handle_2fa(
 
     state
         .two_fa_code_store
-        .write()
-        .await
         .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
@@ -73,10 +93,18 @@ async fn handle_2fa(
 
 #[tracing::instrument(name = "no 2FA scenario", skip_all)]
 async fn handle_no_2fa(
-    email: &Email,
+    user: &User,
+    org_id: Option<&OrgId>,
+    audience: &str,
+    state: &AppState,
     jar: CookieJar,
 ) -> Result<(StatusCode, CookieJar, Json<LoginResponse>), AuthAPIError> {
-    let auth_cookie = generate_auth_cookie(email).map_err(AuthAPIError::UnexpectedError)?;
+    let authentication = Authentication::now(vec![AuthMethod::Password]);
+    let auth_cookie = issue_auth_cookie(state, &user.id, org_id, audience, &authentication).await?;
+
+    if let Err(e) = state.user_store.record_login(&user.id).await {
+        tracing::error!("Failed to record login: {:?}", e);
+    }
 
     let updated_jar = jar.add(auth_cookie);
 
@@ -86,10 +114,42 @@ async fn handle_no_2fa(
         LoginResponse::RegularAuth.into(),
     ))
 }
+// Issues a cookie carrying the user's current grants, scoped to `org_id` if given, for one of
+// the configured audiences
+pub(super) async fn issue_auth_cookie(
+    state: &AppState,
+    user_id: &UserId,
+    org_id: Option<&OrgId>,
+    audience: &str,
+    authentication: &Authentication,
+) -> Result<Cookie<'static>, AuthAPIError> {
+    let grants = state
+        .role_store
+        .get_grants(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    generate_auth_cookie(
+        user_id,
+        &grants,
+        org_id,
+        audience,
+        authentication,
+        &state.token_settings,
+    )
+    .map_err(AuthAPIError::UnexpectedError)
+}
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub email: String,
     pub password: Secret<String>,
+    // Logs into one of the user's organizations
+    #[serde(rename = "orgId", default)]
+    pub org_id: Option<String>,
+    // The app the token is for; defaults to the first configured audience
+    #[serde(default)]
+    pub audience: Option<String>,
 }
 
 // The login route can return 2 possible success responses.
@@ -1,30 +1,24 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
 
 use crate::{
     app_state::AppState,
     domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    utils::{auth::decode_token, auth_token::AuthToken, constants::JWT_COOKIE_NAME},
 };
 
+// Takes any current token issued here, including one its user can no longer use, e.g. once
+// their account is locked, so that they can still end the session
 pub async fn logout(
     State(state): State<AppState>,
     jar: CookieJar,
+    AuthToken(token): AuthToken,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
-    let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
-
-    let token: Secret<String> = cookie.value().to_owned().into();
-
-    validate_token(&token, state.banned_token_store.clone())
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let claims = decode_token(&token, &state.token_settings)?;
 
     state
         .banned_token_store
-        .write()
-        .await
-        .add_token(token)
+        .add_token(&claims.jti, claims.ban_ttl(&state.token_settings))
         .await
         .map_err(|_| AuthAPIError::TokenAlreadyInvalidated)?;
 
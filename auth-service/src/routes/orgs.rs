@@ -0,0 +1,495 @@
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    Extension, Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, Invitation, InvitationId, Membership, OrgId, OrgRole, OrgStoreError,
+        Organization, UserId,
+    },
+    utils::{
+        auth::{validate_token, Claims},
+        auth_token::AuthToken,
+    },
+};
+
+use super::login::issue_auth_cookie;
+
+const MAX_ORG_NAME_LENGTH: usize = 100;
+
+// Lets through only requests carrying a valid token; the handlers behind it get the
+// caller's claims as an `Extension<Claims>`
+pub async fn require_auth(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AuthAPIError> {
+    let AuthToken(token) = AuthToken::from_headers(request.headers(), &state.token_sources)?;
+
+    let claims = validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+        state.org_store.clone(),
+        &state.token_settings,
+    )
+    .await?;
+
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+#[tracing::instrument(name = "Create organization", skip_all)]
+pub async fn create_org(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreateOrgRequest>,
+) -> Result<(StatusCode, Json<OrgResponse>), AuthAPIError> {
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_ORG_NAME_LENGTH {
+        return Err(AuthAPIError::InvalidOrgInput);
+    }
+
+    let org = Organization::new(name.to_owned());
+    state
+        .org_store
+        .create_org(&org, &caller(&claims)?)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(OrgResponse::new(&org, OrgRole::Owner)),
+    ))
+}
+
+#[tracing::instrument(name = "List organizations", skip_all)]
+pub async fn list_orgs(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<OrgResponse>>, AuthAPIError> {
+    let orgs = state
+        .org_store
+        .list_user_orgs(&caller(&claims)?)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok(Json(
+        orgs.iter()
+            .map(|(org, role)| OrgResponse::new(org, *role))
+            .collect(),
+    ))
+}
+
+// Re-issues the auth cookie for another of the caller's organizations, with the same audience
+// and authentication, without asking for their credentials again; the token it replaces is
+// banned
+#[tracing::instrument(name = "Switch organization", skip_all)]
+pub async fn switch_org(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    jar: CookieJar,
+    Json(request): Json<SwitchOrgRequest>,
+) -> Result<(StatusCode, CookieJar), AuthAPIError> {
+    let user_id = caller(&claims)?;
+    let org_id = resolve_org(&state, Some(&request.org_id), &user_id).await?;
+    let audience = state
+        .token_settings
+        .audience(claims.aud.first().map(String::as_str))?;
+    let auth_cookie = issue_auth_cookie(
+        &state,
+        &user_id,
+        org_id.as_ref(),
+        audience,
+        &claims.authentication(),
+    )
+    .await?;
+
+    state
+        .banned_token_store
+        .add_token(&claims.jti, claims.ban_ttl(&state.token_settings))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::OK, jar.add(auth_cookie)))
+}
+
+#[tracing::instrument(name = "List organization members", skip_all)]
+pub async fn list_members(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+) -> Result<Json<Vec<MemberResponse>>, AuthAPIError> {
+    let (org_id, _) = membership(&state, &claims, &org_id).await?;
+
+    let members = state
+        .org_store
+        .list_members(&org_id)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok(Json(members.iter().map(MemberResponse::from).collect()))
+}
+
+#[tracing::instrument(name = "Change organization role", skip_all)]
+pub async fn set_member_role(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((org_id, user_id)): Path<(String, String)>,
+    Json(request): Json<SetRoleRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let (org_id, caller_role) = membership(&state, &claims, &org_id).await?;
+    let role = parse_role(&request.role)?;
+    let user_id = member_id(&user_id)?;
+    let current = state
+        .org_store
+        .get_role(&org_id, &user_id)
+        .await
+        .map_err(map_member_error)?;
+
+    if !(caller_role.can_manage(current) && caller_role.can_manage(role)) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    state
+        .org_store
+        .set_role(&org_id, &user_id, role)
+        .await
+        .map_err(map_member_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Members may always leave; removing others takes a role that can manage theirs
+#[tracing::instrument(name = "Remove organization member", skip_all)]
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((org_id, user_id)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let (org_id, caller_role) = membership(&state, &claims, &org_id).await?;
+    let user_id = member_id(&user_id)?;
+
+    if user_id != caller(&claims)? {
+        let role = state
+            .org_store
+            .get_role(&org_id, &user_id)
+            .await
+            .map_err(map_member_error)?;
+        if !caller_role.can_manage(role) {
+            return Err(AuthAPIError::Forbidden);
+        }
+    }
+
+    state
+        .org_store
+        .remove_member(&org_id, &user_id)
+        .await
+        .map_err(map_member_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Invite to organization", skip_all)]
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<(StatusCode, Json<InvitationResponse>), AuthAPIError> {
+    let (org_id, caller_role) = membership(&state, &claims, &org_id).await?;
+    let email = Email::parse(request.email.into()).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let role = match request.role {
+        Some(role) => parse_role(&role)?,
+        None => OrgRole::Member,
+    };
+    if !caller_role.can_manage(role) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    let invitation = Invitation::new(org_id, email, role, caller(&claims)?);
+    state
+        .org_store
+        .add_invitation(&invitation)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(InvitationResponse::from(&invitation)),
+    ))
+}
+
+#[tracing::instrument(name = "List organization invitations", skip_all)]
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+) -> Result<Json<Vec<InvitationResponse>>, AuthAPIError> {
+    let org_id = manager_membership(&state, &claims, &org_id).await?;
+
+    let invitations = state
+        .org_store
+        .list_invitations(&org_id)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok(Json(
+        invitations.iter().map(InvitationResponse::from).collect(),
+    ))
+}
+
+#[tracing::instrument(name = "Revoke organization invitation", skip_all)]
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((org_id, invitation_id)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let org_id = manager_membership(&state, &claims, &org_id).await?;
+    let invitation_id =
+        InvitationId::parse(&invitation_id).map_err(|_| AuthAPIError::InvitationNotFound)?;
+
+    state
+        .org_store
+        .revoke_invitation(&org_id, &invitation_id)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Invitations addressed to the caller's email, from any organization
+#[tracing::instrument(name = "List my invitations", skip_all)]
+pub async fn list_my_invitations(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<InvitationResponse>>, AuthAPIError> {
+    let email = caller_email(&state, &claims).await?;
+
+    let invitations = state
+        .org_store
+        .list_invitations_for(&email)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok(Json(
+        invitations.iter().map(InvitationResponse::from).collect(),
+    ))
+}
+
+#[tracing::instrument(name = "Accept invitation", skip_all)]
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(invitation_id): Path<String>,
+) -> Result<Json<OrgResponse>, AuthAPIError> {
+    let invitation_id =
+        InvitationId::parse(&invitation_id).map_err(|_| AuthAPIError::InvitationNotFound)?;
+    let email = caller_email(&state, &claims).await?;
+    let user_id = caller(&claims)?;
+
+    let org_id = state
+        .org_store
+        .accept_invitation(&invitation_id, &email, &user_id)
+        .await
+        .map_err(map_org_store_error)?;
+    let org = state
+        .org_store
+        .get_org(&org_id)
+        .await
+        .map_err(map_org_store_error)?;
+    let role = state
+        .org_store
+        .get_role(&org_id, &user_id)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok(Json(OrgResponse::new(&org, role)))
+}
+
+// Checks that the user belongs to the organization a token is requested for; ids that don't
+// parse are treated like organizations the user isn't in
+pub(super) async fn resolve_org(
+    state: &AppState,
+    org_id: Option<&str>,
+    user_id: &UserId,
+) -> Result<Option<OrgId>, AuthAPIError> {
+    let Some(org_id) = org_id else {
+        return Ok(None);
+    };
+    let org_id = OrgId::parse(org_id).map_err(|_| AuthAPIError::NotOrgMember)?;
+
+    state
+        .org_store
+        .get_role(&org_id, user_id)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok(Some(org_id))
+}
+
+// The caller's role in the organization; outsiders can't tell whether it exists
+pub(super) async fn membership(
+    state: &AppState,
+    claims: &Claims,
+    org_id: &str,
+) -> Result<(OrgId, OrgRole), AuthAPIError> {
+    let org_id = OrgId::parse(org_id).map_err(|_| AuthAPIError::NotOrgMember)?;
+
+    let role = state
+        .org_store
+        .get_role(&org_id, &caller(claims)?)
+        .await
+        .map_err(map_org_store_error)?;
+
+    Ok((org_id, role))
+}
+
+async fn manager_membership(
+    state: &AppState,
+    claims: &Claims,
+    org_id: &str,
+) -> Result<OrgId, AuthAPIError> {
+    let (org_id, role) = membership(state, claims, org_id).await?;
+    if !role.can_manage(OrgRole::Member) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    Ok(org_id)
+}
+
+pub(super) fn caller(claims: &Claims) -> Result<UserId, AuthAPIError> {
+    UserId::parse(&claims.sub).map_err(|_| AuthAPIError::InvalidToken)
+}
+
+async fn caller_email(state: &AppState, claims: &Claims) -> Result<Email, AuthAPIError> {
+    state
+        .user_store
+        .get_user_by_id(&caller(claims)?)
+        .await
+        .map(|user| user.email)
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+fn member_id(id: &str) -> Result<UserId, AuthAPIError> {
+    UserId::parse(id).map_err(|_| AuthAPIError::UserNotFound)
+}
+
+fn parse_role(role: &str) -> Result<OrgRole, AuthAPIError> {
+    OrgRole::parse(role).map_err(|_| AuthAPIError::InvalidOrgInput)
+}
+
+fn map_org_store_error(e: OrgStoreError) -> AuthAPIError {
+    match e {
+        OrgStoreError::OrgNotFound | OrgStoreError::NotMember => AuthAPIError::NotOrgMember,
+        OrgStoreError::LastOwner => AuthAPIError::LastOrgOwner,
+        OrgStoreError::InvitationAlreadyExists => AuthAPIError::InvitationAlreadyExists,
+        OrgStoreError::InvitationNotFound => AuthAPIError::InvitationNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// For store calls about another member, where `NotMember` is about them, not the caller
+fn map_member_error(e: OrgStoreError) -> AuthAPIError {
+    match e {
+        OrgStoreError::NotMember => AuthAPIError::UserNotFound,
+        e => map_org_store_error(e),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateOrgRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct SwitchOrgRequest {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetRoleRequest {
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateInvitationRequest {
+    pub email: String,
+    // Defaults to `member`
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OrgResponse {
+    pub id: String,
+    pub name: String,
+    // The caller's role in the organization
+    pub role: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl OrgResponse {
+    fn new(org: &Organization, role: OrgRole) -> Self {
+        Self {
+            id: org.id.to_string(),
+            name: org.name.clone(),
+            role: role.as_str().to_owned(),
+            created_at: org.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MemberResponse {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    pub role: String,
+    #[serde(rename = "joinedAt")]
+    pub joined_at: String,
+}
+
+impl From<&Membership> for MemberResponse {
+    fn from(member: &Membership) -> Self {
+        Self {
+            user_id: member.user_id.to_string(),
+            role: member.role.as_str().to_owned(),
+            joined_at: member.joined_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InvitationResponse {
+    pub id: String,
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    pub email: String,
+    pub role: String,
+    #[serde(rename = "invitedBy")]
+    pub invited_by: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<&Invitation> for InvitationResponse {
+    fn from(invitation: &Invitation) -> Self {
+        Self {
+            id: invitation.id.to_string(),
+            org_id: invitation.org_id.to_string(),
+            email: invitation.email.as_ref().expose_secret().clone(),
+            role: invitation.role.as_str().to_owned(),
+            invited_by: invitation.invited_by.to_string(),
+            created_at: invitation.created_at.to_rfc3339(),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use axum::{extract::State, http::StatusCode, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, PasswordReset, PasswordResetToken, User, UserStoreError},
+    utils::password::validate_new_password,
+};
+
+// Emails the user a token to set a new password with. The answer is the same whether or not
+// the address belongs to an account
+#[tracing::instrument(name = "Request password reset", skip_all)]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    match state.user_store.get_user(&email).await {
+        Ok(user) => send_password_reset(&state, &user).await?,
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+// Also lifts a reset forced by an admin; the user's existing sessions end
+#[tracing::instrument(name = "Reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let token =
+        PasswordResetToken::parse(request.token).map_err(|_| AuthAPIError::InvalidPasswordReset)?;
+    let password = validate_new_password(&state, request.new_password, &email).await?;
+
+    state
+        .user_store
+        .complete_password_reset(&email, &token, password)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::InvalidCredentials => AuthAPIError::InvalidPasswordReset,
+            UserStoreError::Overloaded => AuthAPIError::ServiceUnavailable,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Only the user learns the token, through the email
+pub(super) async fn send_password_reset(state: &AppState, user: &User) -> Result<(), AuthAPIError> {
+    let (reset, token) = PasswordReset::new(state.password_reset_ttl);
+    state
+        .user_store
+        .start_password_reset(&user.id, &reset)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let content = format!(
+        "A password reset was requested for your account. Your reset token is {}; it expires at {}.",
+        token.as_ref().expose_secret(),
+        reset.expires_at.to_rfc3339(),
+    );
+    state
+        .email_client
+        .send_email(&user.email, "Reset your password", &content)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub email: Secret<String>,
+    pub token: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
@@ -0,0 +1,170 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Duration;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Permission, PersonalAccessToken, PersonalAccessTokenId,
+        PersonalAccessTokenStoreError,
+    },
+    utils::auth::Claims,
+};
+
+use super::orgs::caller;
+
+const MAX_TOKEN_NAME_LENGTH: usize = 100;
+const DEFAULT_TOKEN_TTL: Duration = Duration::days(30);
+
+// The secret is only ever in this response
+#[tracing::instrument(name = "Create personal access token", skip_all)]
+pub async fn create_personal_access_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreatePersonalAccessTokenRequest>,
+) -> Result<(StatusCode, Json<CreatePersonalAccessTokenResponse>), AuthAPIError> {
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TOKEN_NAME_LENGTH {
+        return Err(AuthAPIError::InvalidPersonalAccessTokenInput);
+    }
+
+    let ttl = match request.expires_in_days {
+        Some(days) => {
+            Duration::try_days(days).ok_or(AuthAPIError::InvalidPersonalAccessTokenInput)?
+        }
+        None => DEFAULT_TOKEN_TTL.min(state.personal_access_token_max_ttl),
+    };
+    if ttl <= Duration::zero() || ttl > state.personal_access_token_max_ttl {
+        return Err(AuthAPIError::InvalidPersonalAccessTokenInput);
+    }
+
+    let scopes = request
+        .scopes
+        .iter()
+        .map(|scope| Permission::parse(scope))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| AuthAPIError::InvalidPersonalAccessTokenInput)?;
+
+    // a token can't be granted more than its owner holds
+    let user_id = caller(&claims)?;
+    let grants = state
+        .role_store
+        .get_grants(&user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if scopes
+        .iter()
+        .any(|scope| !grants.permissions.contains(scope))
+    {
+        return Err(AuthAPIError::MissingPermission);
+    }
+
+    let (token, secret) = PersonalAccessToken::new(user_id, name.to_owned(), scopes, ttl);
+    state
+        .personal_access_token_store
+        .add_token(&token)
+        .await
+        .map_err(map_personal_access_token_store_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatePersonalAccessTokenResponse {
+            token: secret.as_ref().expose_secret().clone(),
+            details: PersonalAccessTokenResponse::from(&token),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "List personal access tokens", skip_all)]
+pub async fn list_personal_access_tokens(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<PersonalAccessTokenResponse>>, AuthAPIError> {
+    let tokens = state
+        .personal_access_token_store
+        .list_tokens(&caller(&claims)?)
+        .await
+        .map_err(map_personal_access_token_store_error)?;
+
+    Ok(Json(
+        tokens
+            .iter()
+            .map(PersonalAccessTokenResponse::from)
+            .collect(),
+    ))
+}
+
+#[tracing::instrument(name = "Revoke personal access token", skip_all)]
+pub async fn revoke_personal_access_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let id =
+        PersonalAccessTokenId::parse(&id).map_err(|_| AuthAPIError::PersonalAccessTokenNotFound)?;
+
+    state
+        .personal_access_token_store
+        .revoke_token(&caller(&claims)?, &id)
+        .await
+        .map_err(map_personal_access_token_store_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn map_personal_access_token_store_error(e: PersonalAccessTokenStoreError) -> AuthAPIError {
+    match e {
+        PersonalAccessTokenStoreError::TokenNotFound => AuthAPIError::PersonalAccessTokenNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreatePersonalAccessTokenRequest {
+    pub name: String,
+    // Permissions the token may use, among those its owner holds
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    // Defaults to 30 days
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePersonalAccessTokenResponse {
+    pub token: String,
+    #[serde(flatten)]
+    pub details: PersonalAccessTokenResponse,
+}
+
+// Never includes the secret
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PersonalAccessTokenResponse {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<String>,
+}
+
+impl From<&PersonalAccessToken> for PersonalAccessTokenResponse {
+    fn from(token: &PersonalAccessToken) -> Self {
+        Self {
+            id: token.id.to_string(),
+            name: token.name.clone(),
+            scopes: token.scopes.iter().map(ToString::to_string).collect(),
+            created_at: token.created_at.to_rfc3339(),
+            expires_at: token.expires_at.to_rfc3339(),
+            last_used_at: token.last_used_at.map(|used_at| used_at.to_rfc3339()),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Grants, Permission, RoleName, RoleStoreError},
+};
+
+use super::admin::find_user;
+
+// Role and permission management for admins; changes to a user's grants show up in their
+// tokens from their next login on
+
+#[tracing::instrument(name = "Admin: list roles", skip_all)]
+pub async fn list_roles(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<RoleResponse>>, AuthAPIError> {
+    let roles = state.role_store.list_roles().await.map_err(map_error)?;
+
+    Ok(Json(
+        roles
+            .into_iter()
+            .map(|role| RoleResponse {
+                name: role.name.to_string(),
+                description: role.description,
+                permissions: role.permissions.iter().map(ToString::to_string).collect(),
+            })
+            .collect(),
+    ))
+}
+
+#[tracing::instrument(name = "Admin: create role", skip_all)]
+pub async fn create_role(
+    State(state): State<AppState>,
+    Json(request): Json<CreateRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let name = parse_role(&request.name)?;
+
+    state
+        .role_store
+        .add_role(&name, &request.description)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[tracing::instrument(name = "Admin: delete role", skip_all)]
+pub async fn delete_role(
+    State(state): State<AppState>,
+    Path(role): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let role = parse_role(&role)?;
+    // without it nobody could manage roles anymore
+    if role == RoleName::admin() {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    state
+        .role_store
+        .delete_role(&role)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin: grant permission", skip_all)]
+pub async fn grant_permission(
+    State(state): State<AppState>,
+    Path(role): Path<String>,
+    Json(request): Json<GrantPermissionRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let role = parse_role(&role)?;
+    let permission = parse_permission(&request.permission)?;
+
+    state
+        .role_store
+        .grant_permission(&role, &permission)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin: revoke permission", skip_all)]
+pub async fn revoke_permission(
+    State(state): State<AppState>,
+    Path((role, permission)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let role = parse_role(&role)?;
+    let permission = parse_permission(&permission)?;
+
+    state
+        .role_store
+        .revoke_permission(&role, &permission)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin: list permissions", skip_all)]
+pub async fn list_permissions(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<PermissionResponse>>, AuthAPIError> {
+    let permissions = state
+        .role_store
+        .list_permissions()
+        .await
+        .map_err(map_error)?;
+
+    Ok(Json(
+        permissions
+            .into_iter()
+            .map(|permission| PermissionResponse {
+                name: permission.name.to_string(),
+                description: permission.description,
+            })
+            .collect(),
+    ))
+}
+
+#[tracing::instrument(name = "Admin: create permission", skip_all)]
+pub async fn create_permission(
+    State(state): State<AppState>,
+    Json(request): Json<CreateRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let name = parse_permission(&request.name)?;
+
+    state
+        .role_store
+        .add_permission(&name, &request.description)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[tracing::instrument(name = "Admin: delete permission", skip_all)]
+pub async fn delete_permission(
+    State(state): State<AppState>,
+    Path(permission): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let permission = parse_permission(&permission)?;
+
+    state
+        .role_store
+        .delete_permission(&permission)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin: get user grants", skip_all)]
+pub async fn get_user_grants(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<GrantsResponse>, AuthAPIError> {
+    let user = find_user(&state, &id).await?;
+
+    let grants = state
+        .role_store
+        .get_grants(&user.id)
+        .await
+        .map_err(map_error)?;
+
+    Ok(Json(GrantsResponse::from(&grants)))
+}
+
+#[tracing::instrument(name = "Admin: assign role", skip_all)]
+pub async fn assign_role(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<AssignRoleRequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let user = find_user(&state, &id).await?;
+    let role = parse_role(&request.role)?;
+
+    state
+        .role_store
+        .assign_role(&user.id, &role)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin: unassign role", skip_all)]
+pub async fn unassign_role(
+    State(state): State<AppState>,
+    Path((id, role)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let user = find_user(&state, &id).await?;
+    let role = parse_role(&role)?;
+
+    state
+        .role_store
+        .unassign_role(&user.id, &role)
+        .await
+        .map_err(map_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn parse_role(name: &str) -> Result<RoleName, AuthAPIError> {
+    RoleName::parse(name).map_err(|_| AuthAPIError::InvalidRoleOrPermission)
+}
+
+fn parse_permission(name: &str) -> Result<Permission, AuthAPIError> {
+    Permission::parse(name).map_err(|_| AuthAPIError::InvalidRoleOrPermission)
+}
+
+fn map_error(e: RoleStoreError) -> AuthAPIError {
+    match e {
+        RoleStoreError::RoleAlreadyExists => AuthAPIError::RoleAlreadyExists,
+        RoleStoreError::RoleNotFound => AuthAPIError::RoleNotFound,
+        RoleStoreError::PermissionAlreadyExists => AuthAPIError::PermissionAlreadyExists,
+        RoleStoreError::PermissionNotFound => AuthAPIError::PermissionNotFound,
+        RoleStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize)]
+pub struct GrantPermissionRequest {
+    pub permission: String,
+}
+
+#[derive(Deserialize)]
+pub struct AssignRoleRequest {
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoleResponse {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PermissionResponse {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GrantsResponse {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+impl From<&Grants> for GrantsResponse {
+    fn from(grants: &Grants) -> Self {
+        Self {
+            roles: grants.roles.iter().map(ToString::to_string).collect(),
+            permissions: grants.permissions.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User, UserStoreError},
+    domain::{
+        AuthAPIError, Email, OrgRole, SignupInvitation, SignupInvitationStoreError, SignupMode,
+        SignupToken, User, UserStoreError,
+    },
+    utils::password::validate_new_password,
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
@@ -13,16 +18,52 @@ pub async fn signup(
     Json(request): Json<SignupRequest>,
 ) -> Result<(StatusCode, impl IntoResponse), AuthAPIError> {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let password =
-        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    state
+        .email_domain_policy
+        .check(&email)
+        .map_err(AuthAPIError::EmailDomainRejected)?;
+    let invitation = match request.invitation_token {
+        Some(token) => Some(find_invitation(&state, token, &email).await?),
+        None if state.signup_mode == SignupMode::InviteOnly => {
+            return Err(AuthAPIError::InvitationRequired)
+        }
+        None => None,
+    };
+    let password = validate_new_password(&state, request.password, &email).await?;
 
     let user = User::new(email, password, request.requires_2fa);
-    let mut user_store = state.user_store.write().await;
+    let user_id = user.id;
 
-    user_store.add_user(user).await.map_err(|err| match err {
-        UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
-        e => AuthAPIError::UnexpectedError(e.into()),
-    })?;
+    state
+        .user_store
+        .add_user(user)
+        .await
+        .map_err(|err| match err {
+            UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
+            UserStoreError::Overloaded => AuthAPIError::ServiceUnavailable,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    if let Some(invitation) = invitation {
+        // the invitation is bound to the email, so a second signup with it would have failed
+        // with `UserAlreadyExists` already; losing this race changes nothing
+        match state
+            .signup_invitation_store
+            .consume_invitation(&invitation.id)
+            .await
+        {
+            Ok(()) | Err(SignupInvitationStoreError::InvitationNotFound) => {}
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        if let Some(org_id) = invitation.org_id {
+            state
+                .org_store
+                .add_member(&org_id, &user_id, OrgRole::Member)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        }
+    }
 
     let response = Json(SignupResponse {
         message: "User created successfully".to_string(),
@@ -31,12 +72,39 @@ pub async fn signup(
     Ok((StatusCode::CREATED, response))
 }
 
+// Unknown, expired and other people's invitations are all rejected alike
+async fn find_invitation(
+    state: &AppState,
+    token: Secret<String>,
+    email: &Email,
+) -> Result<SignupInvitation, AuthAPIError> {
+    let token = SignupToken::parse(token).map_err(|_| AuthAPIError::InvalidInvitation)?;
+
+    let invitation = state
+        .signup_invitation_store
+        .get_invitation_by_token_hash(&token.hash())
+        .await
+        .map_err(|e| match e {
+            SignupInvitationStoreError::InvitationNotFound => AuthAPIError::InvalidInvitation,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    if invitation.is_expired(Utc::now()) || invitation.email != *email {
+        return Err(AuthAPIError::InvalidInvitation);
+    }
+
+    Ok(invitation)
+}
+
 #[derive(Deserialize)]
 pub struct SignupRequest {
     pub email: Secret<String>,
     pub password: Secret<String>,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    // Required in invite-only mode
+    #[serde(rename = "invitationToken", default)]
+    pub invitation_token: Option<Secret<String>>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
@@ -0,0 +1,196 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, OrgId, OrgRole, SignupInvitation, SignupInvitationId,
+        SignupInvitationStoreError,
+    },
+    utils::auth::Claims,
+};
+
+use super::orgs::{caller, membership};
+
+#[tracing::instrument(name = "Admin: create signup invitation", skip_all)]
+pub async fn create_signup_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreateSignupInvitationRequest>,
+) -> Result<(StatusCode, Json<SignupInvitationResponse>), AuthAPIError> {
+    invite(&state, &claims, request, None).await
+}
+
+#[tracing::instrument(name = "Admin: list signup invitations", skip_all)]
+pub async fn list_signup_invitations(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SignupInvitationResponse>>, AuthAPIError> {
+    list(&state, None).await
+}
+
+#[tracing::instrument(name = "Admin: revoke signup invitation", skip_all)]
+pub async fn revoke_signup_invitation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    revoke(&state, &id, None).await
+}
+
+// Whoever signs up with an organization's invitation joins it as a member
+#[tracing::instrument(name = "Create organization signup invitation", skip_all)]
+pub async fn create_org_signup_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+    Json(request): Json<CreateSignupInvitationRequest>,
+) -> Result<(StatusCode, Json<SignupInvitationResponse>), AuthAPIError> {
+    let org_id = owner_membership(&state, &claims, &org_id).await?;
+    invite(&state, &claims, request, Some(org_id)).await
+}
+
+#[tracing::instrument(name = "List organization signup invitations", skip_all)]
+pub async fn list_org_signup_invitations(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+) -> Result<Json<Vec<SignupInvitationResponse>>, AuthAPIError> {
+    let org_id = owner_membership(&state, &claims, &org_id).await?;
+    list(&state, Some(&org_id)).await
+}
+
+#[tracing::instrument(name = "Revoke organization signup invitation", skip_all)]
+pub async fn revoke_org_signup_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((org_id, id)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let org_id = owner_membership(&state, &claims, &org_id).await?;
+    revoke(&state, &id, Some(&org_id)).await
+}
+
+// Only the invitee learns the token, through the email
+async fn invite(
+    state: &AppState,
+    claims: &Claims,
+    request: CreateSignupInvitationRequest,
+    org_id: Option<OrgId>,
+) -> Result<(StatusCode, Json<SignupInvitationResponse>), AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let (invitation, token) =
+        SignupInvitation::new(email, org_id, caller(claims)?, state.signup_invitation_ttl);
+    state
+        .signup_invitation_store
+        .add_invitation(&invitation)
+        .await
+        .map_err(map_signup_invitation_store_error)?;
+
+    let content = format!(
+        "You have been invited to sign up. Your invitation token is {}; it expires at {}.",
+        token.as_ref().expose_secret(),
+        invitation.expires_at.to_rfc3339(),
+    );
+    state
+        .email_client
+        .send_email(&invitation.email, "Your invitation", &content)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SignupInvitationResponse::from(&invitation)),
+    ))
+}
+
+async fn list(
+    state: &AppState,
+    org_id: Option<&OrgId>,
+) -> Result<Json<Vec<SignupInvitationResponse>>, AuthAPIError> {
+    let invitations = state
+        .signup_invitation_store
+        .list_invitations(org_id)
+        .await
+        .map_err(map_signup_invitation_store_error)?;
+
+    Ok(Json(
+        invitations
+            .iter()
+            .map(SignupInvitationResponse::from)
+            .collect(),
+    ))
+}
+
+async fn revoke(
+    state: &AppState,
+    id: &str,
+    org_id: Option<&OrgId>,
+) -> Result<StatusCode, AuthAPIError> {
+    let id = SignupInvitationId::parse(id).map_err(|_| AuthAPIError::InvitationNotFound)?;
+
+    state
+        .signup_invitation_store
+        .revoke_invitation(&id, org_id)
+        .await
+        .map_err(map_signup_invitation_store_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn owner_membership(
+    state: &AppState,
+    claims: &Claims,
+    org_id: &str,
+) -> Result<OrgId, AuthAPIError> {
+    let (org_id, role) = membership(state, claims, org_id).await?;
+    if role != OrgRole::Owner {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    Ok(org_id)
+}
+
+fn map_signup_invitation_store_error(e: SignupInvitationStoreError) -> AuthAPIError {
+    match e {
+        SignupInvitationStoreError::OrgNotFound => AuthAPIError::NotOrgMember,
+        SignupInvitationStoreError::InvitationNotFound => AuthAPIError::InvitationNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateSignupInvitationRequest {
+    pub email: Secret<String>,
+}
+
+// Never includes the token
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignupInvitationResponse {
+    pub id: String,
+    pub email: String,
+    #[serde(rename = "orgId")]
+    pub org_id: Option<String>,
+    #[serde(rename = "invitedBy")]
+    pub invited_by: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+impl From<&SignupInvitation> for SignupInvitationResponse {
+    fn from(invitation: &SignupInvitation) -> Self {
+        Self {
+            id: invitation.id.to_string(),
+            email: invitation.email.as_ref().expose_secret().clone(),
+            org_id: invitation.org_id.map(|org_id| org_id.to_string()),
+            invited_by: invitation.invited_by.to_string(),
+            created_at: invitation.created_at.to_rfc3339(),
+            expires_at: invitation.expires_at.to_rfc3339(),
+        }
+    }
+}
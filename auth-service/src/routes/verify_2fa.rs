@@ -4,10 +4,14 @@ use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, AuthMethod, Authentication, Email, LoginAttemptId, TwoFACode,
+        TwoFACodeStoreError,
+    },
 };
 
+use super::{login::issue_auth_cookie, resolve_org};
+
 #[tracing::instrument(name = "Verify 2FA code", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
@@ -20,27 +24,56 @@ pub async fn verify_2fa(
     let two_fa_code =
         TwoFACode::parse(request.two_fa_code.into()).map_err(|_| AuthAPIError::InvalidCredentials)?; // Validate the 2FA code in `request`
 
-    // TODO: reduce the lock scope here and everywhere else
-    // see https://discord.com/channels/818251276378701824/1433205499775680594/1434195659958784220
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    redeem_2fa_code(&state, &email, &login_attempt_id, &two_fa_code).await?;
+
+    let user = state
+        .user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    // the account may have been suspended since the code was sent
+    user.ensure_can_log_in()?;
+
+    let org_id = resolve_org(&state, request.org_id.as_deref(), &user.id).await?;
+    let audience = state.token_settings.audience(request.audience.as_deref())?;
+    let authentication = Authentication::now(vec![AuthMethod::Password, AuthMethod::EmailOtp]);
+    let auth_cookie =
+        issue_auth_cookie(&state, &user.id, org_id.as_ref(), audience, &authentication).await?;
+
+    if let Err(e) = state.user_store.record_login(&user.id).await {
+        tracing::error!("Failed to record login: {:?}", e);
+    }
+    let updated_jar = jar.add(auth_cookie);
+
+    Ok((StatusCode::OK, updated_jar))
+}
+
+// Uses up the code sent to `email`, if it is the one given
+pub(super) async fn redeem_2fa_code(
+    state: &AppState,
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    two_fa_code: &TwoFACode,
+) -> Result<(), AuthAPIError> {
+    let two_fa_code_store = &state.two_fa_code_store;
     let code_tuple = two_fa_code_store
-        .get_code(&email)
+        .get_code(email)
         .await
         .map_err(|_| AuthAPIError::IncorrectCredentials)?;
 
-    if !(code_tuple.0 == login_attempt_id && code_tuple.1 == two_fa_code) {
+    if !(code_tuple.0 == *login_attempt_id && code_tuple.1 == *two_fa_code) {
         return Err(AuthAPIError::IncorrectCredentials);
     }
 
+    // Without a lock around get + remove, only the request that actually removes the code
+    // may succeed, so a code can't be redeemed twice by concurrent requests
     two_fa_code_store
-        .remove_code(&email)
+        .remove_code(email)
         .await
-        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
-
-    let auth_cookie = generate_auth_cookie(&email).map_err(AuthAPIError::UnexpectedError)?;
-    let updated_jar = jar.add(auth_cookie);
-
-    Ok((StatusCode::OK, updated_jar))
+        .map_err(|e| match e {
+            TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::IncorrectCredentials,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })
 }
 
 #[derive(Deserialize)]
@@ -50,4 +83,9 @@ pub struct Verify2FARequest {
     login_attempt_id: String,
     #[serde(rename = "2FACode")]
     two_fa_code: String,
+    // Same as for `/login`
+    #[serde(rename = "orgId", default)]
+    org_id: Option<String>,
+    #[serde(default)]
+    audience: Option<String>,
 }
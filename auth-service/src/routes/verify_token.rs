@@ -1,21 +1,165 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use serde::Deserialize;
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::auth::validate_token};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, PersonalAccessTokenSecret},
+    utils::{
+        auth::{validate_personal_access_token, validate_token, Claims},
+        auth_token::AuthToken,
+    },
+};
 
 #[tracing::instrument(name = "Verify auth token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
-    Json(request): Json<VerifyTokenRequest>,
+    Query(query): Query<AudienceQuery>,
+    AuthToken(token): AuthToken,
+) -> Result<StatusCode, AuthAPIError> {
+    authenticate(&state, &token, query.audience.as_deref()).await?;
+
+    Ok(StatusCode::OK)
+}
+
+// Lets relying services authorise by permission, not only by the token being valid. The token
+// comes from the app's token sources like on the other routes, the body's `token` field being
+// the body source
+#[tracing::instrument(name = "Verify auth token permission", skip_all)]
+pub async fn verify_token_permission(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<VerifyTokenPermissionRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    validate_token(&request.token.into(), state.banned_token_store.clone())
-        .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let AuthToken(token) =
+        AuthToken::from_headers_or_body(&headers, request.token, &state.token_sources)?;
+    let claims = authenticate(&state, &token, request.audience.as_deref()).await?;
+
+    if !claims.has_permission(&request.permission) {
+        return Err(AuthAPIError::MissingPermission);
+    }
 
     Ok(StatusCode::OK)
 }
 
+// Describes the token to relying services; tokens that would be rejected are only reported
+// as inactive, but leaving out a required audience is an error
+#[tracing::instrument(name = "Introspect auth token", skip_all)]
+pub async fn introspect_token(
+    State(state): State<AppState>,
+    Query(query): Query<AudienceQuery>,
+    AuthToken(token): AuthToken,
+) -> Result<Json<IntrospectionResponse>, AuthAPIError> {
+    match authenticate(&state, &token, query.audience.as_deref()).await {
+        Ok(claims) => Ok(Json(IntrospectionResponse::active(
+            claims,
+            token_type(&token),
+        ))),
+        Err(e @ (AuthAPIError::MissingAudience | AuthAPIError::UnexpectedError(_))) => Err(e),
+        Err(_) => Ok(Json(IntrospectionResponse::default())),
+    }
+}
+
+// Accepts both the tokens issued at login and personal access tokens. A relying app names
+// itself as the `audience` so that tokens issued for other apps are rejected; it may only leave
+// it out when tokens are issued for a single app
+pub(super) async fn authenticate(
+    state: &AppState,
+    token: &Secret<String>,
+    audience: Option<&str>,
+) -> Result<Claims, AuthAPIError> {
+    let claims = if PersonalAccessTokenSecret::is_personal_access_token(token) {
+        validate_personal_access_token(
+            token,
+            state.personal_access_token_store.clone(),
+            state.user_store.clone(),
+            state.role_store.clone(),
+            &state.token_settings,
+        )
+        .await?
+    } else {
+        validate_token(
+            token,
+            state.banned_token_store.clone(),
+            state.user_store.clone(),
+            state.org_store.clone(),
+            &state.token_settings,
+        )
+        .await?
+    };
+
+    state.token_settings.check_audience(&claims, audience)?;
+
+    Ok(claims)
+}
+
+fn token_type(token: &Secret<String>) -> &'static str {
+    if PersonalAccessTokenSecret::is_personal_access_token(token) {
+        "personal_access_token"
+    } else {
+        "access_token"
+    }
+}
+
 #[derive(Deserialize)]
-pub struct VerifyTokenRequest {
-    pub token: String,
+pub struct AudienceQuery {
+    pub audience: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyTokenPermissionRequest {
+    #[serde(default)]
+    pub token: Option<Secret<String>>,
+    pub permission: String,
+    #[serde(default)]
+    pub audience: Option<String>,
+}
+
+// Field names follow RFC 7662
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+}
+
+impl IntrospectionResponse {
+    fn active(claims: Claims, token_type: &str) -> Self {
+        Self {
+            active: true,
+            iss: Some(claims.iss),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            scope: Some(claims.scope),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            nbf: Some(claims.nbf),
+            jti: Some(claims.jti),
+            token_type: Some(token_type.to_owned()),
+            org_id: claims.org_id,
+        }
+    }
 }
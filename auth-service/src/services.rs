@@ -1,4 +1,10 @@
+pub mod clock;
 pub mod data_stores;
+pub mod hashing_pool;
+pub mod hibp_breached_password_checker;
 pub mod mock_email_client;
+pub mod password_hasher;
 pub mod postmark_email_client;
+pub mod redis_connection;
 pub mod resend_email_client;
+pub mod user_import;
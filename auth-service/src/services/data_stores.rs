@@ -1,13 +1,91 @@
+mod cached_banned_token_store;
+mod hashmap_org_store;
+mod hashmap_personal_access_token_store;
+mod hashmap_role_store;
+mod hashmap_signup_invitation_store;
 mod hashmap_two_fa_code_store;
 mod hashmap_user_store;
 mod hashset_banned_token_store;
+#[cfg(feature = "postgres")]
+mod postgres_banned_token_store;
+#[cfg(feature = "postgres")]
+mod postgres_org_store;
+#[cfg(feature = "postgres")]
+mod postgres_personal_access_token_store;
+#[cfg(feature = "postgres")]
+mod postgres_role_store;
+#[cfg(feature = "postgres")]
+mod postgres_signup_invitation_store;
+#[cfg(feature = "postgres")]
+mod postgres_two_fa_code_store;
+#[cfg(feature = "postgres")]
 mod postgres_user_store;
 mod redis_banned_token_store;
 mod redis_two_fa_code_store;
+#[cfg(feature = "sqlite")]
+mod sqlite_org_store;
+#[cfg(feature = "sqlite")]
+mod sqlite_personal_access_token_store;
+#[cfg(feature = "sqlite")]
+mod sqlite_role_store;
+#[cfg(feature = "sqlite")]
+mod sqlite_signup_invitation_store;
+#[cfg(feature = "sqlite")]
+mod sqlite_user_store;
+mod ttl_map;
 
+pub use cached_banned_token_store::*;
+pub use hashmap_org_store::*;
+pub use hashmap_personal_access_token_store::*;
+pub use hashmap_role_store::*;
+pub use hashmap_signup_invitation_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
 pub use hashset_banned_token_store::*;
+#[cfg(feature = "postgres")]
+pub use postgres_banned_token_store::*;
+#[cfg(feature = "postgres")]
+pub use postgres_org_store::*;
+#[cfg(feature = "postgres")]
+pub use postgres_personal_access_token_store::*;
+#[cfg(feature = "postgres")]
+pub use postgres_role_store::*;
+#[cfg(feature = "postgres")]
+pub use postgres_signup_invitation_store::*;
+#[cfg(feature = "postgres")]
+pub use postgres_two_fa_code_store::*;
+#[cfg(feature = "postgres")]
 pub use postgres_user_store::*;
 pub use redis_banned_token_store::*;
 pub use redis_two_fa_code_store::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_org_store::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_personal_access_token_store::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_role_store::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_signup_invitation_store::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_user_store::*;
+
+// A LIKE pattern matching canonical emails that contain `search`; `\` is the escape character
+fn email_search_pattern(search: &str) -> String {
+    let escaped = search
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn email_search_pattern_escapes_wildcards() {
+        assert_eq!(email_search_pattern("Example"), "%example%");
+        assert_eq!(email_search_pattern("a_b%c\\d"), "%a\\_b\\%c\\\\d%");
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use color_eyre::eyre::{eyre, Context, Result};
+use futures_util::StreamExt;
+use tokio::task::JoinHandle;
+
+use super::{redis_banned_token_store::BanMessage, ttl_map::TtlMap, RedisBannedTokenStore};
+use crate::{
+    app_state::BannedTokenStoreType,
+    domain::{BannedTokenStore, BannedTokenStoreError, Clock},
+    services::{clock::SystemClock, redis_connection::RedisConfig},
+};
+
+// Keeps every live ban in memory so that token validation needs no round trip to the store.
+// Replicas learn about each other's bans through Redis pub/sub; while the subscription is down
+// the local set may be incomplete, so misses fall back to the wrapped store until it resyncs.
+pub struct CachedBannedTokenStore {
+    inner: BannedTokenStoreType,
+    banned: Arc<TtlMap<String, ()>>,
+    synced: Arc<AtomicBool>,
+}
+
+impl CachedBannedTokenStore {
+    pub fn new(inner: BannedTokenStoreType) -> Self {
+        Self::with_clock(inner, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(inner: BannedTokenStoreType, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner,
+            banned: Arc::new(TtlMap::new(clock)),
+            synced: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Expired bans are never reported; this only frees the memory they take
+    pub fn spawn_eviction(&self, interval: Duration) -> JoinHandle<()> {
+        self.banned.spawn_eviction(interval)
+    }
+
+    // Follows the bans published by every replica, resubscribing whenever the connection drops
+    pub fn spawn_sync(&self, source: RedisBannedTokenStore, config: RedisConfig) -> JoinHandle<()> {
+        let banned = self.banned.clone();
+        let synced = self.synced.clone();
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = sync(&source, &config, &banned, &synced).await {
+                    tracing::error!("Revocation cache out of sync: {:?}", e);
+                }
+                synced.store(false, Ordering::Release);
+                tokio::time::sleep(RESYNC_DELAY).await;
+            }
+        })
+    }
+}
+
+async fn sync(
+    source: &RedisBannedTokenStore,
+    config: &RedisConfig,
+    banned: &TtlMap<String, ()>,
+    synced: &AtomicBool,
+) -> Result<()> {
+    let mut pubsub = config
+        .pubsub_client()
+        .await
+        .wrap_err("failed to find a Redis node to subscribe to")?
+        .get_async_pubsub()
+        .await
+        .wrap_err("failed to connect to Redis pub/sub")?;
+    pubsub
+        .subscribe(source.channel())
+        .await
+        .wrap_err("failed to subscribe to banned tokens")?;
+
+    // subscribed first, so that no ban falls between the snapshot and the first message
+    for (jti, ttl) in source.banned_jtis().await? {
+        banned.insert(jti, (), ttl).await;
+    }
+    synced.store(true, Ordering::Release);
+    tracing::info!("Revocation cache synced");
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        match message.get_payload::<String>() {
+            Ok(payload) => apply(banned, &payload).await,
+            Err(e) => tracing::warn!("Ignoring unreadable ban message: {:?}", e),
+        }
+    }
+
+    Err(eyre!("Redis pub/sub connection closed"))
+}
+
+async fn apply(banned: &TtlMap<String, ()>, payload: &str) {
+    match serde_json::from_str::<BanMessage>(payload) {
+        Ok(ban) => {
+            banned
+                .insert(ban.jti, (), Duration::from_millis(ban.ttl_ms))
+                .await
+        }
+        Err(e) => tracing::warn!("Ignoring malformed ban message: {:?}", e),
+    }
+}
+
+#[async_trait]
+impl BannedTokenStore for CachedBannedTokenStore {
+    async fn add_token(&self, jti: &str, ttl: Duration) -> Result<(), BannedTokenStoreError> {
+        self.inner.add_token(jti, ttl).await?;
+        self.banned.insert(jti.to_owned(), (), ttl).await;
+        Ok(())
+    }
+
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        if self.banned.get(jti).await.is_some() {
+            return Ok(true);
+        }
+        if self.synced.load(Ordering::Acquire) {
+            return Ok(false);
+        }
+        self.inner.contains_token(jti).await
+    }
+}
+
+const RESYNC_DELAY: Duration = Duration::from_secs(1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{clock::MockClock, data_stores::HashsetBannedTokenStore};
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn store() -> (
+        CachedBannedTokenStore,
+        Arc<HashsetBannedTokenStore>,
+        Arc<MockClock>,
+    ) {
+        let clock = Arc::new(MockClock::new());
+        let inner = Arc::new(HashsetBannedTokenStore::new(clock.clone()));
+        let store = CachedBannedTokenStore::with_clock(inner.clone(), clock.clone());
+        (store, inner, clock)
+    }
+
+    #[tokio::test]
+    async fn bans_are_written_through_and_cached() {
+        let (store, inner, clock) = store();
+        store.add_token("jti1", TTL).await.unwrap();
+
+        assert!(inner.contains_token("jti1").await.unwrap());
+        assert!(store.banned.get("jti1").await.is_some());
+
+        clock.advance(TTL);
+        assert!(!store.contains_token("jti1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn misses_fall_back_to_the_store_until_synced() {
+        let (store, inner, _) = store();
+        // banned by another replica, before this one subscribed
+        inner.add_token("jti1", TTL).await.unwrap();
+
+        assert!(store.contains_token("jti1").await.unwrap());
+
+        store.synced.store(true, Ordering::Release);
+        assert!(!store.contains_token("jti1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn published_bans_are_applied() {
+        let (store, _, clock) = store();
+        store.synced.store(true, Ordering::Release);
+
+        apply(&store.banned, r#"{"jti":"jti1","ttl_ms":60000}"#).await;
+        apply(&store.banned, "not a ban").await;
+
+        assert!(store.contains_token("jti1").await.unwrap());
+        clock.advance(TTL);
+        assert!(!store.contains_token("jti1").await.unwrap());
+    }
+}
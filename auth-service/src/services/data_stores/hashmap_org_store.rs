@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    Email, Invitation, InvitationId, Membership, OrgId, OrgRole, OrgStore, OrgStoreError,
+    Organization, UserId,
+};
+
+// Doesn't know about users, so any user id can become a member
+#[derive(Default)]
+pub struct HashmapOrgStore {
+    state: RwLock<State>,
+}
+
+#[derive(Default)]
+struct State {
+    orgs: HashMap<OrgId, Organization>,
+    members: HashMap<OrgId, Vec<Membership>>,
+    invitations: HashMap<InvitationId, Invitation>,
+}
+
+impl State {
+    fn members(&self, org_id: &OrgId) -> Result<&Vec<Membership>, OrgStoreError> {
+        self.members.get(org_id).ok_or(OrgStoreError::OrgNotFound)
+    }
+
+    fn member(&self, org_id: &OrgId, user_id: &UserId) -> Result<&Membership, OrgStoreError> {
+        self.members
+            .get(org_id)
+            .and_then(|members| members.iter().find(|member| member.user_id == *user_id))
+            .ok_or(OrgStoreError::NotMember)
+    }
+
+    // `role` is what the member is about to become; `None` when they are being removed
+    fn ensure_not_last_owner(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: Option<OrgRole>,
+    ) -> Result<(), OrgStoreError> {
+        let member = self.member(org_id, user_id)?;
+        let owners = self.members[org_id]
+            .iter()
+            .filter(|member| member.role == OrgRole::Owner)
+            .count();
+        if member.role == OrgRole::Owner && role != Some(OrgRole::Owner) && owners == 1 {
+            return Err(OrgStoreError::LastOwner);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrgStore for HashmapOrgStore {
+    async fn create_org(&self, org: &Organization, owner: &UserId) -> Result<(), OrgStoreError> {
+        let mut state = self.state.write().await;
+        state.orgs.insert(org.id, org.clone());
+        state.members.insert(
+            org.id,
+            vec![Membership {
+                user_id: *owner,
+                role: OrgRole::Owner,
+                joined_at: org.created_at,
+            }],
+        );
+        Ok(())
+    }
+
+    async fn get_org(&self, id: &OrgId) -> Result<Organization, OrgStoreError> {
+        self.state
+            .read()
+            .await
+            .orgs
+            .get(id)
+            .cloned()
+            .ok_or(OrgStoreError::OrgNotFound)
+    }
+
+    async fn list_user_orgs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<(Organization, OrgRole)>, OrgStoreError> {
+        let state = self.state.read().await;
+        let mut orgs: Vec<_> = state
+            .members
+            .iter()
+            .filter_map(|(org_id, members)| {
+                let member = members.iter().find(|member| member.user_id == *user_id)?;
+                Some((
+                    member.joined_at,
+                    state.orgs.get(org_id)?.clone(),
+                    member.role,
+                ))
+            })
+            .collect();
+        orgs.sort_by_key(|(joined_at, _, _)| *joined_at);
+
+        Ok(orgs.into_iter().map(|(_, org, role)| (org, role)).collect())
+    }
+
+    async fn get_role(&self, org_id: &OrgId, user_id: &UserId) -> Result<OrgRole, OrgStoreError> {
+        self.state
+            .read()
+            .await
+            .member(org_id, user_id)
+            .map(|member| member.role)
+    }
+
+    async fn list_members(&self, org_id: &OrgId) -> Result<Vec<Membership>, OrgStoreError> {
+        self.state.read().await.members(org_id).cloned()
+    }
+
+    async fn set_role(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError> {
+        let mut state = self.state.write().await;
+        state.ensure_not_last_owner(org_id, user_id, Some(role))?;
+        if let Some(member) = state
+            .members
+            .get_mut(org_id)
+            .and_then(|members| members.iter_mut().find(|member| member.user_id == *user_id))
+        {
+            member.role = role;
+        }
+        Ok(())
+    }
+
+    async fn remove_member(&self, org_id: &OrgId, user_id: &UserId) -> Result<(), OrgStoreError> {
+        let mut state = self.state.write().await;
+        state.ensure_not_last_owner(org_id, user_id, None)?;
+        if let Some(members) = state.members.get_mut(org_id) {
+            members.retain(|member| member.user_id != *user_id);
+        }
+        Ok(())
+    }
+
+    async fn add_member(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError> {
+        let mut state = self.state.write().await;
+        let members = state
+            .members
+            .get_mut(org_id)
+            .ok_or(OrgStoreError::OrgNotFound)?;
+        if !members.iter().any(|member| member.user_id == *user_id) {
+            members.push(Membership {
+                user_id: *user_id,
+                role,
+                joined_at: Utc::now(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn add_invitation(&self, invitation: &Invitation) -> Result<(), OrgStoreError> {
+        let mut state = self.state.write().await;
+        if !state.orgs.contains_key(&invitation.org_id) {
+            return Err(OrgStoreError::OrgNotFound);
+        }
+        if state
+            .invitations
+            .values()
+            .any(|pending| pending.org_id == invitation.org_id && pending.email == invitation.email)
+        {
+            return Err(OrgStoreError::InvitationAlreadyExists);
+        }
+
+        state.invitations.insert(invitation.id, invitation.clone());
+        Ok(())
+    }
+
+    async fn list_invitations(&self, org_id: &OrgId) -> Result<Vec<Invitation>, OrgStoreError> {
+        let mut invitations: Vec<_> = self
+            .state
+            .read()
+            .await
+            .invitations
+            .values()
+            .filter(|invitation| invitation.org_id == *org_id)
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|invitation| invitation.created_at);
+        Ok(invitations)
+    }
+
+    async fn revoke_invitation(
+        &self,
+        org_id: &OrgId,
+        id: &InvitationId,
+    ) -> Result<(), OrgStoreError> {
+        let mut state = self.state.write().await;
+        match state.invitations.get(id) {
+            Some(invitation) if invitation.org_id == *org_id => {
+                state.invitations.remove(id);
+                Ok(())
+            }
+            _ => Err(OrgStoreError::InvitationNotFound),
+        }
+    }
+
+    async fn list_invitations_for(&self, email: &Email) -> Result<Vec<Invitation>, OrgStoreError> {
+        let mut invitations: Vec<_> = self
+            .state
+            .read()
+            .await
+            .invitations
+            .values()
+            .filter(|invitation| invitation.email == *email)
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|invitation| invitation.created_at);
+        Ok(invitations)
+    }
+
+    async fn accept_invitation(
+        &self,
+        id: &InvitationId,
+        email: &Email,
+        user_id: &UserId,
+    ) -> Result<OrgId, OrgStoreError> {
+        let mut state = self.state.write().await;
+        let invitation = match state.invitations.get(id) {
+            Some(invitation) if invitation.email == *email => state
+                .invitations
+                .remove(id)
+                .ok_or(OrgStoreError::InvitationNotFound)?,
+            _ => return Err(OrgStoreError::InvitationNotFound),
+        };
+
+        let members = state.members.entry(invitation.org_id).or_default();
+        if !members.iter().any(|member| member.user_id == *user_id) {
+            members.push(Membership {
+                user_id: *user_id,
+                role: invitation.role,
+                joined_at: Utc::now(),
+            });
+        }
+        Ok(invitation.org_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned().into()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn the_last_owner_cannot_leave_or_be_demoted() {
+        let store = HashmapOrgStore::default();
+        let owner = UserId::default();
+        let org = Organization::new("Acme".to_owned());
+        store.create_org(&org, &owner).await.unwrap();
+
+        assert_eq!(
+            store.set_role(&org.id, &owner, OrgRole::Admin).await,
+            Err(OrgStoreError::LastOwner)
+        );
+        assert_eq!(
+            store.remove_member(&org.id, &owner).await,
+            Err(OrgStoreError::LastOwner)
+        );
+
+        let invitation =
+            Invitation::new(org.id, email("second@example.com"), OrgRole::Owner, owner);
+        store.add_invitation(&invitation).await.unwrap();
+        let second = UserId::default();
+        store
+            .accept_invitation(&invitation.id, &email("Second@Example.com"), &second)
+            .await
+            .unwrap();
+
+        store.remove_member(&org.id, &owner).await.unwrap();
+        assert_eq!(
+            store.get_role(&org.id, &owner).await,
+            Err(OrgStoreError::NotMember)
+        );
+        assert_eq!(store.get_role(&org.id, &second).await, Ok(OrgRole::Owner));
+    }
+
+    #[tokio::test]
+    async fn invitations_are_scoped_to_their_organization_and_invitee() {
+        let store = HashmapOrgStore::default();
+        let owner = UserId::default();
+        let (acme, globex) = (
+            Organization::new("Acme".to_owned()),
+            Organization::new("Globex".to_owned()),
+        );
+        store.create_org(&acme, &owner).await.unwrap();
+        store.create_org(&globex, &owner).await.unwrap();
+
+        let invitation = Invitation::new(acme.id, email("new@example.com"), OrgRole::Member, owner);
+        store.add_invitation(&invitation).await.unwrap();
+        assert_eq!(
+            store.add_invitation(&invitation).await,
+            Err(OrgStoreError::InvitationAlreadyExists)
+        );
+        assert!(store.list_invitations(&globex.id).await.unwrap().is_empty());
+        assert_eq!(
+            store.revoke_invitation(&globex.id, &invitation.id).await,
+            Err(OrgStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store
+                .accept_invitation(&invitation.id, &email("other@example.com"), &owner)
+                .await,
+            Err(OrgStoreError::InvitationNotFound)
+        );
+
+        store
+            .revoke_invitation(&acme.id, &invitation.id)
+            .await
+            .unwrap();
+        assert!(store
+            .list_invitations_for(&email("new@example.com"))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
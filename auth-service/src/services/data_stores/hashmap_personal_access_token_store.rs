@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    PersonalAccessToken, PersonalAccessTokenId, PersonalAccessTokenStore,
+    PersonalAccessTokenStoreError, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapPersonalAccessTokenStore {
+    tokens: RwLock<HashMap<PersonalAccessTokenId, PersonalAccessToken>>,
+}
+
+#[async_trait]
+impl PersonalAccessTokenStore for HashmapPersonalAccessTokenStore {
+    async fn add_token(
+        &self,
+        token: &PersonalAccessToken,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        self.tokens.write().await.insert(token.id, token.clone());
+        Ok(())
+    }
+
+    async fn list_tokens(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PersonalAccessToken>, PersonalAccessTokenStoreError> {
+        let mut tokens: Vec<_> = self
+            .tokens
+            .read()
+            .await
+            .values()
+            .filter(|token| token.user_id == *user_id)
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|token| token.created_at);
+        Ok(tokens)
+    }
+
+    async fn revoke_token(
+        &self,
+        user_id: &UserId,
+        id: &PersonalAccessTokenId,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        let mut tokens = self.tokens.write().await;
+        match tokens.get(id) {
+            Some(token) if token.user_id == *user_id => {
+                tokens.remove(id);
+                Ok(())
+            }
+            _ => Err(PersonalAccessTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn get_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<PersonalAccessToken, PersonalAccessTokenStoreError> {
+        self.tokens
+            .read()
+            .await
+            .values()
+            .find(|token| token.token_hash == token_hash)
+            .cloned()
+            .ok_or(PersonalAccessTokenStoreError::TokenNotFound)
+    }
+
+    async fn record_use(
+        &self,
+        id: &PersonalAccessTokenId,
+        used_at: DateTime<Utc>,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        let mut tokens = self.tokens.write().await;
+        let token = tokens
+            .get_mut(id)
+            .ok_or(PersonalAccessTokenStoreError::TokenNotFound)?;
+        token.last_used_at = Some(used_at);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn token(user_id: UserId) -> PersonalAccessToken {
+        PersonalAccessToken::new(user_id, "ci".to_owned(), Vec::new(), Duration::days(1)).0
+    }
+
+    #[tokio::test]
+    async fn tokens_are_found_by_hash_and_record_their_use() {
+        let store = HashmapPersonalAccessTokenStore::default();
+        let token = token(UserId::default());
+        store.add_token(&token).await.unwrap();
+
+        let used_at = Utc::now();
+        store.record_use(&token.id, used_at).await.unwrap();
+
+        let found = store.get_token_by_hash(&token.token_hash).await.unwrap();
+        assert_eq!(found.id, token.id);
+        assert_eq!(found.last_used_at, Some(used_at));
+        assert_eq!(
+            store.get_token_by_hash("unknown").await,
+            Err(PersonalAccessTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn users_only_see_and_revoke_their_own_tokens() {
+        let store = HashmapPersonalAccessTokenStore::default();
+        let (alice, bob) = (UserId::default(), UserId::default());
+        let token = token(alice);
+        store.add_token(&token).await.unwrap();
+
+        assert!(store.list_tokens(&bob).await.unwrap().is_empty());
+        assert_eq!(
+            store.revoke_token(&bob, &token.id).await,
+            Err(PersonalAccessTokenStoreError::TokenNotFound)
+        );
+
+        assert_eq!(
+            store.list_tokens(&alice).await.unwrap(),
+            std::slice::from_ref(&token)
+        );
+        store.revoke_token(&alice, &token.id).await.unwrap();
+        assert!(store.list_tokens(&alice).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap};
+
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    Grants, Permission, PermissionDefinition, RoleDefinition, RoleName, RoleStore, RoleStoreError,
+    UserId,
+};
+
+// Doesn't know about users, so roles can be assigned to any user id
+pub struct HashmapRoleStore {
+    state: RwLock<State>,
+}
+
+#[derive(Default)]
+struct State {
+    roles: BTreeMap<RoleName, StoredRole>,
+    permissions: BTreeMap<Permission, String>,
+    user_roles: HashMap<UserId, BTreeSet<RoleName>>,
+}
+
+struct StoredRole {
+    description: String,
+    permissions: BTreeSet<Permission>,
+}
+
+impl Default for HashmapRoleStore {
+    // Starts out with the built-in admin role, like the database does
+    fn default() -> Self {
+        let mut state = State::default();
+        state.roles.insert(
+            RoleName::admin(),
+            StoredRole {
+                description: "Manages users, roles and permissions".to_owned(),
+                permissions: BTreeSet::new(),
+            },
+        );
+
+        Self {
+            state: RwLock::new(state),
+        }
+    }
+}
+
+#[async_trait]
+impl RoleStore for HashmapRoleStore {
+    async fn add_role(&self, name: &RoleName, description: &str) -> Result<(), RoleStoreError> {
+        match self.state.write().await.roles.entry(name.clone()) {
+            Entry::Occupied(_) => Err(RoleStoreError::RoleAlreadyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(StoredRole {
+                    description: description.to_owned(),
+                    permissions: BTreeSet::new(),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    async fn delete_role(&self, name: &RoleName) -> Result<(), RoleStoreError> {
+        let mut state = self.state.write().await;
+        state
+            .roles
+            .remove(name)
+            .ok_or(RoleStoreError::RoleNotFound)?;
+
+        for roles in state.user_roles.values_mut() {
+            roles.remove(name);
+        }
+        Ok(())
+    }
+
+    async fn list_roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError> {
+        Ok(self
+            .state
+            .read()
+            .await
+            .roles
+            .iter()
+            .map(|(name, role)| RoleDefinition {
+                name: name.clone(),
+                description: role.description.clone(),
+                permissions: role.permissions.iter().cloned().collect(),
+            })
+            .collect())
+    }
+
+    async fn add_permission(
+        &self,
+        name: &Permission,
+        description: &str,
+    ) -> Result<(), RoleStoreError> {
+        match self.state.write().await.permissions.entry(name.clone()) {
+            Entry::Occupied(_) => Err(RoleStoreError::PermissionAlreadyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(description.to_owned());
+                Ok(())
+            }
+        }
+    }
+
+    async fn delete_permission(&self, name: &Permission) -> Result<(), RoleStoreError> {
+        let mut state = self.state.write().await;
+        state
+            .permissions
+            .remove(name)
+            .ok_or(RoleStoreError::PermissionNotFound)?;
+
+        for role in state.roles.values_mut() {
+            role.permissions.remove(name);
+        }
+        Ok(())
+    }
+
+    async fn list_permissions(&self) -> Result<Vec<PermissionDefinition>, RoleStoreError> {
+        Ok(self
+            .state
+            .read()
+            .await
+            .permissions
+            .iter()
+            .map(|(name, description)| PermissionDefinition {
+                name: name.clone(),
+                description: description.clone(),
+            })
+            .collect())
+    }
+
+    async fn grant_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        let mut state = self.state.write().await;
+        if !state.permissions.contains_key(permission) {
+            return Err(RoleStoreError::PermissionNotFound);
+        }
+
+        state
+            .roles
+            .get_mut(role)
+            .ok_or(RoleStoreError::RoleNotFound)?
+            .permissions
+            .insert(permission.clone());
+        Ok(())
+    }
+
+    async fn revoke_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        self.state
+            .write()
+            .await
+            .roles
+            .get_mut(role)
+            .ok_or(RoleStoreError::RoleNotFound)?
+            .permissions
+            .remove(permission);
+        Ok(())
+    }
+
+    async fn assign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError> {
+        let mut state = self.state.write().await;
+        if !state.roles.contains_key(role) {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        state
+            .user_roles
+            .entry(*user_id)
+            .or_default()
+            .insert(role.clone());
+        Ok(())
+    }
+
+    async fn unassign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError> {
+        if let Some(roles) = self.state.write().await.user_roles.get_mut(user_id) {
+            roles.remove(role);
+        }
+        Ok(())
+    }
+
+    async fn get_grants(&self, user_id: &UserId) -> Result<Grants, RoleStoreError> {
+        let state = self.state.read().await;
+        let Some(roles) = state.user_roles.get(user_id) else {
+            return Ok(Grants::default());
+        };
+
+        let permissions: BTreeSet<_> = roles
+            .iter()
+            .filter_map(|role| state.roles.get(role))
+            .flat_map(|role| role.permissions.iter().cloned())
+            .collect();
+
+        Ok(Grants {
+            roles: roles.iter().cloned().collect(),
+            permissions: permissions.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(name: &str) -> RoleName {
+        RoleName::parse(name).unwrap()
+    }
+
+    fn permission(name: &str) -> Permission {
+        Permission::parse(name).unwrap()
+    }
+
+    #[tokio::test]
+    async fn grants_combine_the_permissions_of_all_assigned_roles() {
+        let store = HashmapRoleStore::default();
+        let user_id = UserId::default();
+        for name in ["reports:read", "reports:write"] {
+            store.add_permission(&permission(name), "").await.unwrap();
+        }
+        for name in ["reader", "writer"] {
+            store.add_role(&role(name), "").await.unwrap();
+        }
+        store
+            .grant_permission(&role("reader"), &permission("reports:read"))
+            .await
+            .unwrap();
+        for name in ["reports:read", "reports:write"] {
+            store
+                .grant_permission(&role("writer"), &permission(name))
+                .await
+                .unwrap();
+        }
+        store.assign_role(&user_id, &role("reader")).await.unwrap();
+        store.assign_role(&user_id, &role("writer")).await.unwrap();
+
+        let grants = store.get_grants(&user_id).await.unwrap();
+        assert_eq!(grants.roles, [role("reader"), role("writer")]);
+        assert_eq!(
+            grants.permissions,
+            [permission("reports:read"), permission("reports:write")]
+        );
+
+        store.delete_role(&role("writer")).await.unwrap();
+        let grants = store.get_grants(&user_id).await.unwrap();
+        assert_eq!(grants.roles, [role("reader")]);
+        assert_eq!(grants.permissions, [permission("reports:read")]);
+
+        store
+            .delete_permission(&permission("reports:read"))
+            .await
+            .unwrap();
+        assert!(store
+            .get_grants(&user_id)
+            .await
+            .unwrap()
+            .permissions
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn unknown_roles_and_permissions_are_rejected() {
+        let store = HashmapRoleStore::default();
+
+        assert_eq!(
+            store.assign_role(&UserId::default(), &role("ghost")).await,
+            Err(RoleStoreError::RoleNotFound)
+        );
+        assert_eq!(
+            store
+                .grant_permission(&RoleName::admin(), &permission("ghost"))
+                .await,
+            Err(RoleStoreError::PermissionNotFound)
+        );
+        assert_eq!(
+            store.add_role(&RoleName::admin(), "").await,
+            Err(RoleStoreError::RoleAlreadyExists)
+        );
+    }
+}
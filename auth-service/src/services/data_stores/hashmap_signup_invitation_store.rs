@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    OrgId, SignupInvitation, SignupInvitationId, SignupInvitationStore, SignupInvitationStoreError,
+};
+
+// Doesn't know about organizations, so invitations can name any organization id
+#[derive(Default)]
+pub struct HashmapSignupInvitationStore {
+    invitations: RwLock<HashMap<SignupInvitationId, SignupInvitation>>,
+}
+
+#[async_trait]
+impl SignupInvitationStore for HashmapSignupInvitationStore {
+    async fn add_invitation(
+        &self,
+        invitation: &SignupInvitation,
+    ) -> Result<(), SignupInvitationStoreError> {
+        self.invitations
+            .write()
+            .await
+            .insert(invitation.id, invitation.clone());
+        Ok(())
+    }
+
+    async fn list_invitations(
+        &self,
+        org_id: Option<&OrgId>,
+    ) -> Result<Vec<SignupInvitation>, SignupInvitationStoreError> {
+        let mut invitations: Vec<_> = self
+            .invitations
+            .read()
+            .await
+            .values()
+            .filter(|invitation| org_id.is_none() || invitation.org_id.as_ref() == org_id)
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|invitation| invitation.created_at);
+        Ok(invitations)
+    }
+
+    async fn revoke_invitation(
+        &self,
+        id: &SignupInvitationId,
+        org_id: Option<&OrgId>,
+    ) -> Result<(), SignupInvitationStoreError> {
+        let mut invitations = self.invitations.write().await;
+        match invitations.get(id) {
+            Some(invitation) if org_id.is_none() || invitation.org_id.as_ref() == org_id => {
+                invitations.remove(id);
+                Ok(())
+            }
+            _ => Err(SignupInvitationStoreError::InvitationNotFound),
+        }
+    }
+
+    async fn get_invitation_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<SignupInvitation, SignupInvitationStoreError> {
+        self.invitations
+            .read()
+            .await
+            .values()
+            .find(|invitation| invitation.token_hash == token_hash)
+            .cloned()
+            .ok_or(SignupInvitationStoreError::InvitationNotFound)
+    }
+
+    async fn consume_invitation(
+        &self,
+        id: &SignupInvitationId,
+    ) -> Result<(), SignupInvitationStoreError> {
+        self.invitations
+            .write()
+            .await
+            .remove(id)
+            .map(|_| ())
+            .ok_or(SignupInvitationStoreError::InvitationNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::domain::{Email, UserId};
+
+    fn invitation(org_id: Option<OrgId>) -> SignupInvitation {
+        let email = Email::parse("new@example.com".to_owned().into()).unwrap();
+        SignupInvitation::new(email, org_id, UserId::default(), Duration::days(1)).0
+    }
+
+    #[tokio::test]
+    async fn invitations_can_be_used_only_once() {
+        let store = HashmapSignupInvitationStore::default();
+        let invitation = invitation(None);
+        store.add_invitation(&invitation).await.unwrap();
+
+        let found = store
+            .get_invitation_by_token_hash(&invitation.token_hash)
+            .await
+            .unwrap();
+        assert_eq!(found, invitation);
+
+        store.consume_invitation(&invitation.id).await.unwrap();
+        assert_eq!(
+            store.consume_invitation(&invitation.id).await,
+            Err(SignupInvitationStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store
+                .get_invitation_by_token_hash(&invitation.token_hash)
+                .await,
+            Err(SignupInvitationStoreError::InvitationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn organizations_only_see_and_revoke_their_own_invitations() {
+        let store = HashmapSignupInvitationStore::default();
+        let (acme, globex) = (OrgId::default(), OrgId::default());
+        let open = invitation(None);
+        let acme_invitation = invitation(Some(acme));
+        store.add_invitation(&open).await.unwrap();
+        store.add_invitation(&acme_invitation).await.unwrap();
+
+        assert_eq!(store.list_invitations(None).await.unwrap().len(), 2);
+        assert_eq!(
+            store.list_invitations(Some(&acme)).await.unwrap(),
+            std::slice::from_ref(&acme_invitation)
+        );
+        assert_eq!(
+            store.revoke_invitation(&open.id, Some(&acme)).await,
+            Err(SignupInvitationStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store
+                .revoke_invitation(&acme_invitation.id, Some(&globex))
+                .await,
+            Err(SignupInvitationStoreError::InvitationNotFound)
+        );
+
+        store
+            .revoke_invitation(&acme_invitation.id, Some(&acme))
+            .await
+            .unwrap();
+        store.revoke_invitation(&open.id, None).await.unwrap();
+        assert!(store.list_invitations(None).await.unwrap().is_empty());
+    }
+}
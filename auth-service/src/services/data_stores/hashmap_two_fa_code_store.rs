@@ -1,27 +1,55 @@
-use std::collections::HashMap;
+use std::{sync::Arc, time::Duration};
 
-use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+use tokio::task::JoinHandle;
 
-#[derive(Default, Clone)]
+use super::ttl_map::TtlMap;
+use crate::{
+    domain::{Clock, Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    services::clock::SystemClock,
+};
+
+// Codes expire after the same TTL as in `RedisTwoFACodeStore`
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: Arc<TtlMap<Email, (LoginAttemptId, TwoFACode)>>,
+}
+
+impl HashmapTwoFACodeStore {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            codes: Arc::new(TtlMap::new(clock)),
+        }
+    }
+
+    // Expired codes are never returned; this only frees the memory they take
+    pub fn spawn_eviction(&self, interval: Duration) -> JoinHandle<()> {
+        self.codes.spawn_eviction(interval)
+    }
+}
+
+impl Default for HashmapTwoFACodeStore {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemClock))
+    }
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        self.codes
+            .insert(email, (login_attempt_id, code), TEN_MINUTES)
+            .await;
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
         self.codes
             .remove(email)
+            .await
             .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)
             .map(|_| ())
     }
@@ -32,20 +60,20 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
         self.codes
             .get(email)
-            .cloned()
+            .await
             .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)
     }
 }
 
+const TEN_MINUTES: Duration = Duration::from_secs(600);
+
 #[cfg(test)]
 mod tests {
     use super::*; // adjust to your module structure
-    use std::collections::HashMap;
+    use crate::services::clock::MockClock;
 
     fn make_store() -> HashmapTwoFACodeStore {
-        HashmapTwoFACodeStore {
-            codes: HashMap::new(),
-        }
+        HashmapTwoFACodeStore::default()
     }
 
     fn make_sample_data() -> (Email, LoginAttemptId, TwoFACode) {
@@ -58,7 +86,7 @@ mod tests {
 
     #[tokio::test]
     async fn add_and_get_code_success() {
-        let mut store = make_store();
+        let store = make_store();
         let (email, attempt_id, code) = make_sample_data();
 
         store
@@ -86,7 +114,7 @@ mod tests {
 
     #[tokio::test]
     async fn remove_code_success() {
-        let mut store = make_store();
+        let store = make_store();
         let (email, attempt_id, code) = make_sample_data();
 
         store
@@ -105,7 +133,7 @@ mod tests {
 
     #[tokio::test]
     async fn remove_code_not_found() {
-        let mut store = make_store();
+        let store = make_store();
         let email = Email::parse("unknown@example.com".to_owned().into()).expect("Must be valid email");
 
         let err = store.remove_code(&email).await.unwrap_err();
@@ -114,7 +142,7 @@ mod tests {
 
     #[tokio::test]
     async fn overwrite_existing_code() {
-        let mut store = make_store();
+        let store = make_store();
         let (email, attempt_id1, code1) = make_sample_data();
         let attempt_id2 = LoginAttemptId::default(); // new UUID
         let code2 = TwoFACode::parse("999999".to_owned().into()).expect("Must be valid 2FA code");
@@ -132,4 +160,19 @@ mod tests {
         assert_eq!(stored_attempt_id, attempt_id2);
         assert_eq!(stored_code, code2);
     }
+
+    #[tokio::test]
+    async fn code_expires_after_ten_minutes() {
+        let clock = Arc::new(MockClock::new());
+        let store = HashmapTwoFACodeStore::new(clock.clone());
+        let (email, attempt_id, code) = make_sample_data();
+
+        store.add_code(email.clone(), attempt_id, code).await.unwrap();
+
+        clock.advance(TEN_MINUTES);
+        let err = store.get_code(&email).await.unwrap_err();
+        assert!(matches!(err, TwoFACodeStoreError::LoginAttemptIdNotFound));
+        let err = store.remove_code(&email).await.unwrap_err();
+        assert!(matches!(err, TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
 }
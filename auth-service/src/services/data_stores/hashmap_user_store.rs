@@ -1,34 +1,58 @@
 use async_trait::async_trait;
-use std::collections::HashMap;
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use std::collections::{hash_map::Entry, HashMap};
+use tokio::sync::RwLock;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::domain::{
+    Email, Password, PasswordReset, PasswordResetToken, StatusChange, StatusTransition, User,
+    UserId, UserPage, UserQuery, UserStore, UserStoreError, UserUpdate,
+};
 
-#[derive(Default, Clone)]
+#[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: RwLock<HashMap<Email, User>>,
+    status_history: RwLock<HashMap<UserId, Vec<StatusTransition>>>,
+    password_resets: RwLock<HashMap<UserId, PasswordReset>>,
 }
 
 #[async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        self.users
-            .insert(user.email.clone(), user)
-            .map_or(Ok(()), |_| Err(UserStoreError::UserAlreadyExists))
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        match self.users.write().await.entry(user.email.clone()) {
+            Entry::Occupied(_) => Err(UserStoreError::UserAlreadyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(user);
+                Ok(())
+            }
+        }
     }
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         self.users
+            .read()
+            .await
             .get(email)
             .ok_or(UserStoreError::UserNotFound)
             .cloned()
     }
 
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        self.users
+            .read()
+            .await
+            .values()
+            .find(|user| &user.id == id)
+            .ok_or(UserStoreError::UserNotFound)
+            .cloned()
+    }
+
     async fn validate_user(
         &self,
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
-        if let Some(user) = self.users.get(email) {
+        if let Some(user) = self.users.read().await.get(email) {
             (&user.email == email && &user.password == password)
                 .then_some(())
                 .ok_or(UserStoreError::InvalidCredentials)
@@ -36,16 +60,188 @@ impl UserStore for HashmapUserStore {
             Err(UserStoreError::UserNotFound)
         }
     }
+
+    async fn record_login(&self, id: &UserId) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users
+            .values_mut()
+            .find(|user| &user.id == id)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.last_login_at = Some(Utc::now());
+        Ok(())
+    }
+
+    async fn set_status(&self, id: &UserId, change: StatusChange) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users
+            .values_mut()
+            .find(|user| &user.id == id)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        let now = Utc::now();
+        let transition = StatusTransition {
+            from: user.status,
+            to: change.status,
+            reason: change.reason,
+            actor: change.actor,
+            changed_at: now,
+        };
+        user.status = change.status;
+        user.updated_at = now;
+
+        self.status_history
+            .write()
+            .await
+            .entry(*id)
+            .or_default()
+            .push(transition);
+        Ok(())
+    }
+
+    async fn get_status_history(
+        &self,
+        id: &UserId,
+    ) -> Result<Vec<StatusTransition>, UserStoreError> {
+        self.get_user_by_id(id).await?;
+
+        Ok(self
+            .status_history
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let search = query.email.as_ref().map(|email| email.to_lowercase());
+        let mut users: Vec<User> = self
+            .users
+            .read()
+            .await
+            .values()
+            .filter(|user| {
+                search.as_ref().is_none_or(|search| {
+                    user.email
+                        .canonical()
+                        .expose_secret()
+                        .contains(search.as_str())
+                })
+            })
+            .cloned()
+            .collect();
+        users.sort_by_key(|user| (user.created_at, *user.id.as_ref()));
+
+        let total = users.len() as u64;
+        let users = users
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .collect();
+
+        Ok(UserPage { users, total })
+    }
+
+    async fn update_user(&self, id: &UserId, update: UserUpdate) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users
+            .values_mut()
+            .find(|user| &user.id == id)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        if let Some(requires_2fa) = update.requires_2fa {
+            user.requires_2fa = requires_2fa;
+        }
+        if let Some(password_reset_required) = update.password_reset_required {
+            user.password_reset_required = password_reset_required;
+        }
+        if let Some(sessions_revoked_at) = update.sessions_revoked_at {
+            user.sessions_revoked_at = Some(sessions_revoked_at);
+        }
+        user.updated_at = Utc::now();
+        Ok(())
+    }
+
+    async fn start_password_reset(
+        &self,
+        id: &UserId,
+        reset: &PasswordReset,
+    ) -> Result<(), UserStoreError> {
+        self.get_user_by_id(id).await?;
+
+        self.password_resets
+            .write()
+            .await
+            .insert(*id, reset.clone());
+        Ok(())
+    }
+
+    async fn complete_password_reset(
+        &self,
+        email: &Email,
+        token: &PasswordResetToken,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users
+            .get_mut(email)
+            .ok_or(UserStoreError::InvalidCredentials)?;
+
+        let now = Utc::now();
+        let mut password_resets = self.password_resets.write().await;
+        match password_resets.get(&user.id) {
+            Some(reset) if reset.token_hash == token.hash() && !reset.is_expired(now) => {
+                password_resets.remove(&user.id);
+            }
+            _ => return Err(UserStoreError::InvalidCredentials),
+        }
+
+        user.password = password;
+        user.password_reset_required = false;
+        user.sessions_revoked_at = Some(now);
+        user.updated_at = now;
+        Ok(())
+    }
+
+    async fn set_password(&self, id: &UserId, password: Password) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users
+            .values_mut()
+            .find(|user| &user.id == id)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.password = password;
+        user.password_reset_required = false;
+        user.updated_at = Utc::now();
+        self.password_resets.write().await.remove(id);
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: &UserId) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let email = users
+            .values()
+            .find(|user| &user.id == id)
+            .map(|user| user.email.clone())
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        users.remove(&email);
+        self.status_history.write().await.remove(id);
+        self.password_resets.write().await.remove(id);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::AccountStatus;
 
     #[tokio::test]
     async fn test_add_user_new() {
-        let mut store = HashmapUserStore::default();
-        assert_eq!(store.users.len(), 0);
+        let store = HashmapUserStore::default();
+        assert_eq!(store.users.read().await.len(), 0);
 
         let user = User::new(
             Email::parse("test@example.com".to_owned().into()).unwrap(),
@@ -54,12 +250,12 @@ mod tests {
         );
 
         store.add_user(user.clone()).await.unwrap();
-        assert_eq!(store.users.len(), 1);
+        assert_eq!(store.users.read().await.len(), 1);
     }
 
     #[tokio::test]
     async fn test_add_user_if_exists() {
-        let mut store = HashmapUserStore::default();
+        let store = HashmapUserStore::default();
         let user = User::new(
             Email::parse("test@example.com".to_owned().into()).unwrap(),
             Password::parse("password123".to_owned().into()).unwrap(),
@@ -75,7 +271,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user_if_exists() {
-        let mut store = HashmapUserStore::default();
+        let store = HashmapUserStore::default();
         let user = User::new(
             Email::parse("test@example.com".to_owned().into()).unwrap(),
             Password::parse("password123".to_owned().into()).unwrap(),
@@ -103,7 +299,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_user_valid() {
-        let mut store = HashmapUserStore::default();
+        let store = HashmapUserStore::default();
         let user = User::new(
             Email::parse("test@example.com".to_owned().into()).unwrap(),
             Password::parse("password123".to_owned().into()).unwrap(),
@@ -122,7 +318,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_user_invalid() {
-        let mut store = HashmapUserStore::default();
+        let store = HashmapUserStore::default();
         let user = User::new(
             Email::parse("test@example.com".to_owned().into()).unwrap(),
             Password::parse("password123".to_owned().into()).unwrap(),
@@ -155,4 +351,165 @@ mod tests {
             Err(UserStoreError::UserNotFound)
         );
     }
+
+    #[tokio::test]
+    async fn test_record_login() {
+        let store = HashmapUserStore::default();
+        let user = User::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            Password::parse("password123".to_owned().into()).unwrap(),
+            false,
+        );
+        store.add_user(user.clone()).await.unwrap();
+
+        store.record_login(&user.id).await.unwrap();
+        assert!(store
+            .get_user(&user.email)
+            .await
+            .unwrap()
+            .last_login_at
+            .is_some());
+
+        assert_eq!(
+            store.record_login(&UserId::default()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_status_records_transitions() {
+        let store = HashmapUserStore::default();
+        let user = User::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            Password::parse("password123".to_owned().into()).unwrap(),
+            false,
+        );
+        store.add_user(user.clone()).await.unwrap();
+
+        let lock = StatusChange {
+            status: AccountStatus::Locked,
+            reason: "suspicious activity".to_owned(),
+            actor: "admin".to_owned(),
+        };
+        store.set_status(&user.id, lock).await.unwrap();
+
+        let stored = store.get_user_by_id(&user.id).await.unwrap();
+        assert_eq!(stored.status, AccountStatus::Locked);
+
+        let history = store.get_status_history(&user.id).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].from, AccountStatus::Active);
+        assert_eq!(history[0].to, AccountStatus::Locked);
+        assert_eq!(history[0].reason, "suspicious activity");
+        assert_eq!(history[0].actor, "admin");
+
+        assert_eq!(
+            store.get_status_history(&UserId::default()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_users_pages_and_searches() {
+        let store = HashmapUserStore::default();
+        for email in ["alice@example.com", "bob@example.com", "Carol@Example.org"] {
+            let user = User::new(
+                Email::parse(email.to_owned().into()).unwrap(),
+                Password::parse("password123".to_owned().into()).unwrap(),
+                false,
+            );
+            store.add_user(user).await.unwrap();
+        }
+
+        let query = UserQuery {
+            email: None,
+            offset: 1,
+            limit: 1,
+        };
+        let page = store.list_users(&query).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.users.len(), 1);
+
+        let query = UserQuery {
+            email: Some("EXAMPLE.ORG".to_owned()),
+            offset: 0,
+            limit: 10,
+        };
+        let page = store.list_users(&query).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(
+            page.users[0].email.as_ref().expose_secret(),
+            "Carol@Example.org"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_user_changes_only_given_fields() {
+        let store = HashmapUserStore::default();
+        let user = User::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            Password::parse("password123".to_owned().into()).unwrap(),
+            true,
+        );
+        store.add_user(user.clone()).await.unwrap();
+
+        let update = UserUpdate {
+            requires_2fa: Some(false),
+            password_reset_required: Some(true),
+            ..Default::default()
+        };
+        store.update_user(&user.id, update).await.unwrap();
+
+        let stored = store.get_user_by_id(&user.id).await.unwrap();
+        assert!(!stored.requires_2fa);
+        assert!(stored.password_reset_required);
+        assert!(stored.sessions_revoked_at.is_none());
+
+        assert_eq!(
+            store
+                .update_user(&UserId::default(), UserUpdate::default())
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn password_reset_sets_the_password_once() {
+        let store = HashmapUserStore::default();
+        let mut user = User::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            Password::parse("password123".to_owned().into()).unwrap(),
+            false,
+        );
+        user.password_reset_required = true;
+        store.add_user(user.clone()).await.unwrap();
+
+        let new_password = Password::parse("new-password123".to_owned().into()).unwrap();
+        let (reset, token) = PasswordReset::new(chrono::Duration::hours(1));
+        store.start_password_reset(&user.id, &reset).await.unwrap();
+        assert_eq!(
+            store
+                .complete_password_reset(
+                    &user.email,
+                    &PasswordResetToken::default(),
+                    new_password.clone()
+                )
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        store
+            .complete_password_reset(&user.email, &token, new_password.clone())
+            .await
+            .unwrap();
+
+        let stored = store.get_user(&user.email).await.unwrap();
+        assert!(!stored.password_reset_required);
+        assert_eq!(stored.password, new_password);
+        assert_eq!(
+            store
+                .complete_password_reset(&user.email, &token, user.password)
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
 }
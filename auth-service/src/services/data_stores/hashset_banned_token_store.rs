@@ -1,57 +1,96 @@
-use std::collections::HashSet;
+use std::{sync::Arc, time::Duration};
 
-use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+use super::ttl_map::TtlMap;
+use crate::{
+    domain::{BannedTokenStore, BannedTokenStoreError, Clock},
+    services::clock::SystemClock,
+};
 use async_trait::async_trait;
-use secrecy::{ExposeSecret, Secret};
+use tokio::task::JoinHandle;
 
-#[derive(Default, Clone)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    tokens: Arc<TtlMap<String, ()>>,
+}
+
+impl HashsetBannedTokenStore {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            tokens: Arc::new(TtlMap::new(clock)),
+        }
+    }
+
+    // Expired tokens are never reported as banned; this only frees the memory they take
+    pub fn spawn_eviction(&self, interval: Duration) -> JoinHandle<()> {
+        self.tokens.spawn_eviction(interval)
+    }
+}
+
+impl Default for HashsetBannedTokenStore {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemClock))
+    }
 }
 
 #[async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+    async fn add_token(&self, jti: &str, ttl: Duration) -> Result<(), BannedTokenStoreError> {
         self.tokens
-            .insert(token.expose_secret().clone())
+            .insert_new(jti.to_owned(), (), ttl)
+            .await
             .then_some(())
             .ok_or(BannedTokenStoreError::AlreadyExists)
     }
 
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.tokens.contains(token.expose_secret()))
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        Ok(self.tokens.get(jti).await.is_some())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::clock::MockClock;
+
+    const TTL: Duration = Duration::from_secs(60);
 
     #[tokio::test]
     async fn test_add_token() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::default();
 
         // Test adding new token
-        let result = store.add_token("token1".to_owned().into()).await;
+        let result = store.add_token("jti1", TTL).await;
         assert!(result.is_ok());
 
         // Test adding duplicate token
-        let result = store.add_token("token1".to_owned().into()).await;
+        let result = store.add_token("jti1", TTL).await;
         assert!(matches!(result, Err(BannedTokenStoreError::AlreadyExists)));
     }
 
     #[tokio::test]
     async fn test_token_exists() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::default();
 
         // Test non-existent token
-        assert!(!store.contains_token(&"token1".to_owned().into()).await.expect("Failed to check token existence"));
+        assert!(!store.contains_token("jti1").await.expect("Failed to check token existence"));
 
         // Add token and test existence
-        store.add_token("token1".to_owned().into()).await.expect("Failed to add token");
-        assert!(store.contains_token(&"token1".to_owned().into()).await.expect("Failed to check token existence"));
+        store.add_token("jti1", TTL).await.expect("Failed to add token");
+        assert!(store.contains_token("jti1").await.expect("Failed to check token existence"));
 
         // Test different non-existent token
-        assert!(!store.contains_token(&"token2".to_owned().into()).await.expect("Failed to check token existence"));
+        assert!(!store.contains_token("jti2").await.expect("Failed to check token existence"));
+    }
+
+    #[tokio::test]
+    async fn token_is_unbanned_once_its_ttl_has_passed() {
+        let clock = Arc::new(MockClock::new());
+        let store = HashsetBannedTokenStore::new(clock.clone());
+        store.add_token("jti1", TTL).await.expect("Failed to add token");
+
+        clock.advance(TTL);
+        assert!(!store.contains_token("jti1").await.expect("Failed to check token existence"));
+
+        // An expired ban can be added again
+        assert!(store.add_token("jti1", TTL).await.is_ok());
     }
 }
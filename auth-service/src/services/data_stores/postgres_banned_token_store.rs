@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use color_eyre::eyre::{Context, Result};
+use sqlx::PgPool;
+
+use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Expired rows are ignored by lookups; this only reclaims the space they take
+    #[tracing::instrument(name = "Purging expired banned tokens from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM banned_tokens
+                WHERE expires_at <= NOW()
+                "#
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to purge expired banned tokens")?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to PostgreSQL", skip_all)]
+    async fn add_token(&self, jti: &str, ttl: Duration) -> Result<(), BannedTokenStoreError> {
+        let ttl = ttl.as_secs_f64();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO banned_tokens (jti, expires_at)
+                VALUES ($1, NOW() + make_interval(secs => $2))
+                ON CONFLICT (jti) DO NOTHING
+                "#,
+            jti,
+            ttl
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to insert banned token")
+        .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        let is_banned = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1
+                    FROM banned_tokens
+                    WHERE jti = $1 AND expires_at > NOW()
+                ) AS "is_banned!"
+                "#,
+            jti
+        )
+        .fetch_one(&self.pool)
+        .await
+        .wrap_err("failed to check if token is banned")
+        .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(is_banned)
+    }
+}
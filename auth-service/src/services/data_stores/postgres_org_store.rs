@@ -0,0 +1,411 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+use secrecy::ExposeSecret;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{
+    Email, Invitation, InvitationId, Membership, OrgId, OrgRole, OrgStore, OrgStoreError,
+    Organization, UserId,
+};
+
+pub struct PostgresOrgStore {
+    pool: PgPool,
+}
+
+impl PostgresOrgStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OrgStore for PostgresOrgStore {
+    #[tracing::instrument(name = "Creating organization in PostgreSQL", skip_all)]
+    async fn create_org(&self, org: &Organization, owner: &UserId) -> Result<(), OrgStoreError> {
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+
+        sqlx::query!(
+            "INSERT INTO organizations (id, name, created_at) VALUES ($1, $2, $3)",
+            org.id.as_ref(),
+            org.name,
+            org.created_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        sqlx::query!(
+            "INSERT INTO org_members (org_id, user_id, role, joined_at) VALUES ($1, $2, $3, $4)",
+            org.id.as_ref(),
+            owner.as_ref(),
+            OrgRole::Owner.as_str(),
+            org.created_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)
+    }
+
+    #[tracing::instrument(name = "Retrieving organization from PostgreSQL", skip_all)]
+    async fn get_org(&self, id: &OrgId) -> Result<Organization, OrgStoreError> {
+        sqlx::query!(
+            "SELECT id, name, created_at FROM organizations WHERE id = $1",
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .map(|row| Organization {
+            id: row.id.into(),
+            name: row.name,
+            created_at: row.created_at,
+        })
+        .ok_or(OrgStoreError::OrgNotFound)
+    }
+
+    #[tracing::instrument(name = "Listing user's organizations in PostgreSQL", skip_all)]
+    async fn list_user_orgs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<(Organization, OrgRole)>, OrgStoreError> {
+        sqlx::query!(
+            r#"
+                SELECT o.id, o.name, o.created_at, m.role
+                FROM org_members m
+                JOIN organizations o ON o.id = m.org_id
+                WHERE m.user_id = $1
+                ORDER BY m.joined_at, o.id
+                "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(|row| {
+            let org = Organization {
+                id: row.id.into(),
+                name: row.name,
+                created_at: row.created_at,
+            };
+            Ok((org, parse_role(&row.role)?))
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving organization role from PostgreSQL", skip_all)]
+    async fn get_role(&self, org_id: &OrgId, user_id: &UserId) -> Result<OrgRole, OrgStoreError> {
+        let role = sqlx::query_scalar!(
+            "SELECT role FROM org_members WHERE org_id = $1 AND user_id = $2",
+            org_id.as_ref(),
+            user_id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrgStoreError::NotMember)?;
+
+        parse_role(&role)
+    }
+
+    #[tracing::instrument(name = "Listing organization members in PostgreSQL", skip_all)]
+    async fn list_members(&self, org_id: &OrgId) -> Result<Vec<Membership>, OrgStoreError> {
+        // tells an unknown organization apart from one without members
+        self.get_org(org_id).await?;
+
+        sqlx::query!(
+            r#"
+                SELECT user_id, role, joined_at
+                FROM org_members
+                WHERE org_id = $1
+                ORDER BY joined_at, user_id
+                "#,
+            org_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(|row| {
+            Ok(Membership {
+                user_id: row.user_id.into(),
+                role: parse_role(&row.role)?,
+                joined_at: row.joined_at,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Changing organization role in PostgreSQL", skip_all)]
+    async fn set_role(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError> {
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+        ensure_not_last_owner(&mut tx, org_id, user_id, Some(role)).await?;
+
+        sqlx::query!(
+            "UPDATE org_members SET role = $3 WHERE org_id = $1 AND user_id = $2",
+            org_id.as_ref(),
+            user_id.as_ref(),
+            role.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)
+    }
+
+    #[tracing::instrument(name = "Removing organization member from PostgreSQL", skip_all)]
+    async fn remove_member(&self, org_id: &OrgId, user_id: &UserId) -> Result<(), OrgStoreError> {
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+        ensure_not_last_owner(&mut tx, org_id, user_id, None).await?;
+
+        sqlx::query!(
+            "DELETE FROM org_members WHERE org_id = $1 AND user_id = $2",
+            org_id.as_ref(),
+            user_id.as_ref()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)
+    }
+
+    #[tracing::instrument(name = "Adding organization member to PostgreSQL", skip_all)]
+    async fn add_member(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO org_members (org_id, user_id, role)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+                "#,
+            org_id.as_ref(),
+            user_id.as_ref(),
+            role.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => OrgStoreError::OrgNotFound,
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding invitation to PostgreSQL", skip_all)]
+    async fn add_invitation(&self, invitation: &Invitation) -> Result<(), OrgStoreError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO org_invitations
+                    (id, org_id, email, canonical_email, role, invited_by, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            invitation.id.as_ref(),
+            invitation.org_id.as_ref(),
+            invitation.email.as_ref().expose_secret(),
+            invitation.email.canonical().expose_secret(),
+            invitation.role.as_str(),
+            invitation.invited_by.as_ref(),
+            invitation.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                OrgStoreError::InvitationAlreadyExists
+            }
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => OrgStoreError::OrgNotFound,
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing invitations in PostgreSQL", skip_all)]
+    async fn list_invitations(&self, org_id: &OrgId) -> Result<Vec<Invitation>, OrgStoreError> {
+        sqlx::query_as!(
+            InvitationRow,
+            r#"
+                SELECT id, org_id, email, role, invited_by, created_at
+                FROM org_invitations
+                WHERE org_id = $1
+                ORDER BY created_at, id
+                "#,
+            org_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(Invitation::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking invitation in PostgreSQL", skip_all)]
+    async fn revoke_invitation(
+        &self,
+        org_id: &OrgId,
+        id: &InvitationId,
+    ) -> Result<(), OrgStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM org_invitations WHERE org_id = $1 AND id = $2",
+            org_id.as_ref(),
+            id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(OrgStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing invitee's invitations in PostgreSQL", skip_all)]
+    async fn list_invitations_for(&self, email: &Email) -> Result<Vec<Invitation>, OrgStoreError> {
+        sqlx::query_as!(
+            InvitationRow,
+            r#"
+                SELECT id, org_id, email, role, invited_by, created_at
+                FROM org_invitations
+                WHERE canonical_email = $1
+                ORDER BY created_at, id
+                "#,
+            email.canonical().expose_secret()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(Invitation::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Accepting invitation in PostgreSQL", skip_all)]
+    async fn accept_invitation(
+        &self,
+        id: &InvitationId,
+        email: &Email,
+        user_id: &UserId,
+    ) -> Result<OrgId, OrgStoreError> {
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+
+        let invitation = sqlx::query!(
+            r#"
+                DELETE FROM org_invitations
+                WHERE id = $1 AND canonical_email = $2
+                RETURNING org_id, role
+                "#,
+            id.as_ref(),
+            email.canonical().expose_secret()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrgStoreError::InvitationNotFound)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO org_members (org_id, user_id, role)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+                "#,
+            invitation.org_id,
+            user_id.as_ref(),
+            invitation.role
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)?;
+        Ok(invitation.org_id.into())
+    }
+}
+
+// `role` is what the member is about to become; `None` when they are being removed. Locks
+// the owners' rows, so concurrent changes can't leave the organization without one
+async fn ensure_not_last_owner(
+    tx: &mut Transaction<'_, Postgres>,
+    org_id: &OrgId,
+    user_id: &UserId,
+    role: Option<OrgRole>,
+) -> Result<(), OrgStoreError> {
+    let current = sqlx::query_scalar!(
+        "SELECT role FROM org_members WHERE org_id = $1 AND user_id = $2 FOR UPDATE",
+        org_id.as_ref(),
+        user_id.as_ref()
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(unexpected)?
+    .ok_or(OrgStoreError::NotMember)?;
+
+    if parse_role(&current)? != OrgRole::Owner || role == Some(OrgRole::Owner) {
+        return Ok(());
+    }
+
+    let owners = sqlx::query_scalar!(
+        "SELECT user_id FROM org_members WHERE org_id = $1 AND role = $2 FOR UPDATE",
+        org_id.as_ref(),
+        OrgRole::Owner.as_str()
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(unexpected)?;
+
+    if owners.len() <= 1 {
+        return Err(OrgStoreError::LastOwner);
+    }
+    Ok(())
+}
+
+struct InvitationRow {
+    id: Uuid,
+    org_id: Uuid,
+    email: String,
+    role: String,
+    invited_by: Uuid,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<InvitationRow> for Invitation {
+    type Error = OrgStoreError;
+
+    fn try_from(row: InvitationRow) -> Result<Self, Self::Error> {
+        Ok(Invitation {
+            id: row.id.into(),
+            org_id: row.org_id.into(),
+            email: Email::parse(row.email.into())
+                .map_err(|e| OrgStoreError::UnexpectedError(eyre!(e)))?,
+            role: parse_role(&row.role)?,
+            invited_by: row.invited_by.into(),
+            created_at: row.created_at,
+        })
+    }
+}
+
+fn parse_role(role: &str) -> Result<OrgRole, OrgStoreError> {
+    OrgRole::parse(role).map_err(OrgStoreError::UnexpectedError)
+}
+
+fn unexpected(e: sqlx::Error) -> OrgStoreError {
+    OrgStoreError::UnexpectedError(Report::from(e))
+}
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Report;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    PersonalAccessToken, PersonalAccessTokenId, PersonalAccessTokenStore,
+    PersonalAccessTokenStoreError, UserId,
+};
+
+pub struct PostgresPersonalAccessTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresPersonalAccessTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PersonalAccessTokenStore for PostgresPersonalAccessTokenStore {
+    #[tracing::instrument(name = "Adding personal access token to PostgreSQL", skip_all)]
+    async fn add_token(
+        &self,
+        token: &PersonalAccessToken,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO personal_access_tokens
+                    (id, user_id, name, scope, token_hash, created_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            token.id.as_ref(),
+            token.user_id.as_ref(),
+            token.name,
+            token.scope(),
+            token.token_hash,
+            token.created_at,
+            token.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing personal access tokens in PostgreSQL", skip_all)]
+    async fn list_tokens(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PersonalAccessToken>, PersonalAccessTokenStoreError> {
+        sqlx::query_as!(
+            PersonalAccessTokenRow,
+            r#"
+                SELECT id, user_id, name, scope, token_hash, created_at, expires_at, last_used_at
+                FROM personal_access_tokens
+                WHERE user_id = $1
+                ORDER BY created_at, id
+                "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(PersonalAccessToken::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking personal access token in PostgreSQL", skip_all)]
+    async fn revoke_token(
+        &self,
+        user_id: &UserId,
+        id: &PersonalAccessTokenId,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM personal_access_tokens WHERE id = $1 AND user_id = $2",
+            id.as_ref(),
+            user_id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(PersonalAccessTokenStoreError::TokenNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving personal access token from PostgreSQL", skip_all)]
+    async fn get_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<PersonalAccessToken, PersonalAccessTokenStoreError> {
+        sqlx::query_as!(
+            PersonalAccessTokenRow,
+            r#"
+                SELECT id, user_id, name, scope, token_hash, created_at, expires_at, last_used_at
+                FROM personal_access_tokens
+                WHERE token_hash = $1
+                "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(PersonalAccessTokenStoreError::TokenNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Recording personal access token use in PostgreSQL", skip_all)]
+    async fn record_use(
+        &self,
+        id: &PersonalAccessTokenId,
+        used_at: DateTime<Utc>,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        let result = sqlx::query!(
+            "UPDATE personal_access_tokens SET last_used_at = $2 WHERE id = $1",
+            id.as_ref(),
+            used_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(PersonalAccessTokenStoreError::TokenNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct PersonalAccessTokenRow {
+    id: Uuid,
+    user_id: Uuid,
+    name: String,
+    scope: String,
+    token_hash: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<PersonalAccessTokenRow> for PersonalAccessToken {
+    type Error = PersonalAccessTokenStoreError;
+
+    fn try_from(row: PersonalAccessTokenRow) -> Result<Self, Self::Error> {
+        Ok(PersonalAccessToken {
+            id: row.id.into(),
+            user_id: row.user_id.into(),
+            name: row.name,
+            scopes: PersonalAccessToken::parse_scope(&row.scope)
+                .map_err(PersonalAccessTokenStoreError::UnexpectedError)?,
+            token_hash: row.token_hash,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+        })
+    }
+}
+
+fn unexpected(e: sqlx::Error) -> PersonalAccessTokenStoreError {
+    PersonalAccessTokenStoreError::UnexpectedError(Report::from(e))
+}
@@ -0,0 +1,297 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Report;
+use sqlx::PgPool;
+
+use crate::domain::{
+    Grants, Permission, PermissionDefinition, RoleDefinition, RoleName, RoleStore, RoleStoreError,
+    UserId,
+};
+
+pub struct PostgresRoleStore {
+    pool: PgPool,
+}
+
+impl PostgresRoleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn role_exists(&self, name: &RoleName) -> Result<bool, RoleStoreError> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1) AS "exists!""#,
+            name.as_ref()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(unexpected)
+    }
+
+    async fn permission_exists(&self, name: &Permission) -> Result<bool, RoleStoreError> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM permissions WHERE name = $1) AS "exists!""#,
+            name.as_ref()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(unexpected)
+    }
+}
+
+#[async_trait]
+impl RoleStore for PostgresRoleStore {
+    #[tracing::instrument(name = "Adding role to PostgreSQL", skip_all)]
+    async fn add_role(&self, name: &RoleName, description: &str) -> Result<(), RoleStoreError> {
+        sqlx::query!(
+            "INSERT INTO roles (name, description) VALUES ($1, $2)",
+            name.as_ref(),
+            description
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                RoleStoreError::RoleAlreadyExists
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting role from PostgreSQL", skip_all)]
+    async fn delete_role(&self, name: &RoleName) -> Result<(), RoleStoreError> {
+        let result = sqlx::query!("DELETE FROM roles WHERE name = $1", name.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing roles in PostgreSQL", skip_all)]
+    async fn list_roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT r.name, r.description, rp.permission AS "permission?"
+                FROM roles r
+                LEFT JOIN role_permissions rp ON rp.role = r.name
+                ORDER BY r.name, rp.permission
+                "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        let mut roles: Vec<RoleDefinition> = Vec::new();
+        for row in rows {
+            let name = RoleName::parse(&row.name).map_err(RoleStoreError::UnexpectedError)?;
+            if roles.last().is_none_or(|role| role.name != name) {
+                roles.push(RoleDefinition {
+                    name,
+                    description: row.description,
+                    permissions: Vec::new(),
+                });
+            }
+            if let Some(permission) = row.permission {
+                let permission =
+                    Permission::parse(&permission).map_err(RoleStoreError::UnexpectedError)?;
+                if let Some(role) = roles.last_mut() {
+                    role.permissions.push(permission);
+                }
+            }
+        }
+
+        Ok(roles)
+    }
+
+    #[tracing::instrument(name = "Adding permission to PostgreSQL", skip_all)]
+    async fn add_permission(
+        &self,
+        name: &Permission,
+        description: &str,
+    ) -> Result<(), RoleStoreError> {
+        sqlx::query!(
+            "INSERT INTO permissions (name, description) VALUES ($1, $2)",
+            name.as_ref(),
+            description
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                RoleStoreError::PermissionAlreadyExists
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting permission from PostgreSQL", skip_all)]
+    async fn delete_permission(&self, name: &Permission) -> Result<(), RoleStoreError> {
+        let result = sqlx::query!("DELETE FROM permissions WHERE name = $1", name.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RoleStoreError::PermissionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing permissions in PostgreSQL", skip_all)]
+    async fn list_permissions(&self) -> Result<Vec<PermissionDefinition>, RoleStoreError> {
+        sqlx::query!("SELECT name, description FROM permissions ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(unexpected)?
+            .into_iter()
+            .map(|row| {
+                Ok(PermissionDefinition {
+                    name: Permission::parse(&row.name).map_err(RoleStoreError::UnexpectedError)?,
+                    description: row.description,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Granting permission in PostgreSQL", skip_all)]
+    async fn grant_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        if !self.role_exists(role).await? {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+        if !self.permission_exists(permission).await? {
+            return Err(RoleStoreError::PermissionNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+                INSERT INTO role_permissions (role, permission)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+                "#,
+            role.as_ref(),
+            permission.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking permission in PostgreSQL", skip_all)]
+    async fn revoke_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        if !self.role_exists(role).await? {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        sqlx::query!(
+            "DELETE FROM role_permissions WHERE role = $1 AND permission = $2",
+            role.as_ref(),
+            permission.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Assigning role in PostgreSQL", skip_all)]
+    async fn assign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError> {
+        if !self.role_exists(role).await? {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_roles (user_id, role)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+                "#,
+            user_id.as_ref(),
+            role.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                RoleStoreError::UserNotFound
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Unassigning role in PostgreSQL", skip_all)]
+    async fn unassign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError> {
+        sqlx::query!(
+            "DELETE FROM user_roles WHERE user_id = $1 AND role = $2",
+            user_id.as_ref(),
+            role.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving grants from PostgreSQL", skip_all)]
+    async fn get_grants(&self, user_id: &UserId) -> Result<Grants, RoleStoreError> {
+        let roles = sqlx::query_scalar!(
+            "SELECT role FROM user_roles WHERE user_id = $1 ORDER BY role",
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        let permissions = sqlx::query_scalar!(
+            r#"
+                SELECT DISTINCT rp.permission
+                FROM user_roles ur
+                JOIN role_permissions rp ON rp.role = ur.role
+                WHERE ur.user_id = $1
+                ORDER BY rp.permission
+                "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(Grants {
+            roles: roles
+                .iter()
+                .map(|role| RoleName::parse(role))
+                .collect::<Result<_, _>>()
+                .map_err(RoleStoreError::UnexpectedError)?,
+            permissions: permissions
+                .iter()
+                .map(|permission| Permission::parse(permission))
+                .collect::<Result<_, _>>()
+                .map_err(RoleStoreError::UnexpectedError)?,
+        })
+    }
+}
+
+fn unexpected(e: sqlx::Error) -> RoleStoreError {
+    RoleStoreError::UnexpectedError(Report::from(e))
+}
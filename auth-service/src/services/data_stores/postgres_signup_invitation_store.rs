@@ -0,0 +1,169 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    Email, OrgId, SignupInvitation, SignupInvitationId, SignupInvitationStore,
+    SignupInvitationStoreError,
+};
+
+pub struct PostgresSignupInvitationStore {
+    pool: PgPool,
+}
+
+impl PostgresSignupInvitationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SignupInvitationStore for PostgresSignupInvitationStore {
+    #[tracing::instrument(name = "Adding signup invitation to PostgreSQL", skip_all)]
+    async fn add_invitation(
+        &self,
+        invitation: &SignupInvitation,
+    ) -> Result<(), SignupInvitationStoreError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO signup_invitations
+                    (id, email, org_id, token_hash, invited_by, created_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            invitation.id.as_ref(),
+            invitation.email.as_ref().expose_secret(),
+            invitation.org_id.as_ref().map(AsRef::<Uuid>::as_ref),
+            invitation.token_hash,
+            invitation.invited_by.as_ref(),
+            invitation.created_at,
+            invitation.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                SignupInvitationStoreError::OrgNotFound
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing signup invitations in PostgreSQL", skip_all)]
+    async fn list_invitations(
+        &self,
+        org_id: Option<&OrgId>,
+    ) -> Result<Vec<SignupInvitation>, SignupInvitationStoreError> {
+        sqlx::query_as!(
+            SignupInvitationRow,
+            r#"
+                SELECT id, email, org_id, token_hash, invited_by, created_at, expires_at
+                FROM signup_invitations
+                WHERE $1::uuid IS NULL OR org_id = $1
+                ORDER BY created_at, id
+                "#,
+            org_id.map(AsRef::<Uuid>::as_ref)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(SignupInvitation::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking signup invitation in PostgreSQL", skip_all)]
+    async fn revoke_invitation(
+        &self,
+        id: &SignupInvitationId,
+        org_id: Option<&OrgId>,
+    ) -> Result<(), SignupInvitationStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM signup_invitations WHERE id = $1 AND ($2::uuid IS NULL OR org_id = $2)",
+            id.as_ref(),
+            org_id.map(AsRef::<Uuid>::as_ref)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SignupInvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving signup invitation from PostgreSQL", skip_all)]
+    async fn get_invitation_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<SignupInvitation, SignupInvitationStoreError> {
+        sqlx::query_as!(
+            SignupInvitationRow,
+            r#"
+                SELECT id, email, org_id, token_hash, invited_by, created_at, expires_at
+                FROM signup_invitations
+                WHERE token_hash = $1
+                "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(SignupInvitationStoreError::InvitationNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Consuming signup invitation in PostgreSQL", skip_all)]
+    async fn consume_invitation(
+        &self,
+        id: &SignupInvitationId,
+    ) -> Result<(), SignupInvitationStoreError> {
+        let result = sqlx::query!("DELETE FROM signup_invitations WHERE id = $1", id.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SignupInvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct SignupInvitationRow {
+    id: Uuid,
+    email: String,
+    org_id: Option<Uuid>,
+    token_hash: String,
+    invited_by: Uuid,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl TryFrom<SignupInvitationRow> for SignupInvitation {
+    type Error = SignupInvitationStoreError;
+
+    fn try_from(row: SignupInvitationRow) -> Result<Self, Self::Error> {
+        Ok(SignupInvitation {
+            id: row.id.into(),
+            email: Email::parse(row.email.into())
+                .map_err(|e| SignupInvitationStoreError::UnexpectedError(eyre!(e)))?,
+            org_id: row.org_id.map(OrgId::from),
+            token_hash: row.token_hash,
+            invited_by: row.invited_by.into(),
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+fn unexpected(e: sqlx::Error) -> SignupInvitationStoreError {
+    SignupInvitationStoreError::UnexpectedError(Report::from(e))
+}
@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use color_eyre::eyre::{Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Expired rows are ignored by lookups; this only reclaims the space they take
+    #[tracing::instrument(name = "Purging expired 2FA codes from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM two_fa_codes
+                WHERE expires_at <= NOW()
+                "#
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to purge expired 2FA codes")?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    // A new login attempt replaces the previous code, like in the other stores
+    #[tracing::instrument(name = "Adding 2FA code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let email = email.canonical().expose_secret();
+        let login_attempt_id = login_attempt_id.as_ref().expose_secret();
+        let code = code.as_ref().expose_secret();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+                VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+                ON CONFLICT (email) DO UPDATE
+                SET login_attempt_id = EXCLUDED.login_attempt_id,
+                    code = EXCLUDED.code,
+                    expires_at = EXCLUDED.expires_at
+                "#,
+            email,
+            login_attempt_id,
+            code,
+            TEN_MINUTES_IN_SECONDS
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to insert 2FA code")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from PostgreSQL", skip_all)]
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let email = email.canonical().expose_secret();
+
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM two_fa_codes
+                WHERE email = $1 AND expires_at > NOW()
+                "#,
+            email
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("failed to delete 2FA code")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from PostgreSQL", skip_all)]
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let email = email.canonical().expose_secret();
+
+        let row = sqlx::query!(
+            r#"
+                SELECT login_attempt_id, code
+                FROM two_fa_codes
+                WHERE email = $1 AND expires_at > NOW()
+                "#,
+            email
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("failed to retrieve 2FA code")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let login_attempt_id = LoginAttemptId::parse(Secret::new(row.login_attempt_id))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let code = TwoFACode::parse(Secret::new(row.code))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((login_attempt_id, code))
+    }
+}
+
+const TEN_MINUTES_IN_SECONDS: f64 = 600.0;
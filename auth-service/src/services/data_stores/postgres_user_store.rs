@@ -1,77 +1,226 @@
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
 use async_trait::async_trait;
-use color_eyre::eyre::{eyre, Context, Result};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
 
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use super::email_search_pattern;
+use crate::{
+    domain::{
+        AccountStatus, Email, Password, PasswordReset, PasswordResetToken, StatusChange,
+        StatusTransition, User, UserId, UserPage, UserQuery, UserStore, UserStoreError, UserUpdate,
+    },
+    services::{password_hasher::PasswordHasher, user_import::ImportedUser},
+};
 
 pub struct PostgresUserStore {
     pool: PgPool,
+    hasher: PasswordHasher,
 }
 
 impl PostgresUserStore {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            hasher: PasswordHasher::default(),
+        }
+    }
+
+    pub fn with_password_hasher(mut self, hasher: PasswordHasher) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    // Stores a user migrated from another system with its existing password hash; the hash is
+    // upgraded to Argon2id on the user's next successful login
+    #[tracing::instrument(name = "Importing user to PostgreSQL", skip_all)]
+    pub async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError> {
+        let id = UserId::default();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO users (id, email, canonical_email, password_hash, requires_2fa)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            id.as_ref(),
+            user.email.as_ref().expose_secret(),
+            user.email.canonical().expose_secret(),
+            user.password_hash.expose_secret(),
+            user.requires_2fa
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_insert_error)?;
+
+        Ok(())
+    }
+
+    // The migration that added `canonical_email` could only lowercase the stored addresses,
+    // which is not what `Email::parse` does for internationalized domains, or for local parts
+    // when EMAIL_LOWERCASE_LOCAL_PART is false. Run at startup, this brings every row in line
+    // with the current canonicalization. Fails, changing nothing, if two accounts would end up
+    // with the same canonical email; they have to be merged by hand first
+    #[tracing::instrument(name = "Recanonicalizing emails in PostgreSQL", skip_all)]
+    pub async fn recanonicalize_emails(&self) -> Result<usize> {
+        let mut transaction = self.pool.begin().await?;
+        let rows = sqlx::query!("SELECT id, email, canonical_email FROM users FOR UPDATE")
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        let mut owners = HashMap::new();
+        let mut changes = Vec::new();
+        for row in rows {
+            // Addresses that no longer parse keep their canonical form; they can't log in anyway
+            let canonical = match Email::parse(row.email.into()) {
+                Ok(email) => email.canonical().expose_secret().to_owned(),
+                Err(_) => row.canonical_email.clone(),
+            };
+            if let Some(other) = owners.insert(canonical.clone(), row.id.to_string()) {
+                return Err(eyre!(
+                    "Users {} and {} have the same canonical email",
+                    other,
+                    row.id
+                ));
+            }
+            if canonical != row.canonical_email {
+                changes.push((row.id, canonical));
+            }
+        }
+
+        // Parked on their ids first, so rows swapping canonical forms don't collide midway
+        for (id, _) in &changes {
+            let parked = id.to_string();
+            sqlx::query!(
+                "UPDATE users SET canonical_email = $1 WHERE id = $2",
+                parked,
+                id
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        for (id, canonical) in &changes {
+            sqlx::query!(
+                "UPDATE users SET canonical_email = $1 WHERE id = $2",
+                canonical,
+                id
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(changes.len())
+    }
+
+    // Brings the stored hash up to the configured parameters; the user has already been
+    // authenticated at this point, so a failure here must not fail the login. Only replaces
+    // `verified_hash`: a password set while the new hash was being computed is kept
+    #[tracing::instrument(name = "Rehashing password in PostgreSQL", skip_all)]
+    pub async fn rehash_password(
+        &self,
+        email: &Email,
+        verified_hash: &Secret<String>,
+        password: &Password,
+    ) -> Result<()> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await?;
+
+        sqlx::query!(
+            r#"
+                UPDATE users
+                SET password_hash = $1, updated_at = NOW()
+                WHERE canonical_email = $2 AND password_hash = $3
+                "#,
+            &password_hash.expose_secret(),
+            email.canonical().expose_secret(),
+            verified_hash.expose_secret()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
     }
 }
 
 #[async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let password_hash = compute_password_hash(user.password.as_ref().to_owned())
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(user.password.as_ref().to_owned())
             .await
-            .map_err(UserStoreError::UnexpectedError)?;
+            .map_err(UserStoreError::from)?;
 
         sqlx::query!(
             r#"
-                INSERT INTO users (email, password_hash, requires_2fa)
-                VALUES ($1, $2, $3)
+                INSERT INTO users (
+                    id, email, canonical_email, password_hash, requires_2fa, status,
+                    created_at, updated_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
                 "#,
+            user.id.as_ref(),
             user.email.as_ref().expose_secret(),
+            user.email.canonical().expose_secret(),
             &password_hash.expose_secret(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.status.as_str(),
+            user.created_at,
+            user.updated_at
         )
         .execute(&self.pool)
         .await
-        .map_err(|err| match err {
-            sqlx::Error::Database(err) if err.code().is_some_and(|code| code == "23505") => {
-                UserStoreError::UserAlreadyExists
-            }
-            e => UserStoreError::UnexpectedError(e.into()),
-        })?;
+        .map_err(map_insert_error)?;
 
         Ok(())
     }
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        sqlx::query!(
+        sqlx::query_as!(
+            UserRow,
             r#"
-                SELECT email, password_hash, requires_2fa
+                SELECT
+                    id, email, password_hash, requires_2fa, status,
+                    password_reset_required, sessions_revoked_at,
+                    created_at, updated_at, last_login_at
                 FROM users
-                WHERE email = $1
+                WHERE canonical_email = $1
                 "#,
-            email.as_ref().expose_secret()
+            email.canonical().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Retrieving user by id from PostgreSQL", skip_all)]
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        sqlx::query_as!(
+            UserRow,
+            r#"
+                SELECT
+                    id, email, password_hash, requires_2fa, status,
+                    password_reset_required, sessions_revoked_at,
+                    created_at, updated_at, last_login_at
+                FROM users
+                WHERE id = $1
+                "#,
+            id.as_ref()
         )
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|row| {
-            Ok(User {
-                email: Email::parse(row.email.into())
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(row.password_hash.into())
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
-        })
         .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
     }
 
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
@@ -82,58 +231,357 @@ impl UserStore for PostgresUserStore {
     ) -> Result<(), UserStoreError> {
         let user = self.get_user(email).await?;
 
-        verify_password_hash(
-            user.password.as_ref().to_owned(),
-            password.as_ref().to_owned(),
+        let password_hash: Secret<String> = user.password.as_ref().to_owned();
+
+        self.hasher
+            .verify_password_hash(password_hash.clone(), password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::from)?;
+
+        if self.hasher.needs_rehash(&password_hash) {
+            if let Err(e) = self.rehash_password(email, &password_hash, password).await {
+                tracing::error!("Failed to rehash password: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording login in PostgreSQL", skip_all)]
+    async fn record_login(&self, id: &UserId) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET last_login_at = NOW()
+                WHERE id = $1
+                "#,
+            id.as_ref()
         )
+        .execute(&self.pool)
         .await
-        .map_err(|_| UserStoreError::InvalidCredentials)
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
     }
-}
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(expected_password_hash.expose_secret())?;
-
-            Argon2::default()
-                .verify_password(
-                    password_candidate.expose_secret().as_bytes(),
-                    &expected_password_hash,
-                )
-                .wrap_err("failed to verify password hash")
+    #[tracing::instrument(name = "Changing account status in PostgreSQL", skip_all)]
+    async fn set_status(&self, id: &UserId, change: StatusChange) -> Result<(), UserStoreError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // the row lock keeps concurrent changes from recording the same previous status
+        let recorded = sqlx::query!(
+            r#"
+                INSERT INTO account_status_changes (user_id, from_status, to_status, reason, actor)
+                SELECT id, status, $2, $3, $4
+                FROM users
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+            id.as_ref(),
+            change.status.as_str(),
+            change.reason,
+            change.actor
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if recorded.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+                UPDATE users
+                SET status = $2, updated_at = NOW()
+                WHERE id = $1
+                "#,
+            id.as_ref(),
+            change.status.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving account status history from PostgreSQL", skip_all)]
+    async fn get_status_history(
+        &self,
+        id: &UserId,
+    ) -> Result<Vec<StatusTransition>, UserStoreError> {
+        // fails for unknown users, rather than returning an empty history
+        self.get_user_by_id(id).await?;
+
+        sqlx::query!(
+            r#"
+                SELECT from_status, to_status, reason, actor, changed_at
+                FROM account_status_changes
+                WHERE user_id = $1
+                ORDER BY changed_at, id
+                "#,
+            id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(StatusTransition {
+                from: AccountStatus::parse(&row.from_status)
+                    .map_err(UserStoreError::UnexpectedError)?,
+                to: AccountStatus::parse(&row.to_status)
+                    .map_err(UserStoreError::UnexpectedError)?,
+                reason: row.reason,
+                actor: row.actor,
+                changed_at: row.changed_at,
+            })
         })
-    })
-    .await;
+        .collect()
+    }
 
-    result?
+    #[tracing::instrument(name = "Listing users in PostgreSQL", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let pattern = query.email.as_deref().map(email_search_pattern);
+
+        let users = sqlx::query_as!(
+            UserRow,
+            r#"
+                SELECT
+                    id, email, password_hash, requires_2fa, status,
+                    password_reset_required, sessions_revoked_at,
+                    created_at, updated_at, last_login_at
+                FROM users
+                WHERE $1::TEXT IS NULL OR canonical_email LIKE $1 ESCAPE '\'
+                ORDER BY created_at, id
+                LIMIT $2 OFFSET $3
+                "#,
+            pattern,
+            i64::from(query.limit),
+            i64::from(query.offset)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(User::try_from)
+        .collect::<Result<_, _>>()?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "total!"
+                FROM users
+                WHERE $1::TEXT IS NULL OR canonical_email LIKE $1 ESCAPE '\'
+                "#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(UserPage {
+            users,
+            total: total.unsigned_abs(),
+        })
+    }
+
+    #[tracing::instrument(name = "Updating user in PostgreSQL", skip_all)]
+    async fn update_user(&self, id: &UserId, update: UserUpdate) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET
+                    requires_2fa = COALESCE($2, requires_2fa),
+                    password_reset_required = COALESCE($3, password_reset_required),
+                    sessions_revoked_at = COALESCE($4, sessions_revoked_at),
+                    updated_at = NOW()
+                WHERE id = $1
+                "#,
+            id.as_ref(),
+            update.requires_2fa,
+            update.password_reset_required,
+            update.sessions_revoked_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Starting password reset in PostgreSQL", skip_all)]
+    async fn start_password_reset(
+        &self,
+        id: &UserId,
+        reset: &PasswordReset,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET password_reset_token_hash = $2, password_reset_expires_at = $3
+                WHERE id = $1
+                "#,
+            id.as_ref(),
+            reset.token_hash,
+            reset.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    // The token is checked and used up in the same statement, so it can't be redeemed twice
+    #[tracing::instrument(name = "Completing password reset in PostgreSQL", skip_all)]
+    async fn complete_password_reset(
+        &self,
+        email: &Email,
+        token: &PasswordResetToken,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::from)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET
+                    password_hash = $3,
+                    password_reset_required = FALSE,
+                    password_reset_token_hash = NULL,
+                    password_reset_expires_at = NULL,
+                    sessions_revoked_at = NOW(),
+                    updated_at = NOW()
+                WHERE canonical_email = $1
+                    AND password_reset_token_hash = $2
+                    AND password_reset_expires_at > NOW()
+                "#,
+            email.canonical().expose_secret(),
+            token.hash(),
+            &password_hash.expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting password in PostgreSQL", skip_all)]
+    async fn set_password(&self, id: &UserId, password: Password) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::from)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET
+                    password_hash = $2,
+                    password_reset_required = FALSE,
+                    password_reset_token_hash = NULL,
+                    password_reset_expires_at = NULL,
+                    updated_at = NOW()
+                WHERE id = $1
+                "#,
+            id.as_ref(),
+            &password_hash.expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    // Roles, memberships, invitations and tokens are removed by the foreign keys' cascades
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&self, id: &UserId) -> Result<(), UserStoreError> {
+        let result = sqlx::query!("DELETE FROM users WHERE id = $1", id.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
 }
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
-    let current_span: tracing::Span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
-            )
-            .hash_password(password.expose_secret().as_bytes(), &salt)?
-            .to_string();
+struct UserRow {
+    id: Uuid,
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    status: String,
+    password_reset_required: bool,
+    sessions_revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    last_login_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
 
-            Ok(Secret::new(password_hash))
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            id: row.id.into(),
+            email: Email::parse(row.email.into())
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(row.password_hash.into())
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            status: AccountStatus::parse(&row.status).map_err(UserStoreError::UnexpectedError)?,
+            password_reset_required: row.password_reset_required,
+            sessions_revoked_at: row.sessions_revoked_at,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            last_login_at: row.last_login_at,
         })
-    })
-    .await;
+    }
+}
 
-    result?
+fn map_insert_error(err: sqlx::Error) -> UserStoreError {
+    match err {
+        sqlx::Error::Database(err) if err.code().is_some_and(|code| code == "23505") => {
+            UserStoreError::UserAlreadyExists
+        }
+        e => UserStoreError::UnexpectedError(e.into()),
+    }
 }
@@ -1,67 +1,162 @@
-use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::Utc;
 use color_eyre::eyre::{Result, WrapErr};
-use redis::{Commands, Connection};
-use secrecy::{ExposeSecret, Secret};
-use tokio::sync::RwLock;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     domain::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+    services::redis_connection::RedisConnection,
 };
 
+// Bans live in a single sorted set scored by expiry time, which works the same on a cluster and
+// can be read back in one go to seed a `CachedBannedTokenStore`
+#[derive(Clone)]
 pub struct RedisBannedTokenStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(conn: RedisConnection) -> Self {
         Self { conn }
     }
+
+    // All current bans with their remaining TTL
+    #[tracing::instrument(skip_all)]
+    pub async fn banned_jtis(&self) -> Result<Vec<(String, Duration)>> {
+        let now = now_ms();
+
+        let bans: Vec<(String, f64)> = self
+            .conn
+            .clone()
+            .zrangebyscore_withscores(self.key(), format!("({now}"), "+inf")
+            .await
+            .wrap_err("failed to get banned tokens from Redis")?;
+
+        Ok(bans
+            .into_iter()
+            .map(|(jti, expires_at)| {
+                let ttl = (expires_at as u64).saturating_sub(now);
+                (jti, Duration::from_millis(ttl))
+            })
+            .collect())
+    }
+
+    // Moves the bans recorded one key per jti, before they were kept in a sorted set, into
+    // the set with their remaining TTL. Those keys were never namespaced, and such deployments
+    // were standalone, so a cluster has none to move. Must run before the cache is seeded
+    #[tracing::instrument(skip_all)]
+    pub async fn migrate_legacy_bans(&self) -> Result<usize> {
+        let mut conn = self.conn.clone();
+        let mut legacy_keys = Vec::new();
+        {
+            let mut keys = conn
+                .scan_match::<_, String>(format!("{LEGACY_BANNED_TOKEN_KEY_PREFIX}*"))
+                .await
+                .wrap_err("failed to scan legacy banned tokens in Redis")?;
+            while let Some(key) = keys.next_item().await {
+                legacy_keys.push(key);
+            }
+        }
+
+        let now = now_ms();
+        for legacy_key in &legacy_keys {
+            let jti = &legacy_key[LEGACY_BANNED_TOKEN_KEY_PREFIX.len()..];
+            // negative when the key has just expired, or has no TTL
+            let ttl_ms: i64 = conn
+                .pttl(legacy_key)
+                .await
+                .wrap_err("failed to get TTL of legacy banned token")?;
+
+            let mut pipe = redis::pipe();
+            if ttl_ms > 0 {
+                pipe.zadd(self.key(), jti, now.saturating_add(ttl_ms.unsigned_abs()))
+                    .ignore();
+            }
+            let _: () = pipe
+                .del(legacy_key)
+                .ignore()
+                .query_async(&mut conn)
+                .await
+                .wrap_err("failed to migrate legacy banned token")?;
+        }
+
+        Ok(legacy_keys.len())
+    }
+
+    pub(super) fn channel(&self) -> String {
+        self.conn.key(BANNED_TOKENS_CHANNEL)
+    }
+
+    fn key(&self) -> String {
+        self.conn.key(BANNED_TOKENS_KEY)
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(skip_all)]
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
-
-        let value = true;
+    async fn add_token(&self, jti: &str, ttl: Duration) -> Result<(), BannedTokenStoreError> {
+        let now = now_ms();
+        let ttl_ms = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        let key = self.key();
 
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
+        // expired bans are dropped whenever a new one comes in
+        let _: () = redis::pipe()
+            .zadd(&key, jti, now.saturating_add(ttl_ms))
+            .ignore()
+            .zrembyscore(&key, "-inf", now)
+            .ignore()
+            .query_async(&mut self.conn.clone())
+            .await
+            .wrap_err("failed to add banned token to Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
+        let message = serde_json::to_string(&BanMessage {
+            jti: jti.to_owned(),
+            ttl_ms,
+        })
+        .wrap_err("failed to serialize ban message")
+        .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        // replicas keep their revocation caches up to date from the published bans
         let _: () = self
             .conn
-            .write()
+            .clone()
+            .publish(self.channel(), message)
             .await
-            .set_ex(&token_key, value, ttl)
-            .wrap_err("failed to set banned token in Redis")
+            .wrap_err("failed to publish banned token")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
-
-        let is_banned: bool = self
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        let expires_at: Option<f64> = self
             .conn
-            .write()
+            .clone()
+            .zscore(self.key(), jti)
             .await
-            .exists(&token_key)
-            .wrap_err("failed to check if token exists in Redis")
+            .wrap_err("failed to check if token is banned in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
-        Ok(is_banned)
+        Ok(expires_at.is_some_and(|expires_at| expires_at as u64 > now_ms()))
     }
 }
 
-const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+#[derive(Serialize, Deserialize)]
+pub(super) struct BanMessage {
+    pub jti: String,
+    pub ttl_ms: u64,
+}
+
+const BANNED_TOKENS_KEY: &str = "banned_tokens";
+const BANNED_TOKENS_CHANNEL: &str = "banned_tokens:published";
+// One key per jti, expiring with the ban, as bans were stored before the sorted set
+const LEGACY_BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn now_ms() -> u64 {
+    Utc::now().timestamp_millis().unsigned_abs()
 }
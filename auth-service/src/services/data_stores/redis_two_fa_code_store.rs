@@ -1,21 +1,21 @@
-use std::sync::Arc;
-
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::AsyncCommands;
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
 
-use crate::domain::{
-    Email, {LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+use crate::{
+    domain::{
+        Email, {LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    },
+    services::redis_connection::RedisConnection,
 };
 
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(conn: RedisConnection) -> Self {
         Self { conn }
     }
 }
@@ -24,12 +24,12 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(skip_all)]
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
+        let key = self.conn.key(&get_key(&email));
 
         let data = TwoFATuple(
             login_attempt_id.as_ref().expose_secret().clone(),
@@ -41,9 +41,9 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .set_ex(&key, serialized_data, TEN_MINUTES_IN_SECONDS)
+            .await
             .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
@@ -51,18 +51,21 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     }
 
     #[tracing::instrument(skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let key = self.conn.key(&get_key(email));
 
-        let _: () = self
+        let removed: u64 = self
             .conn
-            .write()
-            .await
+            .clone()
             .del(&key)
+            .await
             .wrap_err("failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        Ok(())
+        match removed {
+            0 => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            _ => Ok(()),
+        }
     }
 
     #[tracing::instrument(skip_all)]
@@ -70,19 +73,19 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
+        let key = self.conn.key(&get_key(email));
 
-        match self.conn.write().await.get::<_, String>(&key) {
+        match self.conn.clone().get::<_, String>(&key).await {
             Ok(value) => {
                 let data: TwoFATuple = serde_json::from_str(&value)
                     .wrap_err("failed to deserialize 2FA tuple")
                     .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                let login_attempt_id =
-                    LoginAttemptId::parse(data.0.into()).map_err(TwoFACodeStoreError::UnexpectedError)?;
+                let login_attempt_id = LoginAttemptId::parse(data.0.into())
+                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                let email_code =
-                    TwoFACode::parse(data.1.into()).map_err(TwoFACodeStoreError::UnexpectedError)?;
+                let email_code = TwoFACode::parse(data.1.into())
+                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
                 Ok((login_attempt_id, email_code))
             }
@@ -98,5 +101,9 @@ const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
 fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+    format!(
+        "{}{}",
+        TWO_FA_CODE_PREFIX,
+        email.canonical().expose_secret()
+    )
 }
@@ -0,0 +1,612 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+use secrecy::ExposeSecret;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+use crate::domain::{
+    Email, Invitation, InvitationId, Membership, OrgId, OrgRole, OrgStore, OrgStoreError,
+    Organization, UserId,
+};
+
+// Single-node alternative to `PostgresOrgStore`
+pub struct SqliteOrgStore {
+    pool: SqlitePool,
+}
+
+impl SqliteOrgStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OrgStore for SqliteOrgStore {
+    #[tracing::instrument(name = "Creating organization in SQLite", skip_all)]
+    async fn create_org(&self, org: &Organization, owner: &UserId) -> Result<(), OrgStoreError> {
+        let id = org.id.to_string();
+        let owner = owner.to_string();
+        let role = OrgRole::Owner.as_str();
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+
+        sqlx::query!(
+            "INSERT INTO organizations (id, name, created_at) VALUES (?, ?, ?)",
+            id,
+            org.name,
+            org.created_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        sqlx::query!(
+            "INSERT INTO org_members (org_id, user_id, role, joined_at) VALUES (?, ?, ?, ?)",
+            id,
+            owner,
+            role,
+            org.created_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)
+    }
+
+    #[tracing::instrument(name = "Retrieving organization from SQLite", skip_all)]
+    async fn get_org(&self, id: &OrgId) -> Result<Organization, OrgStoreError> {
+        let id = id.to_string();
+
+        let row = sqlx::query!(
+            r#"
+                SELECT id, name, created_at AS "created_at: DateTime<Utc>"
+                FROM organizations
+                WHERE id = ?
+                "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrgStoreError::OrgNotFound)?;
+
+        Ok(Organization {
+            id: parse_org_id(&row.id)?,
+            name: row.name,
+            created_at: row.created_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Listing user's organizations in SQLite", skip_all)]
+    async fn list_user_orgs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<(Organization, OrgRole)>, OrgStoreError> {
+        let user_id = user_id.to_string();
+
+        sqlx::query!(
+            r#"
+                SELECT o.id, o.name, o.created_at AS "created_at: DateTime<Utc>", m.role
+                FROM org_members m
+                JOIN organizations o ON o.id = m.org_id
+                WHERE m.user_id = ?
+                ORDER BY m.joined_at, o.id
+                "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(|row| {
+            let org = Organization {
+                id: parse_org_id(&row.id)?,
+                name: row.name,
+                created_at: row.created_at,
+            };
+            Ok((org, parse_role(&row.role)?))
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving organization role from SQLite", skip_all)]
+    async fn get_role(&self, org_id: &OrgId, user_id: &UserId) -> Result<OrgRole, OrgStoreError> {
+        let org_id = org_id.to_string();
+        let user_id = user_id.to_string();
+
+        let role = sqlx::query_scalar!(
+            "SELECT role FROM org_members WHERE org_id = ? AND user_id = ?",
+            org_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrgStoreError::NotMember)?;
+
+        parse_role(&role)
+    }
+
+    #[tracing::instrument(name = "Listing organization members in SQLite", skip_all)]
+    async fn list_members(&self, org_id: &OrgId) -> Result<Vec<Membership>, OrgStoreError> {
+        // tells an unknown organization apart from one without members
+        self.get_org(org_id).await?;
+        let org_id = org_id.to_string();
+
+        sqlx::query!(
+            r#"
+                SELECT user_id, role, joined_at AS "joined_at: DateTime<Utc>"
+                FROM org_members
+                WHERE org_id = ?
+                ORDER BY joined_at, user_id
+                "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(|row| {
+            Ok(Membership {
+                user_id: parse_user_id(&row.user_id)?,
+                role: parse_role(&row.role)?,
+                joined_at: row.joined_at,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Changing organization role in SQLite", skip_all)]
+    async fn set_role(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError> {
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+        ensure_not_last_owner(&mut tx, org_id, user_id, Some(role)).await?;
+        let org_id = org_id.to_string();
+        let user_id = user_id.to_string();
+        let role = role.as_str();
+
+        sqlx::query!(
+            "UPDATE org_members SET role = ? WHERE org_id = ? AND user_id = ?",
+            role,
+            org_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)
+    }
+
+    #[tracing::instrument(name = "Removing organization member from SQLite", skip_all)]
+    async fn remove_member(&self, org_id: &OrgId, user_id: &UserId) -> Result<(), OrgStoreError> {
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+        ensure_not_last_owner(&mut tx, org_id, user_id, None).await?;
+        let org_id = org_id.to_string();
+        let user_id = user_id.to_string();
+
+        sqlx::query!(
+            "DELETE FROM org_members WHERE org_id = ? AND user_id = ?",
+            org_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)
+    }
+
+    #[tracing::instrument(name = "Adding organization member to SQLite", skip_all)]
+    async fn add_member(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        role: OrgRole,
+    ) -> Result<(), OrgStoreError> {
+        let org_id = org_id.to_string();
+        let user_id = user_id.to_string();
+        let role = role.as_str();
+        let joined_at = Utc::now();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO org_members (org_id, user_id, role, joined_at)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT DO NOTHING
+                "#,
+            org_id,
+            user_id,
+            role,
+            joined_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => OrgStoreError::OrgNotFound,
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding invitation to SQLite", skip_all)]
+    async fn add_invitation(&self, invitation: &Invitation) -> Result<(), OrgStoreError> {
+        let id = invitation.id.to_string();
+        let org_id = invitation.org_id.to_string();
+        let email = invitation.email.as_ref().expose_secret();
+        let canonical_email = invitation.email.canonical().expose_secret();
+        let role = invitation.role.as_str();
+        let invited_by = invitation.invited_by.to_string();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO org_invitations
+                    (id, org_id, email, canonical_email, role, invited_by, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            id,
+            org_id,
+            email,
+            canonical_email,
+            role,
+            invited_by,
+            invitation.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                OrgStoreError::InvitationAlreadyExists
+            }
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => OrgStoreError::OrgNotFound,
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing invitations in SQLite", skip_all)]
+    async fn list_invitations(&self, org_id: &OrgId) -> Result<Vec<Invitation>, OrgStoreError> {
+        let org_id = org_id.to_string();
+
+        sqlx::query_as!(
+            InvitationRow,
+            r#"
+                SELECT
+                    id, org_id, email, role, invited_by,
+                    created_at AS "created_at: DateTime<Utc>"
+                FROM org_invitations
+                WHERE org_id = ?
+                ORDER BY created_at, id
+                "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(Invitation::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking invitation in SQLite", skip_all)]
+    async fn revoke_invitation(
+        &self,
+        org_id: &OrgId,
+        id: &InvitationId,
+    ) -> Result<(), OrgStoreError> {
+        let org_id = org_id.to_string();
+        let id = id.to_string();
+
+        let result = sqlx::query!(
+            "DELETE FROM org_invitations WHERE org_id = ? AND id = ?",
+            org_id,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(OrgStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing invitee's invitations in SQLite", skip_all)]
+    async fn list_invitations_for(&self, email: &Email) -> Result<Vec<Invitation>, OrgStoreError> {
+        let canonical_email = email.canonical().expose_secret();
+
+        sqlx::query_as!(
+            InvitationRow,
+            r#"
+                SELECT
+                    id, org_id, email, role, invited_by,
+                    created_at AS "created_at: DateTime<Utc>"
+                FROM org_invitations
+                WHERE canonical_email = ?
+                ORDER BY created_at, id
+                "#,
+            canonical_email
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(Invitation::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Accepting invitation in SQLite", skip_all)]
+    async fn accept_invitation(
+        &self,
+        id: &InvitationId,
+        email: &Email,
+        user_id: &UserId,
+    ) -> Result<OrgId, OrgStoreError> {
+        let id = id.to_string();
+        let canonical_email = email.canonical().expose_secret();
+        let user_id = user_id.to_string();
+        // bound rather than defaulted, so it sorts alongside the owners' `joined_at`
+        let joined_at = Utc::now();
+        let mut tx = self.pool.begin().await.map_err(unexpected)?;
+
+        let invitation = sqlx::query!(
+            r#"
+                DELETE FROM org_invitations
+                WHERE id = ? AND canonical_email = ?
+                RETURNING org_id, role
+                "#,
+            id,
+            canonical_email
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrgStoreError::InvitationNotFound)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO org_members (org_id, user_id, role, joined_at)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT DO NOTHING
+                "#,
+            invitation.org_id,
+            user_id,
+            invitation.role,
+            joined_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(unexpected)?;
+
+        tx.commit().await.map_err(unexpected)?;
+        parse_org_id(&invitation.org_id)
+    }
+}
+
+// `role` is what the member is about to become; `None` when they are being removed
+async fn ensure_not_last_owner(
+    tx: &mut Transaction<'_, Sqlite>,
+    org_id: &OrgId,
+    user_id: &UserId,
+    role: Option<OrgRole>,
+) -> Result<(), OrgStoreError> {
+    let org_id = org_id.to_string();
+    let user_id = user_id.to_string();
+    let owner = OrgRole::Owner.as_str();
+
+    let current = sqlx::query_scalar!(
+        "SELECT role FROM org_members WHERE org_id = ? AND user_id = ?",
+        org_id,
+        user_id
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(unexpected)?
+    .ok_or(OrgStoreError::NotMember)?;
+
+    if parse_role(&current)? != OrgRole::Owner || role == Some(OrgRole::Owner) {
+        return Ok(());
+    }
+
+    let owners = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!: i64" FROM org_members WHERE org_id = ? AND role = ?"#,
+        org_id,
+        owner
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(unexpected)?;
+
+    if owners <= 1 {
+        return Err(OrgStoreError::LastOwner);
+    }
+    Ok(())
+}
+
+struct InvitationRow {
+    id: String,
+    org_id: String,
+    email: String,
+    role: String,
+    invited_by: String,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<InvitationRow> for Invitation {
+    type Error = OrgStoreError;
+
+    fn try_from(row: InvitationRow) -> Result<Self, Self::Error> {
+        Ok(Invitation {
+            id: InvitationId::parse(&row.id).map_err(OrgStoreError::UnexpectedError)?,
+            org_id: parse_org_id(&row.org_id)?,
+            email: Email::parse(row.email.into())
+                .map_err(|e| OrgStoreError::UnexpectedError(eyre!(e)))?,
+            role: parse_role(&row.role)?,
+            invited_by: parse_user_id(&row.invited_by)?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+fn parse_org_id(id: &str) -> Result<OrgId, OrgStoreError> {
+    OrgId::parse(id).map_err(OrgStoreError::UnexpectedError)
+}
+
+fn parse_user_id(id: &str) -> Result<UserId, OrgStoreError> {
+    UserId::parse(id).map_err(OrgStoreError::UnexpectedError)
+}
+
+fn parse_role(role: &str) -> Result<OrgRole, OrgStoreError> {
+    OrgRole::parse(role).map_err(OrgStoreError::UnexpectedError)
+}
+
+fn unexpected(e: sqlx::Error) -> OrgStoreError {
+    OrgStoreError::UnexpectedError(Report::from(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+
+    async fn store() -> SqliteOrgStore {
+        // every connection to `sqlite::memory:` opens a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        SqliteOrgStore::new(pool)
+    }
+
+    async fn add_user(store: &SqliteOrgStore, email: &str) -> UserId {
+        let id = UserId::default();
+        sqlx::query(
+            "INSERT INTO users (id, email, canonical_email, password_hash) VALUES (?, ?, ?, '')",
+        )
+        .bind(id.to_string())
+        .bind(email)
+        .bind(email)
+        .execute(&store.pool)
+        .await
+        .expect("Failed to add user");
+        id
+    }
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned().into()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn members_join_through_invitations() {
+        let store = store().await;
+        let owner = add_user(&store, "owner@example.com").await;
+        let invitee = add_user(&store, "invitee@example.com").await;
+        let org = Organization::new("Acme".to_owned());
+        store.create_org(&org, &owner).await.unwrap();
+
+        let invitation =
+            Invitation::new(org.id, email("Invitee@Example.com"), OrgRole::Admin, owner);
+        store.add_invitation(&invitation).await.unwrap();
+        assert_eq!(
+            store.add_invitation(&invitation).await,
+            Err(OrgStoreError::InvitationAlreadyExists)
+        );
+        assert_eq!(
+            store
+                .list_invitations_for(&email("invitee@example.com"))
+                .await
+                .unwrap(),
+            std::slice::from_ref(&invitation)
+        );
+
+        let org_id = store
+            .accept_invitation(&invitation.id, &email("invitee@example.com"), &invitee)
+            .await
+            .unwrap();
+        assert_eq!(org_id, org.id);
+        assert!(store.list_invitations(&org.id).await.unwrap().is_empty());
+
+        let members = store.list_members(&org.id).await.unwrap();
+        assert_eq!(
+            members
+                .iter()
+                .map(|member| (member.user_id, member.role))
+                .collect::<Vec<_>>(),
+            [(owner, OrgRole::Owner), (invitee, OrgRole::Admin)]
+        );
+        assert_eq!(
+            store.list_user_orgs(&invitee).await.unwrap(),
+            [(org.clone(), OrgRole::Admin)]
+        );
+    }
+
+    #[tokio::test]
+    async fn the_last_owner_stays() {
+        let store = store().await;
+        let owner = add_user(&store, "owner@example.com").await;
+        let org = Organization::new("Acme".to_owned());
+        store.create_org(&org, &owner).await.unwrap();
+
+        assert_eq!(
+            store.set_role(&org.id, &owner, OrgRole::Member).await,
+            Err(OrgStoreError::LastOwner)
+        );
+        assert_eq!(
+            store.remove_member(&org.id, &owner).await,
+            Err(OrgStoreError::LastOwner)
+        );
+        assert_eq!(
+            store.remove_member(&org.id, &UserId::default()).await,
+            Err(OrgStoreError::NotMember)
+        );
+        store
+            .set_role(&org.id, &owner, OrgRole::Owner)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn other_organizations_are_out_of_reach() {
+        let store = store().await;
+        let owner = add_user(&store, "owner@example.com").await;
+        let (acme, globex) = (
+            Organization::new("Acme".to_owned()),
+            Organization::new("Globex".to_owned()),
+        );
+        store.create_org(&acme, &owner).await.unwrap();
+        store.create_org(&globex, &owner).await.unwrap();
+        let invitation = Invitation::new(acme.id, email("new@example.com"), OrgRole::Member, owner);
+        store.add_invitation(&invitation).await.unwrap();
+
+        assert!(store.list_invitations(&globex.id).await.unwrap().is_empty());
+        assert_eq!(
+            store.revoke_invitation(&globex.id, &invitation.id).await,
+            Err(OrgStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store
+                .accept_invitation(&invitation.id, &email("owner@example.com"), &owner)
+                .await,
+            Err(OrgStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store.list_members(&OrgId::default()).await,
+            Err(OrgStoreError::OrgNotFound)
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Report;
+use sqlx::SqlitePool;
+
+use crate::domain::{
+    PersonalAccessToken, PersonalAccessTokenId, PersonalAccessTokenStore,
+    PersonalAccessTokenStoreError, UserId,
+};
+
+// Single-node alternative to `PostgresPersonalAccessTokenStore`
+pub struct SqlitePersonalAccessTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqlitePersonalAccessTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PersonalAccessTokenStore for SqlitePersonalAccessTokenStore {
+    #[tracing::instrument(name = "Adding personal access token to SQLite", skip_all)]
+    async fn add_token(
+        &self,
+        token: &PersonalAccessToken,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        let id = token.id.to_string();
+        let user_id = token.user_id.to_string();
+        let scope = token.scope();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO personal_access_tokens
+                    (id, user_id, name, scope, token_hash, created_at, expires_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            id,
+            user_id,
+            token.name,
+            scope,
+            token.token_hash,
+            token.created_at,
+            token.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing personal access tokens in SQLite", skip_all)]
+    async fn list_tokens(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<PersonalAccessToken>, PersonalAccessTokenStoreError> {
+        let user_id = user_id.to_string();
+
+        sqlx::query_as!(
+            PersonalAccessTokenRow,
+            r#"
+                SELECT
+                    id, user_id, name, scope, token_hash,
+                    created_at AS "created_at: DateTime<Utc>",
+                    expires_at AS "expires_at: DateTime<Utc>",
+                    last_used_at AS "last_used_at: DateTime<Utc>"
+                FROM personal_access_tokens
+                WHERE user_id = ?
+                ORDER BY created_at, id
+                "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(PersonalAccessToken::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking personal access token in SQLite", skip_all)]
+    async fn revoke_token(
+        &self,
+        user_id: &UserId,
+        id: &PersonalAccessTokenId,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        let id = id.to_string();
+        let user_id = user_id.to_string();
+
+        let result = sqlx::query!(
+            "DELETE FROM personal_access_tokens WHERE id = ? AND user_id = ?",
+            id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(PersonalAccessTokenStoreError::TokenNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving personal access token from SQLite", skip_all)]
+    async fn get_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<PersonalAccessToken, PersonalAccessTokenStoreError> {
+        sqlx::query_as!(
+            PersonalAccessTokenRow,
+            r#"
+                SELECT
+                    id, user_id, name, scope, token_hash,
+                    created_at AS "created_at: DateTime<Utc>",
+                    expires_at AS "expires_at: DateTime<Utc>",
+                    last_used_at AS "last_used_at: DateTime<Utc>"
+                FROM personal_access_tokens
+                WHERE token_hash = ?
+                "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(PersonalAccessTokenStoreError::TokenNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Recording personal access token use in SQLite", skip_all)]
+    async fn record_use(
+        &self,
+        id: &PersonalAccessTokenId,
+        used_at: DateTime<Utc>,
+    ) -> Result<(), PersonalAccessTokenStoreError> {
+        let id = id.to_string();
+
+        let result = sqlx::query!(
+            "UPDATE personal_access_tokens SET last_used_at = ? WHERE id = ?",
+            used_at,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(PersonalAccessTokenStoreError::TokenNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct PersonalAccessTokenRow {
+    id: String,
+    user_id: String,
+    name: String,
+    scope: String,
+    token_hash: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<PersonalAccessTokenRow> for PersonalAccessToken {
+    type Error = PersonalAccessTokenStoreError;
+
+    fn try_from(row: PersonalAccessTokenRow) -> Result<Self, Self::Error> {
+        Ok(PersonalAccessToken {
+            id: PersonalAccessTokenId::parse(&row.id)
+                .map_err(PersonalAccessTokenStoreError::UnexpectedError)?,
+            user_id: UserId::parse(&row.user_id)
+                .map_err(PersonalAccessTokenStoreError::UnexpectedError)?,
+            name: row.name,
+            scopes: PersonalAccessToken::parse_scope(&row.scope)
+                .map_err(PersonalAccessTokenStoreError::UnexpectedError)?,
+            token_hash: row.token_hash,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+        })
+    }
+}
+
+fn unexpected(e: sqlx::Error) -> PersonalAccessTokenStoreError {
+    PersonalAccessTokenStoreError::UnexpectedError(Report::from(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::domain::Permission;
+
+    async fn store() -> SqlitePersonalAccessTokenStore {
+        // every connection to `sqlite::memory:` opens a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        SqlitePersonalAccessTokenStore::new(pool)
+    }
+
+    async fn add_user(store: &SqlitePersonalAccessTokenStore, email: &str) -> UserId {
+        let id = UserId::default();
+        sqlx::query(
+            "INSERT INTO users (id, email, canonical_email, password_hash) VALUES (?, ?, ?, '')",
+        )
+        .bind(id.to_string())
+        .bind(email)
+        .bind(email)
+        .execute(&store.pool)
+        .await
+        .expect("Failed to add user");
+        id
+    }
+
+    fn token(user_id: UserId) -> PersonalAccessToken {
+        let scopes = vec![Permission::parse("reports:read").unwrap()];
+        PersonalAccessToken::new(user_id, "ci".to_owned(), scopes, Duration::days(30)).0
+    }
+
+    #[tokio::test]
+    async fn tokens_round_trip_and_record_their_use() {
+        let store = store().await;
+        let user_id = add_user(&store, "dev@example.com").await;
+        let token = token(user_id);
+        store.add_token(&token).await.unwrap();
+
+        let used_at = Utc::now();
+        store.record_use(&token.id, used_at).await.unwrap();
+
+        let found = store.get_token_by_hash(&token.token_hash).await.unwrap();
+        assert_eq!(found.id, token.id);
+        assert_eq!(found.scopes, token.scopes);
+        assert_eq!(found.expires_at, token.expires_at);
+        assert_eq!(found.last_used_at, Some(used_at));
+        assert_eq!(
+            store
+                .record_use(&PersonalAccessTokenId::default(), used_at)
+                .await,
+            Err(PersonalAccessTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn users_only_see_and_revoke_their_own_tokens() {
+        let store = store().await;
+        let alice = add_user(&store, "alice@example.com").await;
+        let bob = add_user(&store, "bob@example.com").await;
+        let token = token(alice);
+        store.add_token(&token).await.unwrap();
+
+        assert!(store.list_tokens(&bob).await.unwrap().is_empty());
+        assert_eq!(
+            store.revoke_token(&bob, &token.id).await,
+            Err(PersonalAccessTokenStoreError::TokenNotFound)
+        );
+
+        assert_eq!(store.list_tokens(&alice).await.unwrap().len(), 1);
+        store.revoke_token(&alice, &token.id).await.unwrap();
+        assert!(store.list_tokens(&alice).await.unwrap().is_empty());
+    }
+}
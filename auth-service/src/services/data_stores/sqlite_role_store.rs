@@ -0,0 +1,445 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Report;
+use sqlx::SqlitePool;
+
+use crate::domain::{
+    Grants, Permission, PermissionDefinition, RoleDefinition, RoleName, RoleStore, RoleStoreError,
+    UserId,
+};
+
+// Single-node alternative to `PostgresRoleStore`
+pub struct SqliteRoleStore {
+    pool: SqlitePool,
+}
+
+impl SqliteRoleStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn role_exists(&self, name: &str) -> Result<bool, RoleStoreError> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM roles WHERE name = ?) AS "exists!: bool""#,
+            name
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(unexpected)
+    }
+
+    async fn permission_exists(&self, name: &str) -> Result<bool, RoleStoreError> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM permissions WHERE name = ?) AS "exists!: bool""#,
+            name
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(unexpected)
+    }
+}
+
+#[async_trait]
+impl RoleStore for SqliteRoleStore {
+    #[tracing::instrument(name = "Adding role to SQLite", skip_all)]
+    async fn add_role(&self, name: &RoleName, description: &str) -> Result<(), RoleStoreError> {
+        let name = name.as_ref();
+        sqlx::query!(
+            "INSERT INTO roles (name, description) VALUES (?, ?)",
+            name,
+            description
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                RoleStoreError::RoleAlreadyExists
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting role from SQLite", skip_all)]
+    async fn delete_role(&self, name: &RoleName) -> Result<(), RoleStoreError> {
+        let name = name.as_ref();
+        let result = sqlx::query!("DELETE FROM roles WHERE name = ?", name)
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing roles in SQLite", skip_all)]
+    async fn list_roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError> {
+        // the type override keeps this query apart from its PostgreSQL twin in the offline
+        // query cache, which is keyed by query text (same in `list_permissions`)
+        let rows = sqlx::query!(
+            r#"
+                SELECT r.name, r.description, rp.permission AS "permission?: String"
+                FROM roles r
+                LEFT JOIN role_permissions rp ON rp.role = r.name
+                ORDER BY r.name, rp.permission
+                "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        let mut roles: Vec<RoleDefinition> = Vec::new();
+        for row in rows {
+            let name = RoleName::parse(&row.name).map_err(RoleStoreError::UnexpectedError)?;
+            if roles.last().is_none_or(|role| role.name != name) {
+                roles.push(RoleDefinition {
+                    name,
+                    description: row.description,
+                    permissions: Vec::new(),
+                });
+            }
+            if let Some(permission) = row.permission {
+                let permission =
+                    Permission::parse(&permission).map_err(RoleStoreError::UnexpectedError)?;
+                if let Some(role) = roles.last_mut() {
+                    role.permissions.push(permission);
+                }
+            }
+        }
+
+        Ok(roles)
+    }
+
+    #[tracing::instrument(name = "Adding permission to SQLite", skip_all)]
+    async fn add_permission(
+        &self,
+        name: &Permission,
+        description: &str,
+    ) -> Result<(), RoleStoreError> {
+        let name = name.as_ref();
+        sqlx::query!(
+            "INSERT INTO permissions (name, description) VALUES (?, ?)",
+            name,
+            description
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                RoleStoreError::PermissionAlreadyExists
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting permission from SQLite", skip_all)]
+    async fn delete_permission(&self, name: &Permission) -> Result<(), RoleStoreError> {
+        let name = name.as_ref();
+        let result = sqlx::query!("DELETE FROM permissions WHERE name = ?", name)
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RoleStoreError::PermissionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing permissions in SQLite", skip_all)]
+    async fn list_permissions(&self) -> Result<Vec<PermissionDefinition>, RoleStoreError> {
+        sqlx::query!(r#"SELECT name AS "name: String", description FROM permissions ORDER BY name"#)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(unexpected)?
+            .into_iter()
+            .map(|row| {
+                Ok(PermissionDefinition {
+                    name: Permission::parse(&row.name).map_err(RoleStoreError::UnexpectedError)?,
+                    description: row.description,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Granting permission in SQLite", skip_all)]
+    async fn grant_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        let permission = permission.as_ref();
+        let role = role.as_ref();
+        if !self.role_exists(role).await? {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+        if !self.permission_exists(permission).await? {
+            return Err(RoleStoreError::PermissionNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+                INSERT INTO role_permissions (role, permission)
+                VALUES (?, ?)
+                ON CONFLICT DO NOTHING
+                "#,
+            role,
+            permission
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking permission in SQLite", skip_all)]
+    async fn revoke_permission(
+        &self,
+        role: &RoleName,
+        permission: &Permission,
+    ) -> Result<(), RoleStoreError> {
+        let permission = permission.as_ref();
+        let role = role.as_ref();
+        if !self.role_exists(role).await? {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        sqlx::query!(
+            "DELETE FROM role_permissions WHERE role = ? AND permission = ?",
+            role,
+            permission
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Assigning role in SQLite", skip_all)]
+    async fn assign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError> {
+        let role = role.as_ref();
+        let user_id = user_id.to_string();
+        if !self.role_exists(role).await? {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_roles (user_id, role)
+                VALUES (?, ?)
+                ON CONFLICT DO NOTHING
+                "#,
+            user_id,
+            role
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                RoleStoreError::UserNotFound
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Unassigning role in SQLite", skip_all)]
+    async fn unassign_role(&self, user_id: &UserId, role: &RoleName) -> Result<(), RoleStoreError> {
+        let role = role.as_ref();
+        let user_id = user_id.to_string();
+
+        sqlx::query!(
+            "DELETE FROM user_roles WHERE user_id = ? AND role = ?",
+            user_id,
+            role
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving grants from SQLite", skip_all)]
+    async fn get_grants(&self, user_id: &UserId) -> Result<Grants, RoleStoreError> {
+        let user_id = user_id.to_string();
+
+        let roles = sqlx::query_scalar!(
+            "SELECT role FROM user_roles WHERE user_id = ? ORDER BY role",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        let permissions = sqlx::query_scalar!(
+            r#"
+                SELECT DISTINCT rp.permission
+                FROM user_roles ur
+                JOIN role_permissions rp ON rp.role = ur.role
+                WHERE ur.user_id = ?
+                ORDER BY rp.permission
+                "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        Ok(Grants {
+            roles: roles
+                .iter()
+                .map(|role| RoleName::parse(role))
+                .collect::<Result<_, _>>()
+                .map_err(RoleStoreError::UnexpectedError)?,
+            permissions: permissions
+                .iter()
+                .map(|permission| Permission::parse(permission))
+                .collect::<Result<_, _>>()
+                .map_err(RoleStoreError::UnexpectedError)?,
+        })
+    }
+}
+
+fn unexpected(e: sqlx::Error) -> RoleStoreError {
+    RoleStoreError::UnexpectedError(Report::from(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+
+    async fn store() -> SqliteRoleStore {
+        // every connection to `sqlite::memory:` opens a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        SqliteRoleStore::new(pool)
+    }
+
+    async fn add_user(store: &SqliteRoleStore) -> UserId {
+        let id = UserId::default();
+        sqlx::query(
+            "INSERT INTO users (id, email, canonical_email, password_hash) VALUES (?, ?, ?, '')",
+        )
+        .bind(id.to_string())
+        .bind(format!("{id}@example.com"))
+        .bind(format!("{id}@example.com"))
+        .execute(&store.pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    fn role(name: &str) -> RoleName {
+        RoleName::parse(name).unwrap()
+    }
+
+    fn permission(name: &str) -> Permission {
+        Permission::parse(name).unwrap()
+    }
+
+    #[tokio::test]
+    async fn admin_role_is_built_in() {
+        let store = store().await;
+
+        let roles = store.list_roles().await.unwrap();
+        assert_eq!(roles.len(), 1);
+        assert_eq!(roles[0].name, RoleName::admin());
+        assert!(roles[0].permissions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn grants_follow_roles_and_permissions() {
+        let store = store().await;
+        let user_id = add_user(&store).await;
+
+        store
+            .add_role(&role("analyst"), "Reads reports")
+            .await
+            .unwrap();
+        for name in ["reports:read", "reports:export"] {
+            store.add_permission(&permission(name), "").await.unwrap();
+            store
+                .grant_permission(&role("analyst"), &permission(name))
+                .await
+                .unwrap();
+        }
+        store.assign_role(&user_id, &role("analyst")).await.unwrap();
+        store.assign_role(&user_id, &role("analyst")).await.unwrap();
+
+        let grants = store.get_grants(&user_id).await.unwrap();
+        assert_eq!(grants.roles, [role("analyst")]);
+        assert_eq!(
+            grants.permissions,
+            [permission("reports:export"), permission("reports:read")]
+        );
+
+        let roles = store.list_roles().await.unwrap();
+        assert_eq!(roles[0].name, role("admin"));
+        assert_eq!(roles[1].permissions.len(), 2);
+
+        store
+            .revoke_permission(&role("analyst"), &permission("reports:export"))
+            .await
+            .unwrap();
+        store
+            .delete_permission(&permission("reports:read"))
+            .await
+            .unwrap();
+        let grants = store.get_grants(&user_id).await.unwrap();
+        assert_eq!(grants.roles, [role("analyst")]);
+        assert!(grants.permissions.is_empty());
+
+        store.delete_role(&role("analyst")).await.unwrap();
+        assert_eq!(store.get_grants(&user_id).await.unwrap(), Grants::default());
+    }
+
+    #[tokio::test]
+    async fn unknown_entities_are_reported() {
+        let store = store().await;
+
+        assert_eq!(
+            store
+                .assign_role(&UserId::default(), &RoleName::admin())
+                .await,
+            Err(RoleStoreError::UserNotFound)
+        );
+        assert_eq!(
+            store.assign_role(&UserId::default(), &role("ghost")).await,
+            Err(RoleStoreError::RoleNotFound)
+        );
+        assert_eq!(
+            store
+                .grant_permission(&RoleName::admin(), &permission("ghost"))
+                .await,
+            Err(RoleStoreError::PermissionNotFound)
+        );
+        assert_eq!(
+            store.delete_role(&role("ghost")).await,
+            Err(RoleStoreError::RoleNotFound)
+        );
+        assert_eq!(
+            store.add_role(&RoleName::admin(), "").await,
+            Err(RoleStoreError::RoleAlreadyExists)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+use secrecy::ExposeSecret;
+use sqlx::SqlitePool;
+
+use crate::domain::{
+    Email, OrgId, SignupInvitation, SignupInvitationId, SignupInvitationStore,
+    SignupInvitationStoreError, UserId,
+};
+
+// Single-node alternative to `PostgresSignupInvitationStore`
+pub struct SqliteSignupInvitationStore {
+    pool: SqlitePool,
+}
+
+impl SqliteSignupInvitationStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SignupInvitationStore for SqliteSignupInvitationStore {
+    #[tracing::instrument(name = "Adding signup invitation to SQLite", skip_all)]
+    async fn add_invitation(
+        &self,
+        invitation: &SignupInvitation,
+    ) -> Result<(), SignupInvitationStoreError> {
+        let id = invitation.id.to_string();
+        let email = invitation.email.as_ref().expose_secret();
+        let org_id = invitation.org_id.map(|org_id| org_id.to_string());
+        let invited_by = invitation.invited_by.to_string();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO signup_invitations
+                    (id, email, org_id, token_hash, invited_by, created_at, expires_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            id,
+            email,
+            org_id,
+            invitation.token_hash,
+            invited_by,
+            invitation.created_at,
+            invitation.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                SignupInvitationStoreError::OrgNotFound
+            }
+            e => unexpected(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing signup invitations in SQLite", skip_all)]
+    async fn list_invitations(
+        &self,
+        org_id: Option<&OrgId>,
+    ) -> Result<Vec<SignupInvitation>, SignupInvitationStoreError> {
+        let org_id = org_id.map(OrgId::to_string);
+
+        sqlx::query_as!(
+            SignupInvitationRow,
+            r#"
+                SELECT
+                    id, email, org_id, token_hash, invited_by,
+                    created_at AS "created_at: DateTime<Utc>",
+                    expires_at AS "expires_at: DateTime<Utc>"
+                FROM signup_invitations
+                WHERE ? IS NULL OR org_id = ?
+                ORDER BY created_at, id
+                "#,
+            org_id,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .into_iter()
+        .map(SignupInvitation::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking signup invitation in SQLite", skip_all)]
+    async fn revoke_invitation(
+        &self,
+        id: &SignupInvitationId,
+        org_id: Option<&OrgId>,
+    ) -> Result<(), SignupInvitationStoreError> {
+        let id = id.to_string();
+        let org_id = org_id.map(OrgId::to_string);
+
+        let result = sqlx::query!(
+            "DELETE FROM signup_invitations WHERE id = ? AND (? IS NULL OR org_id = ?)",
+            id,
+            org_id,
+            org_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SignupInvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving signup invitation from SQLite", skip_all)]
+    async fn get_invitation_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<SignupInvitation, SignupInvitationStoreError> {
+        sqlx::query_as!(
+            SignupInvitationRow,
+            r#"
+                SELECT
+                    id, email, org_id, token_hash, invited_by,
+                    created_at AS "created_at: DateTime<Utc>",
+                    expires_at AS "expires_at: DateTime<Utc>"
+                FROM signup_invitations
+                WHERE token_hash = ?
+                "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(SignupInvitationStoreError::InvitationNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Consuming signup invitation in SQLite", skip_all)]
+    async fn consume_invitation(
+        &self,
+        id: &SignupInvitationId,
+    ) -> Result<(), SignupInvitationStoreError> {
+        let id = id.to_string();
+
+        let result = sqlx::query!("DELETE FROM signup_invitations WHERE id = ?", id)
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SignupInvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct SignupInvitationRow {
+    id: String,
+    email: String,
+    org_id: Option<String>,
+    token_hash: String,
+    invited_by: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl TryFrom<SignupInvitationRow> for SignupInvitation {
+    type Error = SignupInvitationStoreError;
+
+    fn try_from(row: SignupInvitationRow) -> Result<Self, Self::Error> {
+        Ok(SignupInvitation {
+            id: SignupInvitationId::parse(&row.id)
+                .map_err(SignupInvitationStoreError::UnexpectedError)?,
+            email: Email::parse(row.email.into())
+                .map_err(|e| SignupInvitationStoreError::UnexpectedError(eyre!(e)))?,
+            org_id: row
+                .org_id
+                .map(|org_id| OrgId::parse(&org_id))
+                .transpose()
+                .map_err(SignupInvitationStoreError::UnexpectedError)?,
+            token_hash: row.token_hash,
+            invited_by: UserId::parse(&row.invited_by)
+                .map_err(SignupInvitationStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+fn unexpected(e: sqlx::Error) -> SignupInvitationStoreError {
+    SignupInvitationStoreError::UnexpectedError(Report::from(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+
+    async fn store() -> SqliteSignupInvitationStore {
+        // every connection to `sqlite::memory:` opens a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        SqliteSignupInvitationStore::new(pool)
+    }
+
+    async fn add_user(store: &SqliteSignupInvitationStore) -> UserId {
+        let id = UserId::default();
+        sqlx::query(
+            "INSERT INTO users (id, email, canonical_email, password_hash) VALUES (?, ?, ?, '')",
+        )
+        .bind(id.to_string())
+        .bind("admin@example.com")
+        .bind("admin@example.com")
+        .execute(&store.pool)
+        .await
+        .expect("Failed to add user");
+        id
+    }
+
+    fn invitation(org_id: Option<OrgId>, invited_by: UserId) -> SignupInvitation {
+        let email = Email::parse("New@Example.com".to_owned().into()).unwrap();
+        SignupInvitation::new(email, org_id, invited_by, Duration::days(1)).0
+    }
+
+    #[tokio::test]
+    async fn invitations_round_trip_and_are_used_once() {
+        let store = store().await;
+        let admin = add_user(&store).await;
+        let invitation = invitation(None, admin);
+        store.add_invitation(&invitation).await.unwrap();
+
+        let found = store
+            .get_invitation_by_token_hash(&invitation.token_hash)
+            .await
+            .unwrap();
+        assert_eq!(found.id, invitation.id);
+        assert_eq!(found.email, invitation.email);
+        assert_eq!(found.expires_at, invitation.expires_at);
+        assert_eq!(
+            store
+                .revoke_invitation(&invitation.id, Some(&OrgId::default()))
+                .await,
+            Err(SignupInvitationStoreError::InvitationNotFound)
+        );
+
+        store.consume_invitation(&invitation.id).await.unwrap();
+        assert_eq!(
+            store.consume_invitation(&invitation.id).await,
+            Err(SignupInvitationStoreError::InvitationNotFound)
+        );
+        assert!(store.list_invitations(None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn invitations_for_unknown_organizations_are_rejected() {
+        let store = store().await;
+        let admin = add_user(&store).await;
+
+        assert_eq!(
+            store
+                .add_invitation(&invitation(Some(OrgId::default()), admin))
+                .await,
+            Err(SignupInvitationStoreError::OrgNotFound)
+        );
+    }
+}
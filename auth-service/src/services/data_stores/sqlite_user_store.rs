@@ -0,0 +1,1175 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use secrecy::{ExposeSecret, Secret};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+use super::email_search_pattern;
+use crate::{
+    domain::{
+        AccountStatus, Email, Password, PasswordReset, PasswordResetToken, StatusChange,
+        StatusTransition, User, UserId, UserPage, UserQuery, UserStore, UserStoreError, UserUpdate,
+    },
+    services::{password_hasher::PasswordHasher, user_import::ImportedUser},
+};
+
+// Single-node alternative to `PostgresUserStore`, with the same hashing behaviour
+pub struct SqliteUserStore {
+    pool: SqlitePool,
+    hasher: PasswordHasher,
+}
+
+impl SqliteUserStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            hasher: PasswordHasher::default(),
+        }
+    }
+
+    pub fn with_password_hasher(mut self, hasher: PasswordHasher) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    // Stores a user migrated from another system with its existing password hash; the hash is
+    // upgraded to Argon2id on the user's next successful login
+    #[tracing::instrument(name = "Importing user to SQLite", skip_all)]
+    pub async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError> {
+        let id = UserId::default().to_string();
+        let email = user.email.as_ref().expose_secret();
+        let canonical_email = user.email.canonical().expose_secret();
+        let password_hash = user.password_hash.expose_secret();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO users (id, email, canonical_email, password_hash, requires_2fa)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            id,
+            email,
+            canonical_email,
+            password_hash,
+            user.requires_2fa
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_insert_error)?;
+
+        Ok(())
+    }
+
+    // The migration that added `canonical_email` could only lowercase the stored addresses,
+    // which is not what `Email::parse` does for internationalized domains, or for local parts
+    // when EMAIL_LOWERCASE_LOCAL_PART is false. Run at startup, this brings every row in line
+    // with the current canonicalization. Fails, changing nothing, if two accounts would end up
+    // with the same canonical email; they have to be merged by hand first
+    #[tracing::instrument(name = "Recanonicalizing emails in SQLite", skip_all)]
+    pub async fn recanonicalize_emails(&self) -> Result<usize> {
+        let mut transaction = self.pool.begin().await?;
+        let rows = sqlx::query!("SELECT id, email, canonical_email FROM users")
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        let mut owners = HashMap::new();
+        let mut changes = Vec::new();
+        for row in rows {
+            // Addresses that no longer parse keep their canonical form; they can't log in anyway
+            let canonical = match Email::parse(row.email.into()) {
+                Ok(email) => email.canonical().expose_secret().to_owned(),
+                Err(_) => row.canonical_email.clone(),
+            };
+            if let Some(other) = owners.insert(canonical.clone(), row.id.clone()) {
+                return Err(eyre!(
+                    "Users {} and {} have the same canonical email",
+                    other,
+                    row.id
+                ));
+            }
+            if canonical != row.canonical_email {
+                changes.push((row.id, canonical));
+            }
+        }
+
+        // Parked on their ids first, so rows swapping canonical forms don't collide midway
+        for (id, _) in &changes {
+            let parked = id.to_string();
+            sqlx::query!(
+                "UPDATE users SET canonical_email = ? WHERE id = ?",
+                parked,
+                id
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        for (id, canonical) in &changes {
+            sqlx::query!(
+                "UPDATE users SET canonical_email = ? WHERE id = ?",
+                canonical,
+                id
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(changes.len())
+    }
+
+    // Brings the stored hash up to the configured parameters; the user has already been
+    // authenticated at this point, so a failure here must not fail the login. Only replaces
+    // `verified_hash`: a password set while the new hash was being computed is kept
+    #[tracing::instrument(name = "Rehashing password in SQLite", skip_all)]
+    async fn rehash_password(
+        &self,
+        email: &Email,
+        verified_hash: &Secret<String>,
+        password: &Password,
+    ) -> Result<()> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await?;
+        let password_hash = password_hash.expose_secret();
+        let updated_at = Utc::now();
+        let email = email.canonical().expose_secret();
+        let verified_hash = verified_hash.expose_secret();
+
+        sqlx::query!(
+            r#"
+                UPDATE users
+                SET password_hash = ?, updated_at = ?
+                WHERE canonical_email = ? AND password_hash = ?
+                "#,
+            password_hash,
+            updated_at,
+            email,
+            verified_hash
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserStore for SqliteUserStore {
+    #[tracing::instrument(name = "Adding user to SQLite", skip_all)]
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(user.password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::from)?;
+        let password_hash = password_hash.expose_secret();
+        let id = user.id.to_string();
+        let email = user.email.as_ref().expose_secret();
+        let canonical_email = user.email.canonical().expose_secret();
+        let status = user.status.as_str();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO users (
+                    id, email, canonical_email, password_hash, requires_2fa, status,
+                    created_at, updated_at
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            id,
+            email,
+            canonical_email,
+            password_hash,
+            user.requires_2fa,
+            status,
+            user.created_at,
+            user.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_insert_error)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user from SQLite", skip_all)]
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        let email = email.canonical().expose_secret();
+
+        sqlx::query_as!(
+            UserRow,
+            r#"
+                SELECT
+                    id,
+                    email,
+                    password_hash,
+                    requires_2fa,
+                    status,
+                    password_reset_required,
+                    sessions_revoked_at AS "sessions_revoked_at: DateTime<Utc>",
+                    created_at AS "created_at: DateTime<Utc>",
+                    updated_at AS "updated_at: DateTime<Utc>",
+                    last_login_at AS "last_login_at: DateTime<Utc>"
+                FROM users
+                WHERE canonical_email = ?
+                "#,
+            email
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Retrieving user by id from SQLite", skip_all)]
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        let id = id.to_string();
+
+        sqlx::query_as!(
+            UserRow,
+            r#"
+                SELECT
+                    id,
+                    email,
+                    password_hash,
+                    requires_2fa,
+                    status,
+                    password_reset_required,
+                    sessions_revoked_at AS "sessions_revoked_at: DateTime<Utc>",
+                    created_at AS "created_at: DateTime<Utc>",
+                    updated_at AS "updated_at: DateTime<Utc>",
+                    last_login_at AS "last_login_at: DateTime<Utc>"
+                FROM users
+                WHERE id = ?
+                "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Validating user credentials in SQLite", skip_all)]
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self.get_user(email).await?;
+
+        let password_hash: Secret<String> = user.password.as_ref().to_owned();
+
+        self.hasher
+            .verify_password_hash(password_hash.clone(), password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::from)?;
+
+        if self.hasher.needs_rehash(&password_hash) {
+            if let Err(e) = self.rehash_password(email, &password_hash, password).await {
+                tracing::error!("Failed to rehash password: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording login in SQLite", skip_all)]
+    async fn record_login(&self, id: &UserId) -> Result<(), UserStoreError> {
+        let last_login_at = Utc::now();
+        let id = id.to_string();
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET last_login_at = ?
+                WHERE id = ?
+                "#,
+            last_login_at,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Changing account status in SQLite", skip_all)]
+    async fn set_status(&self, id: &UserId, change: StatusChange) -> Result<(), UserStoreError> {
+        let id = id.to_string();
+        let status = change.status.as_str();
+        let updated_at = Utc::now();
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // writing first takes the database lock, so the previous status can't change under us
+        let recorded = sqlx::query!(
+            r#"
+                INSERT INTO account_status_changes (user_id, from_status, to_status, reason, actor)
+                SELECT id, status, ?, ?, ?
+                FROM users
+                WHERE id = ?
+                "#,
+            status,
+            change.reason,
+            change.actor,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if recorded.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+                UPDATE users
+                SET status = ?, updated_at = ?
+                WHERE id = ?
+                "#,
+            status,
+            updated_at,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving account status history from SQLite", skip_all)]
+    async fn get_status_history(
+        &self,
+        id: &UserId,
+    ) -> Result<Vec<StatusTransition>, UserStoreError> {
+        // fails for unknown users, rather than returning an empty history
+        self.get_user_by_id(id).await?;
+        let id = id.to_string();
+
+        sqlx::query!(
+            r#"
+                SELECT
+                    from_status,
+                    to_status,
+                    reason,
+                    actor,
+                    changed_at AS "changed_at: DateTime<Utc>"
+                FROM account_status_changes
+                WHERE user_id = ?
+                ORDER BY changed_at, id
+                "#,
+            id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(StatusTransition {
+                from: AccountStatus::parse(&row.from_status)
+                    .map_err(UserStoreError::UnexpectedError)?,
+                to: AccountStatus::parse(&row.to_status)
+                    .map_err(UserStoreError::UnexpectedError)?,
+                reason: row.reason,
+                actor: row.actor,
+                changed_at: row.changed_at,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Listing users in SQLite", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let pattern = query.email.as_deref().map(email_search_pattern);
+        let limit = i64::from(query.limit);
+        let offset = i64::from(query.offset);
+
+        let users = sqlx::query_as!(
+            UserRow,
+            r#"
+                SELECT
+                    id,
+                    email,
+                    password_hash,
+                    requires_2fa,
+                    status,
+                    password_reset_required,
+                    sessions_revoked_at AS "sessions_revoked_at: DateTime<Utc>",
+                    created_at AS "created_at: DateTime<Utc>",
+                    updated_at AS "updated_at: DateTime<Utc>",
+                    last_login_at AS "last_login_at: DateTime<Utc>"
+                FROM users
+                WHERE ?1 IS NULL OR canonical_email LIKE ?1 ESCAPE '\'
+                ORDER BY created_at, id
+                LIMIT ?2 OFFSET ?3
+                "#,
+            pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(User::try_from)
+        .collect::<Result<_, _>>()?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*)
+                FROM users
+                WHERE ?1 IS NULL OR canonical_email LIKE ?1 ESCAPE '\'
+                "#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(UserPage {
+            users,
+            total: total.unsigned_abs(),
+        })
+    }
+
+    #[tracing::instrument(name = "Updating user in SQLite", skip_all)]
+    async fn update_user(&self, id: &UserId, update: UserUpdate) -> Result<(), UserStoreError> {
+        let id = id.to_string();
+        let updated_at = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET
+                    requires_2fa = COALESCE(?, requires_2fa),
+                    password_reset_required = COALESCE(?, password_reset_required),
+                    sessions_revoked_at = COALESCE(?, sessions_revoked_at),
+                    updated_at = ?
+                WHERE id = ?
+                "#,
+            update.requires_2fa,
+            update.password_reset_required,
+            update.sessions_revoked_at,
+            updated_at,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Starting password reset in SQLite", skip_all)]
+    async fn start_password_reset(
+        &self,
+        id: &UserId,
+        reset: &PasswordReset,
+    ) -> Result<(), UserStoreError> {
+        let id = id.to_string();
+        let token_hash = &reset.token_hash;
+        let expires_at = reset.expires_at;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET password_reset_token_hash = ?, password_reset_expires_at = ?
+                WHERE id = ?
+                "#,
+            token_hash,
+            expires_at,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    // The token is checked and used up in the same statement, so it can't be redeemed twice.
+    // Timestamps are stored in one text format, so they compare in time order
+    #[tracing::instrument(name = "Completing password reset in SQLite", skip_all)]
+    async fn complete_password_reset(
+        &self,
+        email: &Email,
+        token: &PasswordResetToken,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::from)?;
+        let password_hash = password_hash.expose_secret();
+        let email = email.canonical().expose_secret();
+        let token_hash = token.hash();
+        let now = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET
+                    password_hash = ?,
+                    password_reset_required = FALSE,
+                    password_reset_token_hash = NULL,
+                    password_reset_expires_at = NULL,
+                    sessions_revoked_at = ?,
+                    updated_at = ?
+                WHERE canonical_email = ?
+                    AND password_reset_token_hash = ?
+                    AND password_reset_expires_at > ?
+                "#,
+            password_hash,
+            now,
+            now,
+            email,
+            token_hash,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting password in SQLite", skip_all)]
+    async fn set_password(&self, id: &UserId, password: Password) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::from)?;
+        let password_hash = password_hash.expose_secret();
+        let id = id.to_string();
+        let updated_at = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET
+                    password_hash = ?,
+                    password_reset_required = FALSE,
+                    password_reset_token_hash = NULL,
+                    password_reset_expires_at = NULL,
+                    updated_at = ?
+                WHERE id = ?
+                "#,
+            password_hash,
+            updated_at,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    // Roles, memberships, invitations and tokens are removed by the foreign keys' cascades
+    #[tracing::instrument(name = "Deleting user from SQLite", skip_all)]
+    async fn delete_user(&self, id: &UserId) -> Result<(), UserStoreError> {
+        let id = id.to_string();
+
+        let result = sqlx::query!("DELETE FROM users WHERE id = ?", id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct UserRow {
+    id: String,
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    status: String,
+    password_reset_required: bool,
+    sessions_revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    last_login_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            id: UserId::parse(&row.id).map_err(UserStoreError::UnexpectedError)?,
+            email: Email::parse(row.email.into())
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(row.password_hash.into())
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            status: AccountStatus::parse(&row.status).map_err(UserStoreError::UnexpectedError)?,
+            password_reset_required: row.password_reset_required,
+            sessions_revoked_at: row.sessions_revoked_at,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            last_login_at: row.last_login_at,
+        })
+    }
+}
+
+fn map_insert_error(err: sqlx::Error) -> UserStoreError {
+    match err {
+        sqlx::Error::Database(err) if err.is_unique_violation() => {
+            UserStoreError::UserAlreadyExists
+        }
+        e => UserStoreError::UnexpectedError(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::services::password_hasher::HashingParams;
+
+    async fn store(hashing_params: HashingParams) -> SqliteUserStore {
+        // every connection to `sqlite::memory:` opens a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite pool");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        SqliteUserStore::new(pool)
+            .with_password_hasher(PasswordHasher::new(hashing_params).unwrap())
+    }
+
+    const CHEAP: HashingParams = HashingParams {
+        memory_cost: 1024,
+        time_cost: 1,
+        parallelism: 1,
+    };
+
+    fn user() -> User {
+        User::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            Password::parse("password123".to_owned().into()).unwrap(),
+            true,
+        )
+    }
+
+    async fn stored_hash(store: &SqliteUserStore) -> String {
+        store
+            .get_user(&user().email)
+            .await
+            .unwrap()
+            .password
+            .as_ref()
+            .expose_secret()
+            .clone()
+    }
+
+    #[tokio::test]
+    async fn added_user_can_be_retrieved_and_validated() {
+        let store = store(CHEAP).await;
+        store.add_user(user()).await.unwrap();
+
+        let stored = store.get_user(&user().email).await.unwrap();
+        assert_eq!(stored.email, user().email);
+        assert!(stored.last_login_at.is_none());
+        assert!(stored.requires_2fa);
+        assert!(stored_hash(&store).await.starts_with("$argon2id$"));
+
+        assert!(store
+            .validate_user(&user().email, &user().password)
+            .await
+            .is_ok());
+        assert_eq!(
+            store
+                .validate_user(
+                    &user().email,
+                    &Password::parse("password124".to_owned().into()).unwrap()
+                )
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_user_is_rejected() {
+        let store = store(CHEAP).await;
+        store.add_user(user()).await.unwrap();
+
+        assert_eq!(
+            store.add_user(user()).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn email_lookups_ignore_case_but_keep_the_original_address() {
+        let store = store(CHEAP).await;
+        let signed_up = User::new(
+            Email::parse("Test@Example.com".to_owned().into()).unwrap(),
+            user().password,
+            true,
+        );
+        store.add_user(signed_up).await.unwrap();
+
+        let stored = store.get_user(&user().email).await.unwrap();
+        assert_eq!(stored.email.as_ref().expose_secret(), "Test@Example.com");
+        assert!(store
+            .validate_user(&user().email, &user().password)
+            .await
+            .is_ok());
+        assert_eq!(
+            store.add_user(user()).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_user_is_not_found() {
+        let store = store(CHEAP).await;
+
+        assert_eq!(
+            store.get_user(&user().email).await.err(),
+            Some(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn outdated_hash_is_upgraded_on_login() {
+        let store = store(CHEAP).await;
+        store
+            .import_user(ImportedUser {
+                email: user().email,
+                password_hash: Secret::new(bcrypt::hash("password123", 4).unwrap()),
+                requires_2fa: false,
+            })
+            .await
+            .unwrap();
+
+        store
+            .validate_user(&user().email, &user().password)
+            .await
+            .unwrap();
+
+        let hash = stored_hash(&store).await;
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert!(store
+            .validate_user(&user().email, &user().password)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn rehash_keeps_a_password_changed_after_verification() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+        let verified_hash = Secret::new(stored_hash(&store).await);
+
+        let new_password = Password::parse("password456".to_owned().into()).unwrap();
+        store
+            .set_password(&user.id, new_password.clone())
+            .await
+            .unwrap();
+        store
+            .rehash_password(&user.email, &verified_hash, &user.password)
+            .await
+            .unwrap();
+
+        assert!(store
+            .validate_user(&user.email, &new_password)
+            .await
+            .is_ok());
+        assert_eq!(
+            store.validate_user(&user.email, &user.password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn user_keeps_its_id_and_records_logins() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+
+        store.record_login(&user.id).await.unwrap();
+
+        let stored = store.get_user(&user.email).await.unwrap();
+        assert_eq!(stored.id, user.id);
+        assert_eq!(stored.created_at, user.created_at);
+        assert!(stored.last_login_at.is_some_and(|at| at >= user.created_at));
+
+        assert_eq!(
+            store.record_login(&UserId::default()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn status_changes_are_applied_and_recorded() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+
+        for (status, reason) in [
+            (AccountStatus::Locked, "too many failed logins"),
+            (AccountStatus::Active, "identity confirmed"),
+        ] {
+            let change = StatusChange {
+                status,
+                reason: reason.to_owned(),
+                actor: "admin".to_owned(),
+            };
+            store.set_status(&user.id, change).await.unwrap();
+        }
+
+        let stored = store.get_user_by_id(&user.id).await.unwrap();
+        assert_eq!(stored.status, AccountStatus::Active);
+
+        let history = store.get_status_history(&user.id).await.unwrap();
+        let transitions: Vec<_> = history
+            .iter()
+            .map(|t| (t.from, t.to, t.reason.as_str(), t.actor.as_str()))
+            .collect();
+        assert_eq!(
+            transitions,
+            [
+                (
+                    AccountStatus::Active,
+                    AccountStatus::Locked,
+                    "too many failed logins",
+                    "admin"
+                ),
+                (
+                    AccountStatus::Locked,
+                    AccountStatus::Active,
+                    "identity confirmed",
+                    "admin"
+                ),
+            ]
+        );
+
+        let unknown = StatusChange {
+            status: AccountStatus::Disabled,
+            reason: String::new(),
+            actor: "admin".to_owned(),
+        };
+        assert_eq!(
+            store.set_status(&UserId::default(), unknown).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn users_are_listed_in_pages_and_searched_by_email() {
+        let store = store(CHEAP).await;
+        for email in [
+            "alice@example.com",
+            "bob@example.com",
+            "Carol@Example.org",
+            "a_b@x.io",
+        ] {
+            let user = User::new(
+                Email::parse(email.to_owned().into()).unwrap(),
+                user().password,
+                false,
+            );
+            store.add_user(user).await.unwrap();
+        }
+
+        let all = UserQuery {
+            email: None,
+            offset: 0,
+            limit: 10,
+        };
+        let page = store.list_users(&all).await.unwrap();
+        assert_eq!(page.total, 4);
+        assert_eq!(page.users.len(), 4);
+
+        let second = UserQuery {
+            offset: 1,
+            limit: 2,
+            ..all.clone()
+        };
+        let page = store.list_users(&second).await.unwrap();
+        assert_eq!(page.total, 4);
+        assert_eq!(page.users.len(), 2);
+
+        for (search, expected) in [("EXAMPLE.ORG", 1), ("example", 3), ("_", 1), ("%", 0)] {
+            let query = UserQuery {
+                email: Some(search.to_owned()),
+                ..all.clone()
+            };
+            assert_eq!(store.list_users(&query).await.unwrap().total, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn update_changes_only_the_given_fields() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+
+        let revoked_at = Utc::now();
+        let update = UserUpdate {
+            requires_2fa: Some(false),
+            sessions_revoked_at: Some(revoked_at),
+            ..Default::default()
+        };
+        store.update_user(&user.id, update).await.unwrap();
+
+        let stored = store.get_user_by_id(&user.id).await.unwrap();
+        assert!(!stored.requires_2fa);
+        assert!(!stored.password_reset_required);
+        assert_eq!(stored.sessions_revoked_at, Some(revoked_at));
+
+        store
+            .update_user(&user.id, UserUpdate::default())
+            .await
+            .unwrap();
+        let stored = store.get_user_by_id(&user.id).await.unwrap();
+        assert!(!stored.requires_2fa);
+        assert_eq!(stored.sessions_revoked_at, Some(revoked_at));
+
+        assert_eq!(
+            store
+                .update_user(&UserId::default(), UserUpdate::default())
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn password_reset_sets_the_password_once() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+        let update = UserUpdate {
+            password_reset_required: Some(true),
+            ..Default::default()
+        };
+        store.update_user(&user.id, update).await.unwrap();
+
+        let new_password = Password::parse("new-password123".to_owned().into()).unwrap();
+        let (reset, token) = PasswordReset::new(chrono::Duration::hours(1));
+        assert_eq!(
+            store
+                .complete_password_reset(&user.email, &token, new_password.clone())
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+
+        store.start_password_reset(&user.id, &reset).await.unwrap();
+        assert_eq!(
+            store
+                .complete_password_reset(
+                    &user.email,
+                    &PasswordResetToken::default(),
+                    new_password.clone()
+                )
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        store
+            .complete_password_reset(&user.email, &token, new_password.clone())
+            .await
+            .unwrap();
+
+        let stored = store.get_user_by_id(&user.id).await.unwrap();
+        assert!(!stored.password_reset_required);
+        assert!(stored.sessions_revoked_at.is_some());
+        assert!(store
+            .validate_user(&user.email, &new_password)
+            .await
+            .is_ok());
+        assert_eq!(
+            store
+                .complete_password_reset(&user.email, &token, user.password)
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_password_reset_is_rejected() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+
+        let (reset, token) = PasswordReset::new(chrono::Duration::seconds(-1));
+        store.start_password_reset(&user.id, &reset).await.unwrap();
+
+        assert_eq!(
+            store
+                .complete_password_reset(&user.email, &token, user.password)
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn set_password_lifts_a_forced_reset() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+        let update = UserUpdate {
+            password_reset_required: Some(true),
+            ..Default::default()
+        };
+        store.update_user(&user.id, update).await.unwrap();
+        let (reset, token) = PasswordReset::new(chrono::Duration::hours(1));
+        store.start_password_reset(&user.id, &reset).await.unwrap();
+
+        let new_password = Password::parse("new-password123".to_owned().into()).unwrap();
+        store
+            .set_password(&user.id, new_password.clone())
+            .await
+            .unwrap();
+
+        let stored = store.get_user_by_id(&user.id).await.unwrap();
+        assert!(!stored.password_reset_required);
+        assert!(store
+            .validate_user(&user.email, &new_password)
+            .await
+            .is_ok());
+        // the pending reset is dropped with it
+        assert_eq!(
+            store
+                .complete_password_reset(&user.email, &token, user.password)
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_user_is_gone_with_their_history() {
+        let store = store(CHEAP).await;
+        let user = user();
+        store.add_user(user.clone()).await.unwrap();
+        let change = StatusChange {
+            status: AccountStatus::Locked,
+            reason: "test".to_owned(),
+            actor: "admin".to_owned(),
+        };
+        store.set_status(&user.id, change).await.unwrap();
+
+        store.delete_user(&user.id).await.unwrap();
+
+        assert_eq!(
+            store.get_user_by_id(&user.id).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        let history: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM account_status_changes")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(history, 0);
+        assert_eq!(
+            store.delete_user(&user.id).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    // The way the migration that added `canonical_email` filled it in
+    async fn lowercase_canonical_emails(store: &SqliteUserStore) {
+        sqlx::query("UPDATE users SET canonical_email = lower(email)")
+            .execute(&store.pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn migrated_emails_are_recanonicalized() {
+        let store = store(CHEAP).await;
+        let email = Email::parse("user@BÜCHER.example".to_owned().into()).unwrap();
+        store
+            .add_user(User::new(email.clone(), user().password, false))
+            .await
+            .unwrap();
+        store.add_user(user()).await.unwrap();
+        lowercase_canonical_emails(&store).await;
+        assert_eq!(
+            store.get_user(&email).await.err(),
+            Some(UserStoreError::UserNotFound)
+        );
+
+        assert_eq!(store.recanonicalize_emails().await.unwrap(), 1);
+        assert!(store.get_user(&email).await.is_ok());
+        assert!(store.get_user(&user().email).await.is_ok());
+        assert_eq!(store.recanonicalize_emails().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn recanonicalization_refuses_to_merge_accounts() {
+        let store = store(CHEAP).await;
+        let first = User::new(
+            Email::parse("user@bücher.example".to_owned().into()).unwrap(),
+            user().password,
+            false,
+        );
+        let second = User::new(user().email, user().password, false);
+        store.add_user(first.clone()).await.unwrap();
+        store.add_user(second.clone()).await.unwrap();
+        // only told apart by a lowercasing that leaves non-ASCII letters alone
+        sqlx::query("UPDATE users SET email = ? WHERE id = ?")
+            .bind("user@BÜCHER.example")
+            .bind(second.id.to_string())
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        lowercase_canonical_emails(&store).await;
+
+        assert!(store.recanonicalize_emails().await.is_err());
+        assert!(store.get_user_by_id(&first.id).await.is_ok());
+        assert_eq!(
+            store.get_user(&first.email).await.err(),
+            Some(UserStoreError::UserNotFound)
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::RwLock, task::JoinHandle};
+
+use crate::domain::Clock;
+
+// In-memory counterpart of Redis `SET EX`: expired entries are invisible to reads right away
+// and are dropped on access or by `evict_expired`
+pub struct TtlMap<K, V> {
+    entries: RwLock<HashMap<K, (V, Instant)>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<K, V> TtlMap<K, V>
+where
+    K: Eq + Hash + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    // Replaces any previous entry and restarts its TTL
+    pub async fn insert(&self, key: K, value: V, ttl: Duration) {
+        let expires_at = self.clock.now() + ttl;
+        self.entries.write().await.insert(key, (value, expires_at));
+    }
+
+    // Returns false if a live entry already exists
+    pub async fn insert_new(&self, key: K, value: V, ttl: Duration) -> bool {
+        let now = self.clock.now();
+        let mut entries = self.entries.write().await;
+
+        if entries
+            .get(&key)
+            .is_some_and(|(_, expires_at)| *expires_at > now)
+        {
+            return false;
+        }
+        entries.insert(key, (value, now + ttl));
+        true
+    }
+
+    pub async fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let now = self.clock.now();
+        {
+            let entries = self.entries.read().await;
+            match entries.get(key) {
+                Some((value, expires_at)) if *expires_at > now => return Some(value.clone()),
+                Some(_) => {}
+                None => return None,
+            }
+        }
+
+        // expired: drop it while we are here
+        self.remove_expired(key, now).await;
+        None
+    }
+
+    // Rechecks under the write lock, as the entry may have been replaced since it was read
+    async fn remove_expired<Q>(&self, key: &Q, now: Instant)
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let mut entries = self.entries.write().await;
+        if entries
+            .get(key)
+            .is_some_and(|(_, expires_at)| *expires_at <= now)
+        {
+            entries.remove(key);
+        }
+    }
+
+    // Returns the removed value, unless it had already expired
+    pub async fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let now = self.clock.now();
+        self.entries
+            .write()
+            .await
+            .remove(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(value, _)| value)
+    }
+
+    // Returns the number of evicted entries
+    pub async fn evict_expired(&self) -> usize {
+        let now = self.clock.now();
+        let mut entries = self.entries.write().await;
+        let len = entries.len();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        len - entries.len()
+    }
+
+    // Evicts expired entries every `interval` until the map is dropped
+    pub fn spawn_eviction(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let map: Weak<Self> = Arc::downgrade(self);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let Some(map) = map.upgrade() else {
+                    return;
+                };
+                let evicted = map.evict_expired().await;
+                if evicted > 0 {
+                    tracing::debug!(evicted, "Evicted expired in-memory entries");
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::clock::MockClock;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn map() -> (Arc<TtlMap<String, u32>>, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new());
+        (Arc::new(TtlMap::new(clock.clone())), clock)
+    }
+
+    #[tokio::test]
+    async fn entries_expire_after_ttl() {
+        let (map, clock) = map();
+        map.insert("a".to_owned(), 1, TTL).await;
+
+        clock.advance(TTL - Duration::from_secs(1));
+        assert_eq!(map.get("a").await, Some(1));
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(map.get("a").await, None);
+        assert_eq!(map.entries.read().await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_not_removed_or_duplicated() {
+        let (map, clock) = map();
+        assert!(map.insert_new("a".to_owned(), 1, TTL).await);
+        assert!(!map.insert_new("a".to_owned(), 2, TTL).await);
+
+        clock.advance(TTL);
+        assert_eq!(map.remove("a").await, None);
+
+        assert!(map.insert_new("a".to_owned(), 3, TTL).await);
+        assert_eq!(map.remove("a").await, Some(3));
+    }
+
+    #[tokio::test]
+    async fn reads_do_not_drop_entries_replaced_after_they_expired() {
+        let (map, clock) = map();
+        map.insert("a".to_owned(), 1, TTL).await;
+        clock.advance(TTL);
+        let read_at = clock.now();
+
+        // as if inserted between a read finding the entry expired and its removal
+        map.insert("a".to_owned(), 2, TTL).await;
+        map.remove_expired("a", read_at).await;
+
+        assert_eq!(map.get("a").await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn evict_expired_drops_only_expired_entries() {
+        let (map, clock) = map();
+        map.insert("old".to_owned(), 1, TTL).await;
+        clock.advance(TTL / 2);
+        map.insert("new".to_owned(), 2, TTL).await;
+        clock.advance(TTL / 2);
+
+        assert_eq!(map.evict_expired().await, 1);
+        assert_eq!(map.get("new").await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn eviction_task_runs_periodically_and_stops_with_the_map() {
+        let (map, clock) = map();
+        map.insert("a".to_owned(), 1, TTL).await;
+        clock.advance(TTL);
+
+        let task = map.spawn_eviction(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(map.entries.read().await.len(), 0);
+
+        drop(map);
+        tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .expect("eviction task must stop once the map is dropped")
+            .unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// A fixed number of OS threads dedicated to password hashing, fed from a bounded queue.
+// Unlike `spawn_blocking`, the amount of memory Argon2 can claim at once is capped at
+// `workers` jobs, and callers fail fast instead of piling up once `queue_depth` jobs are waiting.
+#[derive(Debug)]
+pub struct HashingPool {
+    sender: SyncSender<Job>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HashingPoolError {
+    #[error("Hashing queue is full")]
+    QueueFull,
+    #[error("Hashing job failed")]
+    JobFailed,
+}
+
+impl HashingPool {
+    // one worker per core
+    pub fn default_workers() -> usize {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    }
+
+    pub fn default_queue_depth(workers: usize) -> usize {
+        workers * 16
+    }
+
+    pub fn new(workers: usize, queue_depth: usize) -> Self {
+        let (sender, receiver) = sync_channel::<Job>(queue_depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("password-hasher-{i}"))
+                .spawn(move || run_worker(&receiver))
+                .expect("Failed to spawn password hashing worker");
+        }
+
+        Self { sender }
+    }
+
+    // Runs `job` on a worker thread, inside the caller's tracing span
+    pub async fn run<T, F>(&self, job: F) -> Result<T, HashingPoolError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let current_span = tracing::Span::current();
+        let enqueued_at = Instant::now();
+
+        let job: Job = Box::new(move || {
+            current_span.in_scope(|| {
+                tracing::debug!(
+                    queue_wait_ms = enqueued_at.elapsed().as_secs_f64() * 1000.0,
+                    "Hashing job picked up"
+                );
+                let _ = result_sender.send(job());
+            })
+        });
+
+        self.sender.try_send(job).map_err(|e| match e {
+            TrySendError::Full(_) => {
+                tracing::warn!("Password hashing queue is full");
+                HashingPoolError::QueueFull
+            }
+            TrySendError::Disconnected(_) => HashingPoolError::JobFailed,
+        })?;
+
+        // the sender is dropped without a value if the job panicked
+        result_receiver
+            .await
+            .map_err(|_| HashingPoolError::JobFailed)
+    }
+}
+
+impl Default for HashingPool {
+    fn default() -> Self {
+        let workers = Self::default_workers();
+        Self::new(workers, Self::default_queue_depth(workers))
+    }
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // the lock is released as soon as a job has been received
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        match job {
+            Ok(job) => {
+                let _ = catch_unwind(AssertUnwindSafe(job));
+            }
+            // the pool has been dropped
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn jobs_are_run_on_workers() {
+        let pool = HashingPool::new(2, 4);
+
+        let thread_name = pool
+            .run(|| thread::current().name().map(str::to_owned))
+            .await
+            .unwrap();
+
+        assert!(thread_name.unwrap().starts_with("password-hasher-"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn full_queue_fails_fast() {
+        let pool = Arc::new(HashingPool::new(1, 1));
+        let (release, blocked) = mpsc::channel::<()>();
+        let (started, wait_started) = mpsc::channel::<()>();
+
+        // occupy the only worker
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(move || {
+                    started.send(()).unwrap();
+                    blocked.recv().unwrap();
+                })
+                .await
+            }
+        });
+        wait_started
+            .recv_timeout(Duration::from_secs(5))
+            .expect("worker must pick up the job");
+
+        // fill the queue
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| 42).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(pool.run(|| 0).await, Err(HashingPoolError::QueueFull));
+
+        release.send(()).unwrap();
+        assert!(running.await.unwrap().is_ok());
+        assert_eq!(queued.await.unwrap(), Ok(42));
+    }
+
+    #[tokio::test]
+    async fn panicking_job_does_not_kill_the_worker() {
+        let pool = HashingPool::new(1, 1);
+
+        assert_eq!(
+            pool.run(|| panic!("boom")).await,
+            Err::<(), _>(HashingPoolError::JobFailed)
+        );
+        assert_eq!(pool.run(|| 1).await, Ok(1));
+    }
+}
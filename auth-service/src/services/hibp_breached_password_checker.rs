@@ -0,0 +1,237 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, ErrorKind, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use sha1::{Digest, Sha1};
+
+use crate::domain::BreachedPasswordChecker;
+
+// Looks passwords up in a local copy of the Pwned Passwords (HIBP) SHA-1 dataset, so that
+// passwords never leave the service. Both layouts produced by the official downloader are
+// supported:
+// - a directory of range files, `<PREFIX>.txt` holding `SUFFIX:COUNT` lines (the same
+//   k-anonymity format the range API returns)
+// - a single file of `HASH:COUNT` lines ordered by hash
+pub struct HibpBreachedPasswordChecker {
+    dataset_path: PathBuf,
+}
+
+impl HibpBreachedPasswordChecker {
+    pub fn new(dataset_path: impl Into<PathBuf>) -> Self {
+        Self {
+            dataset_path: dataset_path.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordChecker for HibpBreachedPasswordChecker {
+    #[tracing::instrument(name = "Checking password against breach dataset", skip_all)]
+    async fn breach_count(&self, password: &Secret<String>) -> Result<u64> {
+        let hash = format!("{:X}", Sha1::digest(password.expose_secret().as_bytes()));
+        let dataset_path = self.dataset_path.clone();
+
+        let current_span: tracing::Span = tracing::Span::current();
+        let result = tokio::task::spawn_blocking(move || {
+            current_span.in_scope(|| {
+                if dataset_path.is_dir() {
+                    search_range_file(&dataset_path, &hash)
+                } else {
+                    search_ordered_file(&dataset_path, &hash)
+                }
+            })
+        })
+        .await;
+
+        result?
+    }
+}
+
+fn search_range_file(dir: &Path, hash: &str) -> Result<u64> {
+    let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+    let path = dir.join(format!("{prefix}.txt"));
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        // no range file means no hash with this prefix has ever been seen
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).wrap_err(format!("failed to open {}", path.display())),
+    };
+
+    for line in BufReader::new(file).lines() {
+        let line = line.wrap_err("failed to read range file")?;
+        if let Some(count) = parse_line(&line, suffix)? {
+            return Ok(count);
+        }
+    }
+
+    Ok(0)
+}
+
+// Binary search over byte offsets; `lo` always points at the start of a line
+fn search_ordered_file(path: &Path, hash: &str) -> Result<u64> {
+    let file = File::open(path).wrap_err(format!("failed to open {}", path.display()))?;
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let (line_start, line) = read_line_at_or_after(&mut reader, mid)?;
+
+        if line_start >= hi || line.is_empty() {
+            hi = mid;
+        } else if line_key(&line) < hash {
+            lo = line_start + line.len() as u64;
+        } else {
+            hi = mid;
+        }
+    }
+
+    let (_, line) = read_line_at_or_after(&mut reader, lo)?;
+    Ok(parse_line(line.trim_end(), hash)?.unwrap_or(0))
+}
+
+// Returns the first full line starting at or after `offset` (with its trailing newline)
+fn read_line_at_or_after(reader: &mut BufReader<File>, offset: u64) -> Result<(u64, String)> {
+    let mut line_start = offset;
+    if offset > 0 {
+        reader.seek(SeekFrom::Start(offset - 1))?;
+        let mut partial = Vec::new();
+        line_start = offset - 1 + reader.read_until(b'\n', &mut partial)? as u64;
+    } else {
+        reader.seek(SeekFrom::Start(0))?;
+    }
+
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .wrap_err("failed to read ordered hash file")?;
+
+    Ok((line_start, line))
+}
+
+fn line_key(line: &str) -> &str {
+    line.split(':').next().unwrap_or_default().trim()
+}
+
+fn parse_line(line: &str, key: &str) -> Result<Option<u64>> {
+    match line.trim().split_once(':') {
+        Some((line_key, count)) if line_key.eq_ignore_ascii_case(key) => count
+            .trim()
+            .parse()
+            .map(Some)
+            .wrap_err("invalid breach count in dataset"),
+        _ => Ok(None),
+    }
+}
+
+const PREFIX_LENGTH: usize = 5;
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn sha1(password: &str) -> String {
+        format!("{:X}", Sha1::digest(password.as_bytes()))
+    }
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("hibp-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn ordered_dataset(passwords: &[(&str, u64)]) -> PathBuf {
+        let mut lines: Vec<String> = passwords
+            .iter()
+            .map(|(password, count)| format!("{}:{}", sha1(password), count))
+            .collect();
+        lines.sort();
+
+        let path = temp_path();
+        fs::write(&path, lines.join("\r\n") + "\r\n").unwrap();
+        path
+    }
+
+    fn range_dataset(passwords: &[(&str, u64)]) -> PathBuf {
+        let dir = temp_path();
+        fs::create_dir(&dir).unwrap();
+        for (password, count) in passwords {
+            let hash = sha1(password);
+            let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+            fs::write(
+                dir.join(format!("{prefix}.txt")),
+                format!("{suffix}:{count}\n"),
+            )
+            .unwrap();
+        }
+        dir
+    }
+
+    const BREACHED: [(&str, u64); 6] = [
+        ("password", 9_545_824),
+        ("123456", 37_359_195),
+        ("qwerty", 10_556_095),
+        ("letmein", 600_000),
+        ("Tr0ub4dor&3", 12),
+        ("monkey", 1_200_000),
+    ];
+
+    #[tokio::test]
+    async fn ordered_file_finds_every_breached_password() {
+        let checker = HibpBreachedPasswordChecker::new(ordered_dataset(&BREACHED));
+
+        for (password, count) in BREACHED {
+            let result = checker
+                .breach_count(&password.to_owned().into())
+                .await
+                .expect("lookup must succeed");
+            assert_eq!(result, count, "Failed for {password}");
+        }
+    }
+
+    #[tokio::test]
+    async fn ordered_file_returns_zero_for_unknown_password() {
+        let checker = HibpBreachedPasswordChecker::new(ordered_dataset(&BREACHED));
+
+        for password in ["x7Kp#2vLq9!w", "", "zzzzzzzzzzzz"] {
+            let result = checker
+                .breach_count(&password.to_owned().into())
+                .await
+                .expect("lookup must succeed");
+            assert_eq!(result, 0, "Failed for {password}");
+        }
+    }
+
+    #[tokio::test]
+    async fn range_files_find_breached_password() {
+        let checker = HibpBreachedPasswordChecker::new(range_dataset(&BREACHED));
+
+        let result = checker
+            .breach_count(&"Tr0ub4dor&3".to_owned().into())
+            .await
+            .expect("lookup must succeed");
+        assert_eq!(result, 12);
+
+        let result = checker
+            .breach_count(&"x7Kp#2vLq9!w".to_owned().into())
+            .await
+            .expect("lookup must succeed");
+        assert_eq!(result, 0);
+    }
+
+    #[tokio::test]
+    async fn missing_dataset_is_an_error() {
+        let checker = HibpBreachedPasswordChecker::new(temp_path());
+
+        assert!(checker
+            .breach_count(&"password".to_owned().into())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,534 @@
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash,
+    PasswordHasher as Argon2PasswordHasher, PasswordVerifier, Version,
+};
+use std::sync::{Arc, OnceLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Context, Report, Result};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+
+use super::hashing_pool::{HashingPool, HashingPoolError};
+use crate::domain::UserStoreError;
+
+// Hash formats `verify_password_hash` understands. Only Argon2id is ever produced; the others
+// are accepted for users imported from other systems and get upgraded on their next login.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Argon2,
+    Bcrypt,
+    // PHC (`$pbkdf2-sha256$...`) or Django (`pbkdf2_sha256$...`) format
+    Pbkdf2Sha256,
+    Scrypt,
+}
+
+impl HashAlgorithm {
+    pub fn detect(password_hash: &str) -> Option<Self> {
+        if password_hash.starts_with("$argon2") {
+            Some(Self::Argon2)
+        } else if ["$2a$", "$2b$", "$2y$"]
+            .iter()
+            .any(|prefix| password_hash.starts_with(prefix))
+        {
+            Some(Self::Bcrypt)
+        } else if password_hash.starts_with("$pbkdf2-sha256$")
+            || password_hash.starts_with(DJANGO_PBKDF2_PREFIX)
+        {
+            Some(Self::Pbkdf2Sha256)
+        } else if password_hash.starts_with("$scrypt$") {
+            Some(Self::Scrypt)
+        } else {
+            None
+        }
+    }
+
+    // Like `detect`, but also parses the rest of the hash, so that a malformed one is turned
+    // away when it is imported rather than on the user's first login
+    pub fn parse(password_hash: &str) -> Result<Self> {
+        let algorithm =
+            Self::detect(password_hash).ok_or_else(|| eyre!("unsupported password hash format"))?;
+        match algorithm {
+            Self::Bcrypt => {
+                password_hash
+                    .parse::<bcrypt::HashParts>()
+                    .wrap_err("malformed bcrypt password hash")?;
+            }
+            Self::Pbkdf2Sha256 if !password_hash.starts_with('$') => {
+                DjangoPbkdf2Hash::parse(password_hash)?;
+            }
+            _ => {
+                PasswordHash::new(password_hash).wrap_err("malformed PHC password hash")?;
+            }
+        }
+
+        Ok(algorithm)
+    }
+}
+
+const DJANGO_PBKDF2_PREFIX: &str = "pbkdf2_sha256$";
+// Django itself produces 32 bytes; anything shorter would make guessing easier, down to an
+// empty hash that every password matches
+const DJANGO_PBKDF2_MIN_HASH_LENGTH: usize = 32;
+// Twice what recent Django versions use. Every login runs the whole count on a hashing worker,
+// so a larger one would let a single imported row tie the pool up
+const DJANGO_PBKDF2_MAX_ITERATIONS: u32 = 2_000_000;
+
+// Argon2id cost parameters used for newly computed hashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashingParams {
+    // memory size in KiB
+    pub memory_cost: u32,
+    // number of iterations
+    pub time_cost: u32,
+    // degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for HashingParams {
+    fn default() -> Self {
+        Self {
+            memory_cost: 15000,
+            time_cost: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordHasherError {
+    #[error("Password does not match the hash")]
+    Mismatch,
+    #[error("Password hashing is overloaded")]
+    Overloaded,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl From<HashingPoolError> for PasswordHasherError {
+    fn from(e: HashingPoolError) -> Self {
+        match e {
+            HashingPoolError::QueueFull => Self::Overloaded,
+            e => Self::UnexpectedError(e.into()),
+        }
+    }
+}
+
+impl From<PasswordHasherError> for UserStoreError {
+    fn from(e: PasswordHasherError) -> Self {
+        match e {
+            PasswordHasherError::Mismatch => Self::InvalidCredentials,
+            PasswordHasherError::Overloaded => Self::Overloaded,
+            e => Self::UnexpectedError(e.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PasswordHasher {
+    params: HashingParams,
+    // built on first use unless `with_pool` provides one, so a hasher that is configured
+    // after it is constructed doesn't start worker threads only to drop them
+    pool: Arc<OnceLock<HashingPool>>,
+}
+
+impl PasswordHasher {
+    pub fn new(params: HashingParams) -> Result<Self> {
+        // fail at startup rather than on the first signup
+        Params::new(
+            params.memory_cost,
+            params.time_cost,
+            params.parallelism,
+            None,
+        )
+        .wrap_err("invalid Argon2 parameters")?;
+
+        Ok(Self {
+            params,
+            pool: Arc::default(),
+        })
+    }
+
+    pub fn with_pool(mut self, pool: HashingPool) -> Self {
+        self.pool = Arc::new(OnceLock::from(pool));
+        self
+    }
+
+    fn pool(&self) -> &HashingPool {
+        self.pool.get_or_init(HashingPool::default)
+    }
+
+    #[tracing::instrument(name = "Computing password hash", skip_all)]
+    pub async fn compute_password_hash(
+        &self,
+        password: Secret<String>,
+    ) -> Result<Secret<String>, PasswordHasherError> {
+        let params = self.params;
+
+        self.pool()
+            .run(move || -> Result<Secret<String>> {
+                let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
+                let password_hash = Argon2::new(
+                    Algorithm::Argon2id,
+                    Version::V0x13,
+                    Params::new(
+                        params.memory_cost,
+                        params.time_cost,
+                        params.parallelism,
+                        None,
+                    )?,
+                )
+                .hash_password(password.expose_secret().as_bytes(), &salt)?
+                .to_string();
+
+                Ok(Secret::new(password_hash))
+            })
+            .await?
+            .map_err(PasswordHasherError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Verify password hash", skip_all)]
+    pub async fn verify_password_hash(
+        &self,
+        expected_password_hash: Secret<String>,
+        password_candidate: Secret<String>,
+    ) -> Result<(), PasswordHasherError> {
+        let matches = self
+            .pool()
+            .run(move || {
+                verify(
+                    expected_password_hash.expose_secret(),
+                    password_candidate.expose_secret().as_bytes(),
+                )
+            })
+            .await?
+            .map_err(PasswordHasherError::UnexpectedError)?;
+
+        match matches {
+            true => Ok(()),
+            false => Err(PasswordHasherError::Mismatch),
+        }
+    }
+
+    // A hash needs to be recomputed when it wasn't made with the currently configured
+    // algorithm, version and cost parameters
+    pub fn needs_rehash(&self, password_hash: &Secret<String>) -> bool {
+        let Ok(password_hash) = PasswordHash::new(password_hash.expose_secret()) else {
+            return true;
+        };
+
+        if password_hash.algorithm != Algorithm::Argon2id.ident()
+            || password_hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        match Params::try_from(&password_hash) {
+            Ok(params) => {
+                params.m_cost() != self.params.memory_cost
+                    || params.t_cost() != self.params.time_cost
+                    || params.p_cost() != self.params.parallelism
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+fn verify(password_hash: &str, candidate: &[u8]) -> Result<bool> {
+    // the params (and for PHC strings the algorithm variant) are taken from the hash itself
+    match HashAlgorithm::detect(password_hash) {
+        Some(HashAlgorithm::Argon2) => verify_phc(&Argon2::default(), password_hash, candidate),
+        Some(HashAlgorithm::Bcrypt) => Ok(bcrypt::verify(candidate, password_hash)?),
+        Some(HashAlgorithm::Pbkdf2Sha256) if password_hash.starts_with('$') => {
+            verify_phc(&Pbkdf2, password_hash, candidate)
+        }
+        Some(HashAlgorithm::Pbkdf2Sha256) => verify_django_pbkdf2(password_hash, candidate),
+        Some(HashAlgorithm::Scrypt) => verify_phc(&Scrypt, password_hash, candidate),
+        None => Err(eyre!("unsupported password hash format")),
+    }
+}
+
+fn verify_phc(
+    verifier: &impl PasswordVerifier,
+    password_hash: &str,
+    candidate: &[u8],
+) -> Result<bool> {
+    let password_hash: PasswordHash<'_> = PasswordHash::new(password_hash)?;
+    match verifier.verify_password(candidate, &password_hash) {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(e).wrap_err("failed to verify password hash"),
+    }
+}
+
+fn verify_django_pbkdf2(password_hash: &str, candidate: &[u8]) -> Result<bool> {
+    let DjangoPbkdf2Hash {
+        iterations,
+        salt,
+        hash: expected,
+    } = DjangoPbkdf2Hash::parse(password_hash)?;
+
+    let mut derived = vec![0u8; expected.len()];
+    pbkdf2::pbkdf2_hmac::<Sha256>(candidate, salt.as_bytes(), iterations, &mut derived);
+
+    Ok(derived.ct_eq(&expected).into())
+}
+
+// `pbkdf2_sha256$<iterations>$<salt>$<base64 hash>`
+struct DjangoPbkdf2Hash<'a> {
+    iterations: u32,
+    salt: &'a str,
+    hash: Vec<u8>,
+}
+
+impl<'a> DjangoPbkdf2Hash<'a> {
+    fn parse(password_hash: &'a str) -> Result<Self> {
+        let mut parts = password_hash
+            .strip_prefix(DJANGO_PBKDF2_PREFIX)
+            .ok_or_else(|| eyre!("malformed PBKDF2 password hash"))?
+            .splitn(3, '$');
+        let (Some(iterations), Some(salt), Some(hash)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(eyre!("malformed PBKDF2 password hash"));
+        };
+
+        let iterations: u32 = iterations
+            .parse()
+            .wrap_err("malformed PBKDF2 iteration count")?;
+        if iterations == 0 {
+            return Err(eyre!("malformed PBKDF2 iteration count"));
+        }
+        if iterations > DJANGO_PBKDF2_MAX_ITERATIONS {
+            return Err(eyre!("PBKDF2 iteration count is too high"));
+        }
+
+        let hash = STANDARD.decode(hash).wrap_err("malformed PBKDF2 hash")?;
+        if hash.len() < DJANGO_PBKDF2_MIN_HASH_LENGTH {
+            return Err(eyre!("PBKDF2 hash is too short"));
+        }
+
+        Ok(Self {
+            iterations,
+            salt,
+            hash,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // keep the tests fast
+    const CHEAP: HashingParams = HashingParams {
+        memory_cost: 1024,
+        time_cost: 1,
+        parallelism: 1,
+    };
+
+    fn hasher(params: HashingParams) -> PasswordHasher {
+        PasswordHasher::new(params).expect("params must be valid")
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let params = HashingParams {
+            memory_cost: 1,
+            ..CHEAP
+        };
+        assert!(PasswordHasher::new(params).is_err());
+    }
+
+    #[tokio::test]
+    async fn pool_is_built_on_first_use() {
+        let hasher = hasher(CHEAP);
+        assert!(hasher.pool.get().is_none());
+        let replaced = hasher.clone().with_pool(HashingPool::new(1, 1));
+        assert!(replaced.pool.get().is_some());
+        assert!(hasher.pool.get().is_none());
+
+        hasher
+            .compute_password_hash("password123".to_owned().into())
+            .await
+            .unwrap();
+        assert!(hasher.pool.get().is_some());
+    }
+
+    #[tokio::test]
+    async fn computed_hash_can_be_verified() {
+        let hasher = hasher(CHEAP);
+        let hash = hasher
+            .compute_password_hash("password123".to_owned().into())
+            .await
+            .unwrap();
+
+        assert!(hasher
+            .verify_password_hash(hash.clone(), "password123".to_owned().into())
+            .await
+            .is_ok());
+        assert!(matches!(
+            hasher
+                .verify_password_hash(hash, "password124".to_owned().into())
+                .await,
+            Err(PasswordHasherError::Mismatch)
+        ));
+    }
+
+    #[tokio::test]
+    async fn hash_with_current_params_does_not_need_rehash() {
+        let hasher = hasher(CHEAP);
+        let hash = hasher
+            .compute_password_hash("password123".to_owned().into())
+            .await
+            .unwrap();
+
+        assert!(!hasher.needs_rehash(&hash));
+    }
+
+    #[tokio::test]
+    async fn hash_with_outdated_params_needs_rehash() {
+        let hash = hasher(CHEAP)
+            .compute_password_hash("password123".to_owned().into())
+            .await
+            .unwrap();
+
+        let stronger = hasher(HashingParams {
+            memory_cost: 2048,
+            time_cost: 2,
+            ..CHEAP
+        });
+        assert!(stronger.needs_rehash(&hash));
+    }
+
+    #[test]
+    fn other_algorithms_need_rehash() {
+        let hasher = hasher(CHEAP);
+        let argon2i = Secret::new(
+            "$argon2i$v=19$m=1024,t=1,p=1$c29tZXNhbHQ$iWh06vD8Fy27wf9npn6FXWiCX4K6pW6Ue1Bnzz07Z8A"
+                .to_owned(),
+        );
+        assert!(hasher.needs_rehash(&argon2i));
+        assert!(hasher.needs_rehash(&Secret::new("not a hash".to_owned())));
+    }
+
+    async fn verifies(password_hash: String, candidate: &str) -> bool {
+        hasher(CHEAP)
+            .verify_password_hash(password_hash.into(), candidate.to_owned().into())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn bcrypt_hashes_are_verified() {
+        let hash = bcrypt::hash("password123", 4).unwrap();
+        assert_eq!(HashAlgorithm::detect(&hash), Some(HashAlgorithm::Bcrypt));
+
+        assert!(verifies(hash.clone(), "password123").await);
+        assert!(!verifies(hash, "password124").await);
+    }
+
+    #[tokio::test]
+    async fn pbkdf2_hashes_are_verified() {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let phc = pbkdf2::Pbkdf2
+            .hash_password_customized(
+                b"password123",
+                Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                None,
+                pbkdf2::Params {
+                    rounds: 1000,
+                    output_length: 32,
+                },
+                &salt,
+            )
+            .unwrap()
+            .to_string();
+        let django =
+            "pbkdf2_sha256$1000$s4ltS4lt$mHWKXntbrI8AFThhQcxJj7lYMjQ8934Ib4Wsr+Foo5g=".to_owned();
+
+        for hash in [phc, django] {
+            assert_eq!(
+                HashAlgorithm::detect(&hash),
+                Some(HashAlgorithm::Pbkdf2Sha256)
+            );
+            assert!(
+                verifies(hash.clone(), "password123").await,
+                "Failed for {hash}"
+            );
+            assert!(
+                !verifies(hash.clone(), "password124").await,
+                "Failed for {hash}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_pbkdf2_hashes_match_no_password() {
+        for hash in [
+            "pbkdf2_sha256$1000$s4ltS4lt$",
+            "pbkdf2_sha256$1000$s4ltS4lt$mHWKXntbrI8AFThhQcxJjw==",
+            "pbkdf2_sha256$0$s4ltS4lt$mHWKXntbrI8AFThhQcxJj7lYMjQ8934Ib4Wsr+Foo5g=",
+        ] {
+            assert!(HashAlgorithm::parse(hash).is_err(), "Failed for {hash}");
+            assert!(!verifies(hash.to_owned(), "").await, "Failed for {hash}");
+            assert!(
+                !verifies(hash.to_owned(), "password123").await,
+                "Failed for {hash}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn pbkdf2_hashes_with_too_many_iterations_are_rejected() {
+        for iterations in [DJANGO_PBKDF2_MAX_ITERATIONS + 1, u32::MAX] {
+            let hash = format!(
+                "pbkdf2_sha256${iterations}$s4ltS4lt$mHWKXntbrI8AFThhQcxJj7lYMjQ8934Ib4Wsr+Foo5g="
+            );
+            assert!(HashAlgorithm::parse(&hash).is_err(), "Failed for {hash}");
+            assert!(
+                !verifies(hash.clone(), "password123").await,
+                "Failed for {hash}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn scrypt_hashes_are_verified() {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let hash = scrypt::Scrypt
+            .hash_password_customized(
+                b"password123",
+                None,
+                None,
+                scrypt::Params::new(4, 8, 1, 32).unwrap(),
+                &salt,
+            )
+            .unwrap()
+            .to_string();
+        assert_eq!(HashAlgorithm::detect(&hash), Some(HashAlgorithm::Scrypt));
+
+        assert!(verifies(hash.clone(), "password123").await);
+        assert!(!verifies(hash, "password124").await);
+    }
+
+    #[tokio::test]
+    async fn legacy_hashes_need_rehash() {
+        let hasher = hasher(CHEAP);
+        for hash in [
+            bcrypt::hash("password123", 4).unwrap(),
+            "pbkdf2_sha256$1000$s4ltS4lt$mHWKXntbrI8AFThhQcxJj7lYMjQ8934Ib4Wsr+Foo5g=".to_owned(),
+        ] {
+            assert!(hasher.needs_rehash(&Secret::new(hash)));
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_hash_formats_are_rejected() {
+        assert_eq!(HashAlgorithm::detect("md5$abc"), None);
+        assert!(!verifies("md5$abc".to_owned(), "password123").await);
+    }
+}
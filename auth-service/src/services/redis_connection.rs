@@ -0,0 +1,387 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{eyre, Result};
+use futures_util::FutureExt;
+use redis::{
+    aio::{ConnectionLike, ConnectionManager},
+    cluster::ClusterClient,
+    cluster_async::ClusterConnection,
+    sentinel::{Sentinel, SentinelNodeConnectionInfo},
+    Client, Cmd, ConnectionAddr, ErrorKind, IntoConnectionInfo, Pipeline, RedisError, RedisFuture,
+    RedisResult, TlsMode, Value,
+};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::{Mutex, RwLock};
+
+// Every mode takes full `redis://` or `rediss://` URLs, with credentials and database number
+#[derive(Clone)]
+pub enum RedisTopology {
+    Standalone {
+        url: Secret<String>,
+    },
+    // The master is reached with the credentials, TLS mode and database of the first sentinel URL
+    Sentinel {
+        sentinels: Vec<Secret<String>>,
+        master_name: String,
+    },
+    Cluster {
+        nodes: Vec<Secret<String>>,
+    },
+}
+
+impl RedisTopology {
+    // `urls` is a comma-separated list; `mode` defaults to standalone
+    pub fn parse(
+        mode: Option<&str>,
+        urls: &Secret<String>,
+        sentinel_master: Option<&str>,
+    ) -> Result<Self> {
+        let mut urls: Vec<Secret<String>> = urls
+            .expose_secret()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| Secret::new(url.to_owned()))
+            .collect();
+        if urls.is_empty() {
+            return Err(eyre!("at least one Redis URL is required"));
+        }
+        for url in &urls {
+            url.expose_secret()
+                .as_str()
+                .into_connection_info()
+                .map_err(|e| eyre!("invalid Redis URL: {e}"))?;
+        }
+
+        match mode.unwrap_or("standalone") {
+            "standalone" if urls.len() == 1 => Ok(Self::Standalone {
+                url: urls.remove(0),
+            }),
+            "standalone" => Err(eyre!("standalone mode takes a single Redis URL")),
+            "sentinel" => Ok(Self::Sentinel {
+                sentinels: urls,
+                master_name: sentinel_master
+                    .ok_or_else(|| eyre!("sentinel mode requires a master name"))?
+                    .to_owned(),
+            }),
+            "cluster" => Ok(Self::Cluster { nodes: urls }),
+            mode => Err(eyre!("unsupported Redis mode: {mode}")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RedisConfig {
+    topology: RedisTopology,
+    namespace: String,
+}
+
+impl RedisConfig {
+    pub fn new(topology: RedisTopology) -> Self {
+        Self {
+            topology,
+            namespace: String::new(),
+        }
+    }
+
+    // Lets several environments share one Redis deployment
+    pub fn with_namespace(mut self, namespace: String) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    pub async fn connect(&self) -> RedisResult<RedisConnection> {
+        let conn = match &self.topology {
+            RedisTopology::Standalone { url } => {
+                Connection::Standalone(Box::new(ConnectionManager::new(client(url)?).await?))
+            }
+            RedisTopology::Sentinel {
+                sentinels,
+                master_name,
+            } => Connection::Sentinel(SentinelConnection::connect(sentinels, master_name).await?),
+            RedisTopology::Cluster { nodes } => {
+                let nodes: Vec<&str> = nodes.iter().map(|n| n.expose_secret().as_str()).collect();
+                Connection::Cluster(ClusterClient::new(nodes)?.get_async_connection().await?)
+            }
+        };
+
+        Ok(RedisConnection {
+            conn,
+            namespace: self.namespace.as_str().into(),
+        })
+    }
+
+    // Published messages reach every node of a cluster and every replica of a master, so a
+    // subscriber can use any of them
+    pub async fn pubsub_client(&self) -> RedisResult<Client> {
+        match &self.topology {
+            RedisTopology::Standalone { url } => client(url),
+            RedisTopology::Sentinel {
+                sentinels,
+                master_name,
+            } => {
+                let (mut sentinel, node_info) = sentinel(sentinels)?;
+                sentinel
+                    .async_master_for(master_name, Some(&node_info))
+                    .await
+            }
+            RedisTopology::Cluster { nodes } => client(&nodes[0]),
+        }
+    }
+}
+
+// A connection to whichever Redis deployment is configured, which keeps track of failovers and
+// cluster slots by itself. Cheap to clone.
+#[derive(Clone)]
+pub struct RedisConnection {
+    conn: Connection,
+    namespace: Arc<str>,
+}
+
+#[derive(Clone)]
+enum Connection {
+    Standalone(Box<ConnectionManager>),
+    Sentinel(SentinelConnection),
+    Cluster(ClusterConnection),
+}
+
+impl RedisConnection {
+    // Prefixes `key` with the configured namespace
+    pub fn key(&self, key: &str) -> String {
+        namespaced(&self.namespace, key)
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match &mut self.conn {
+            Connection::Standalone(conn) => conn.req_packed_command(cmd),
+            Connection::Sentinel(conn) => conn.req_packed_command(cmd),
+            Connection::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match &mut self.conn {
+            Connection::Standalone(conn) => conn.req_packed_commands(cmd, offset, count),
+            Connection::Sentinel(conn) => conn.req_packed_commands(cmd, offset, count),
+            Connection::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match &self.conn {
+            Connection::Standalone(conn) => conn.get_db(),
+            Connection::Sentinel(conn) => conn.get_db(),
+            Connection::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+// Talks to the master the sentinels point at, and asks them again once it stops being reachable
+// or writable
+#[derive(Clone)]
+struct SentinelConnection {
+    state: Arc<SentinelState>,
+}
+
+struct SentinelState {
+    sentinel: Mutex<Sentinel>,
+    master_name: String,
+    node_info: SentinelNodeConnectionInfo,
+    // bumped on every rediscovery, so that concurrent failures trigger a single one
+    master: RwLock<(u64, ConnectionManager)>,
+}
+
+impl SentinelConnection {
+    async fn connect(sentinels: &[Secret<String>], master_name: &str) -> RedisResult<Self> {
+        let (mut sentinel, node_info) = sentinel(sentinels)?;
+        let master = sentinel
+            .async_master_for(master_name, Some(&node_info))
+            .await?;
+        let master = ConnectionManager::new(master).await?;
+
+        Ok(Self {
+            state: Arc::new(SentinelState {
+                sentinel: Mutex::new(sentinel),
+                master_name: master_name.to_owned(),
+                node_info,
+                master: RwLock::new((0, master)),
+            }),
+        })
+    }
+
+    async fn master(&self) -> (u64, ConnectionManager) {
+        self.state.master.read().await.clone()
+    }
+
+    async fn rediscover(&self, generation: u64) -> RedisResult<ConnectionManager> {
+        let mut sentinel = self.state.sentinel.lock().await;
+
+        let (current_generation, master) = self.master().await;
+        if current_generation != generation {
+            return Ok(master);
+        }
+
+        let client = sentinel
+            .async_master_for(&self.state.master_name, Some(&self.state.node_info))
+            .await?;
+        let master = ConnectionManager::new(client).await?;
+        tracing::warn!("Reconnected to the Redis master reported by Sentinel");
+
+        *self.state.master.write().await = (generation + 1, master.clone());
+        Ok(master)
+    }
+
+    // Returns the master to retry on, if the command cannot have reached the old one
+    async fn recover(&self, generation: u64, err: RedisError) -> RedisResult<ConnectionManager> {
+        let retry = err.kind() == ErrorKind::ReadOnly || err.is_connection_refusal();
+        if !retry && !err.is_io_error() && !err.is_connection_dropped() {
+            return Err(err);
+        }
+
+        let master = self.rediscover(generation).await?;
+        if retry {
+            Ok(master)
+        } else {
+            Err(err)
+        }
+    }
+}
+
+impl ConnectionLike for SentinelConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        async move {
+            let (generation, mut master) = self.master().await;
+            match master.req_packed_command(cmd).await {
+                Err(e) => {
+                    let mut master = self.recover(generation, e).await?;
+                    master.req_packed_command(cmd).await
+                }
+                result => result,
+            }
+        }
+        .boxed()
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        async move {
+            let (generation, mut master) = self.master().await;
+            match master.req_packed_commands(cmd, offset, count).await {
+                Err(e) => {
+                    let mut master = self.recover(generation, e).await?;
+                    master.req_packed_commands(cmd, offset, count).await
+                }
+                result => result,
+            }
+        }
+        .boxed()
+    }
+
+    fn get_db(&self) -> i64 {
+        self.state
+            .node_info
+            .redis_connection_info
+            .as_ref()
+            .map_or(0, |info| info.db)
+    }
+}
+
+fn namespaced(namespace: &str, key: &str) -> String {
+    if namespace.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{namespace}:{key}")
+    }
+}
+
+fn client(url: &Secret<String>) -> RedisResult<Client> {
+    Client::open(url.expose_secret().as_str())
+}
+
+fn sentinel(sentinels: &[Secret<String>]) -> RedisResult<(Sentinel, SentinelNodeConnectionInfo)> {
+    let infos = sentinels
+        .iter()
+        .map(|url| url.expose_secret().as_str().into_connection_info())
+        .collect::<RedisResult<Vec<_>>>()?;
+
+    let first = &infos[0];
+    let node_info = SentinelNodeConnectionInfo {
+        tls_mode: match &first.addr {
+            ConnectionAddr::TcpTls { insecure: true, .. } => Some(TlsMode::Insecure),
+            ConnectionAddr::TcpTls { .. } => Some(TlsMode::Secure),
+            _ => None,
+        },
+        redis_connection_info: Some(first.redis.clone()),
+    };
+
+    Ok((Sentinel::build(infos)?, node_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(urls: &str) -> Secret<String> {
+        Secret::new(urls.to_owned())
+    }
+
+    #[test]
+    fn standalone_is_the_default_mode() {
+        let topology = RedisTopology::parse(None, &urls("rediss://user:pw@host:6380/2"), None);
+        assert!(matches!(
+            topology,
+            Ok(RedisTopology::Standalone { url }) if url.expose_secret() == "rediss://user:pw@host:6380/2"
+        ));
+
+        assert!(RedisTopology::parse(None, &urls("redis://a/,redis://b/"), None).is_err());
+    }
+
+    #[test]
+    fn sentinel_mode_requires_a_master_name() {
+        let sentinels = urls("redis://s1:26379/, redis://s2:26379/");
+
+        assert!(RedisTopology::parse(Some("sentinel"), &sentinels, None).is_err());
+        assert!(matches!(
+            RedisTopology::parse(Some("sentinel"), &sentinels, Some("mymaster")),
+            Ok(RedisTopology::Sentinel { sentinels, master_name })
+                if sentinels.len() == 2 && master_name == "mymaster"
+        ));
+    }
+
+    #[test]
+    fn cluster_mode_takes_every_node() {
+        let nodes = urls("redis://n1:7000/,redis://n2:7001/,redis://n3:7002/");
+
+        assert!(matches!(
+            RedisTopology::parse(Some("cluster"), &nodes, None),
+            Ok(RedisTopology::Cluster { nodes }) if nodes.len() == 3
+        ));
+    }
+
+    #[test]
+    fn invalid_configuration_is_rejected() {
+        assert!(RedisTopology::parse(None, &urls(""), None).is_err());
+        assert!(RedisTopology::parse(None, &urls("http://host/"), None).is_err());
+        assert!(RedisTopology::parse(Some("replicated"), &urls("redis://host/"), None).is_err());
+    }
+
+    #[test]
+    fn keys_are_namespaced() {
+        assert_eq!(namespaced("", "banned_tokens"), "banned_tokens");
+        assert_eq!(
+            namespaced("staging", "banned_tokens"),
+            "staging:banned_tokens"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{domain::Email, services::password_hasher::HashAlgorithm};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    JsonLines,
+    Csv,
+}
+
+impl ImportFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl" | "ndjson") => Ok(Self::JsonLines),
+            Some("csv") => Ok(Self::Csv),
+            _ => Err(eyre!(
+                "cannot infer import format of {}, expected a .jsonl or .csv file",
+                path.display()
+            )),
+        }
+    }
+}
+
+// A user exported from another system, with the password hash kept as-is
+#[derive(Debug)]
+pub struct ImportedUser {
+    pub email: Email,
+    pub password_hash: Secret<String>,
+    pub requires_2fa: bool,
+}
+
+#[derive(Deserialize)]
+struct ImportRecord {
+    email: String,
+    hash: String,
+    #[serde(default)]
+    requires_2fa: bool,
+}
+
+impl TryFrom<ImportRecord> for ImportedUser {
+    type Error = color_eyre::eyre::Report;
+
+    fn try_from(record: ImportRecord) -> Result<Self> {
+        let email = Email::parse(Secret::new(record.email))?;
+        HashAlgorithm::parse(&record.hash)?;
+
+        Ok(Self {
+            email,
+            password_hash: Secret::new(record.hash),
+            requires_2fa: record.requires_2fa,
+        })
+    }
+}
+
+// Reads `email, hash, requires_2fa` records; the whole file is rejected if any record is invalid,
+// so that an import never stops half way because of a typo
+pub fn read_users(reader: impl Read, format: ImportFormat) -> Result<Vec<ImportedUser>> {
+    match format {
+        ImportFormat::JsonLines => read_json_lines(reader),
+        ImportFormat::Csv => read_csv(reader),
+    }
+}
+
+fn read_json_lines(reader: impl Read) -> Result<Vec<ImportedUser>> {
+    let mut users = Vec::new();
+    for (index, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line.wrap_err("failed to read import file")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let user = serde_json::from_str::<ImportRecord>(&line)
+            .map_err(Into::into)
+            .and_then(ImportedUser::try_from)
+            .wrap_err(format!("invalid record on line {}", index + 1))?;
+        users.push(user);
+    }
+
+    Ok(users)
+}
+
+fn read_csv(reader: impl Read) -> Result<Vec<ImportedUser>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let mut users = Vec::new();
+    for record in reader.deserialize::<ImportRecord>() {
+        // the header is line 1
+        let line = users.len() + 2;
+        let user = record
+            .map_err(Into::into)
+            .and_then(ImportedUser::try_from)
+            .wrap_err(format!("invalid record on line {line}"))?;
+        users.push(user);
+    }
+
+    Ok(users)
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    const BCRYPT_HASH: &str = "$2b$04$MjSBp5bBZ4ZZ0X3G9uj2bO4sB.OBJ0dIEvFMdU3ysi6GNnGQuK2Ya";
+    const DJANGO_HASH: &str =
+        "pbkdf2_sha256$1000$s4ltS4lt$mHWKXntbrI8AFThhQcxJj7lYMjQ8934Ib4Wsr+Foo5g=";
+
+    #[test]
+    fn format_is_inferred_from_extension() {
+        assert_eq!(
+            ImportFormat::from_path(Path::new("users.jsonl")).unwrap(),
+            ImportFormat::JsonLines
+        );
+        assert_eq!(
+            ImportFormat::from_path(Path::new("users.csv")).unwrap(),
+            ImportFormat::Csv
+        );
+        assert!(ImportFormat::from_path(Path::new("users.txt")).is_err());
+    }
+
+    #[test]
+    fn json_lines_are_read() {
+        let input = format!(
+            "{{\"email\":\"a@example.com\",\"hash\":\"{BCRYPT_HASH}\",\"requires_2fa\":true}}\n\n\
+             {{\"email\":\"b@example.com\",\"hash\":\"{DJANGO_HASH}\"}}\n"
+        );
+
+        let users = read_users(input.as_bytes(), ImportFormat::JsonLines).unwrap();
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].email.as_ref().expose_secret(), "a@example.com");
+        assert_eq!(users[0].password_hash.expose_secret(), BCRYPT_HASH);
+        assert!(users[0].requires_2fa);
+        assert!(!users[1].requires_2fa);
+    }
+
+    #[test]
+    fn csv_is_read() {
+        let input = format!(
+            "email, hash, requires_2fa\n\
+             a@example.com, {BCRYPT_HASH}, false\n\
+             b@example.com, {DJANGO_HASH}, true\n"
+        );
+
+        let users = read_users(input.as_bytes(), ImportFormat::Csv).unwrap();
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[1].email.as_ref().expose_secret(), "b@example.com");
+        assert_eq!(users[1].password_hash.expose_secret(), DJANGO_HASH);
+        assert!(users[1].requires_2fa);
+    }
+
+    #[test]
+    fn invalid_records_reject_the_file() {
+        let input = format!(
+            "email,hash,requires_2fa\n\
+             a@example.com,{BCRYPT_HASH},false\n\
+             not-an-email,{BCRYPT_HASH},false\n"
+        );
+        let err = read_users(input.as_bytes(), ImportFormat::Csv).unwrap_err();
+        assert!(err.to_string().contains("line 3"));
+
+        let input = "{\"email\":\"a@example.com\",\"hash\":\"md5$abc\"}\n";
+        let err = read_users(input.as_bytes(), ImportFormat::JsonLines).unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+
+        // recognised, but without a hash every password would match
+        let input = "email,hash,requires_2fa\n\
+                     a@example.com,pbkdf2_sha256$1000$s4ltS4lt$,false\n";
+        let err = read_users(input.as_bytes(), ImportFormat::Csv).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
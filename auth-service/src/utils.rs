@@ -1,3 +1,5 @@
 pub mod auth;
+pub mod auth_token;
 pub mod constants;
+pub mod password;
 pub mod tracing;
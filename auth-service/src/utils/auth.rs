@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Result, WrapErr};
@@ -5,13 +7,111 @@ use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
-use crate::{app_state::BannedTokenStoreType, domain::Email, utils::constants::JWT_SECRET};
+use crate::{
+    app_state::{
+        BannedTokenStoreType, OrgStoreType, PersonalAccessTokenStoreType, RoleStoreType,
+        UserStoreType,
+    },
+    domain::{
+        AuthAPIError, Authentication, Grants, OrgId, OrgStoreError, PersonalAccessTokenSecret,
+        PersonalAccessTokenStoreError, UserId, UserStoreError,
+    },
+    utils::constants::JWT_SECRET,
+};
 
 use super::constants::JWT_COOKIE_NAME;
 
+pub const DEFAULT_TOKEN_ISSUER: &str = "auth-service";
+pub const DEFAULT_TOKEN_AUDIENCE: &str = "app-service";
+// jsonwebtoken's own default
+const DEFAULT_TOKEN_LEEWAY_SECONDS: u64 = 60;
+
+// Who tokens are issued by, the apps they may be issued for, and how much clock skew is
+// allowed when checking their `exp` and `nbf`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSettings {
+    pub issuer: String,
+    pub audiences: Vec<String>,
+    pub leeway_seconds: u64,
+}
+
+impl TokenSettings {
+    // Comma-separated, as in the environment variable
+    pub fn parse_audiences(audiences: &str) -> Result<Vec<String>> {
+        let audiences: Vec<String> = audiences
+            .split(',')
+            .map(|audience| audience.trim().to_owned())
+            .collect();
+        if audiences.iter().any(String::is_empty) {
+            return Err(eyre!("Token audiences must not be empty"));
+        }
+
+        Ok(audiences)
+    }
+
+    // The audience of a new token: the one asked for, if it's configured, or the first one
+    pub fn audience<'a>(&'a self, requested: Option<&'a str>) -> Result<&'a str, AuthAPIError> {
+        match requested {
+            Some(audience) if self.audiences.iter().any(|a| a == audience) => Ok(audience),
+            Some(_) => Err(AuthAPIError::InvalidAudience),
+            None => self
+                .audiences
+                .first()
+                .map(String::as_str)
+                .ok_or(AuthAPIError::InvalidAudience),
+        }
+    }
+
+    // Whether an app naming itself `requested` may rely on the token. Apps can only leave their
+    // name out when tokens are issued for a single one; otherwise a token issued for another
+    // app would pass
+    pub fn check_audience(
+        &self,
+        claims: &Claims,
+        requested: Option<&str>,
+    ) -> Result<(), AuthAPIError> {
+        match requested {
+            Some(audience) if claims.has_audience(audience) => Ok(()),
+            Some(_) => Err(AuthAPIError::InvalidToken),
+            None if self.audiences.len() > 1 => Err(AuthAPIError::MissingAudience),
+            None => Ok(()),
+        }
+    }
+
+    // Tokens issued for any of the audiences are accepted here
+    fn validation(&self) -> Validation {
+        let mut validation = Validation::default();
+        validation.leeway = self.leeway_seconds;
+        validation.validate_nbf = true;
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&self.audiences);
+        validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+        validation
+    }
+}
+
+impl Default for TokenSettings {
+    fn default() -> Self {
+        Self {
+            issuer: DEFAULT_TOKEN_ISSUER.to_owned(),
+            audiences: vec![DEFAULT_TOKEN_AUDIENCE.to_owned()],
+            leeway_seconds: DEFAULT_TOKEN_LEEWAY_SECONDS,
+        }
+    }
+}
+
+// `org_id` scopes the token to one of the user's organizations, `audience` is the app it is
+// for, one of `settings.audiences`, and `authentication` is how the user last proved who they are
 #[tracing::instrument(skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    user_id: &UserId,
+    grants: &Grants,
+    org_id: Option<&OrgId>,
+    audience: &str,
+    authentication: &Authentication,
+    settings: &TokenSettings,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user_id, grants, org_id, audience, authentication, settings)?;
     Ok(create_auth_cookie(token))
 }
 
@@ -29,11 +129,24 @@ fn create_auth_cookie(token: Secret<String>) -> Cookie<'static> {
 pub const TOKEN_TTL_SECONDS: i64 = 600;
 
 #[tracing::instrument(skip_all)]
-fn generate_auth_token(email: &Email) -> Result<Secret<String>> {
+fn generate_auth_token(
+    user_id: &UserId,
+    grants: &Grants,
+    org_id: Option<&OrgId>,
+    audience: &str,
+    authentication: &Authentication,
+    settings: &TokenSettings,
+) -> Result<Secret<String>> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .ok_or(eyre!("failed to create 10 minute time delta"))?;
 
-    let exp = Utc::now()
+    let now = Utc::now();
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast iat time to usize")?;
+
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(eyre!("failed to add 10 minutes to current time"))?
         .timestamp();
@@ -43,34 +156,170 @@ fn generate_auth_token(email: &Email) -> Result<Secret<String>> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().to_owned();
+    let sub = user_id.to_string();
+    let jti = uuid::Uuid::new_v4().to_string();
+    let roles = grants.roles.iter().map(ToString::to_string).collect();
+    let scope = grants
+        .permissions
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<&str>>()
+        .join(" ");
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        iss: settings.issuer.clone(),
+        sub,
+        aud: vec![audience.to_owned()],
+        exp,
+        nbf: iat,
+        iat,
+        jti,
+        auth_time: authentication.time.timestamp().unsigned_abs() as usize,
+        amr: authentication.amr(),
+        acr: authentication.acr().to_owned(),
+        roles,
+        scope,
+        org_id: org_id.map(ToString::to_string),
+    };
 
     create_token(&claims)
 }
 
+// Besides checking the token itself, makes sure its user still exists and may use it, and
+// still belongs to the organization the token is scoped to
 #[tracing::instrument(skip_all)]
 pub async fn validate_token(
     token: &Secret<String>,
     banned_token_store: BannedTokenStoreType,
-) -> Result<Claims> {
-    match banned_token_store.read().await.contains_token(token).await {
-        Ok(value) => {
-            if value {
-                return Err(eyre!("token is banned"));
+    user_store: UserStoreType,
+    org_store: OrgStoreType,
+    settings: &TokenSettings,
+) -> Result<Claims, AuthAPIError> {
+    let claims = decode_token(token, settings)?;
+
+    if banned_token_store
+        .contains_token(&claims.jti)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+    {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let user_id = UserId::parse(&claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    ensure_user_may_use_token(&user_store, &user_id, claims.iat as i64).await?;
+
+    // A token scoped to an organization stops working once its user leaves it
+    if let Some(org_id) = claims.org_id.as_deref() {
+        let org_id = OrgId::parse(org_id).map_err(|_| AuthAPIError::InvalidToken)?;
+        match org_store.get_role(&org_id, &user_id).await {
+            Ok(_) => {}
+            Err(OrgStoreError::OrgNotFound | OrgStoreError::NotMember) => {
+                return Err(AuthAPIError::InvalidToken)
             }
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
         }
-        Err(e) => return Err(e.into()),
     }
 
+    Ok(claims)
+}
+
+// Only checks that the token was issued here and is current; `validate_token` also makes sure
+// it can still be used
+pub fn decode_token(
+    token: &Secret<String>,
+    settings: &TokenSettings,
+) -> Result<Claims, AuthAPIError> {
     decode::<Claims>(
         token.expose_secret(),
         &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
+        &settings.validation(),
     )
     .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+// Personal access tokens get claims like those of login tokens, for every audience, without
+// roles, and with the scopes they were created with that their owner still holds. Every use is
+// recorded
+#[tracing::instrument(skip_all)]
+pub async fn validate_personal_access_token(
+    token: &Secret<String>,
+    personal_access_token_store: PersonalAccessTokenStoreType,
+    user_store: UserStoreType,
+    role_store: RoleStoreType,
+    settings: &TokenSettings,
+) -> Result<Claims, AuthAPIError> {
+    let secret =
+        PersonalAccessTokenSecret::parse(token.clone()).map_err(|_| AuthAPIError::InvalidToken)?;
+    let map_store_error = |e| match e {
+        PersonalAccessTokenStoreError::TokenNotFound => AuthAPIError::InvalidToken,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    };
+
+    let pat = personal_access_token_store
+        .get_token_by_hash(&secret.hash())
+        .await
+        .map_err(map_store_error)?;
+    let now = Utc::now();
+    if pat.is_expired(now) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    ensure_user_may_use_token(&user_store, &pat.user_id, pat.created_at.timestamp()).await?;
+
+    let grants = role_store
+        .get_grants(&pat.user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    personal_access_token_store
+        .record_use(&pat.id, now)
+        .await
+        .map_err(map_store_error)?;
+
+    let scope = pat
+        .scopes
+        .iter()
+        .filter(|scope| grants.permissions.contains(scope))
+        .map(AsRef::as_ref)
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    let iat = pat.created_at.timestamp().unsigned_abs() as usize;
+    Ok(Claims {
+        iss: settings.issuer.clone(),
+        sub: pat.user_id.to_string(),
+        aud: settings.audiences.clone(),
+        exp: pat.expires_at.timestamp().unsigned_abs() as usize,
+        nbf: iat,
+        iat,
+        jti: pat.id.to_string(),
+        auth_time: iat,
+        amr: Vec::new(),
+        acr: Authentication::NONE.to_owned(),
+        roles: Vec::new(),
+        scope,
+        org_id: None,
+    })
+}
+
+// The user must still exist, be active, and not have had their sessions revoked since
+async fn ensure_user_may_use_token(
+    user_store: &UserStoreType,
+    user_id: &UserId,
+    issued_at: i64,
+) -> Result<(), AuthAPIError> {
+    let user = user_store
+        .get_user_by_id(user_id)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    user.status.ensure_active()?;
+    if user.is_revoked(issued_at) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    Ok(())
 }
 
 #[tracing::instrument(skip_all)]
@@ -84,27 +333,101 @@ fn create_token(claims: &Claims) -> Result<Secret<String>> {
     .wrap_err("failed to create token")
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
+    pub iss: String,
     pub sub: String,
+    // The apps the token may be used with
+    pub aud: Vec<String>,
     pub exp: usize,
+    pub nbf: usize,
+    pub iat: usize,
+    pub jti: String,
+    // When and how the user last authenticated, which re-issued tokens keep
+    pub auth_time: usize,
+    pub amr: Vec<String>,
+    pub acr: String,
+    pub roles: Vec<String>,
+    // Space-separated permissions granted by the roles
+    pub scope: String,
+    // Set on tokens scoped to an organization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.scope.split_whitespace().any(|p| p == permission)
+    }
+
+    pub fn has_audience(&self, audience: &str) -> bool {
+        self.aud.iter().any(|a| a == audience)
+    }
+
+    pub fn authentication(&self) -> Authentication {
+        Authentication::from_claims(self.auth_time, &self.amr)
+    }
+
+    // How long a ban on this token must last: until `exp`, plus the leeway validation allows past it
+    pub fn ban_ttl(&self, settings: &TokenSettings) -> Duration {
+        let valid_until = (self.exp as u64).saturating_add(settings.leeway_seconds);
+        let now = Utc::now().timestamp().unsigned_abs();
+
+        Duration::from_secs(valid_until.saturating_sub(now))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::domain::BannedTokenStore;
+    use crate::domain::{
+        AccountStatus, AuthMethod, BannedTokenStore, Email, OrgRole, OrgStore, Organization,
+        Password, Permission, PersonalAccessToken, PersonalAccessTokenStore, RoleName, RoleStore,
+        StatusChange, User, UserStore, UserUpdate,
+    };
     use std::sync::Arc;
 
-    use tokio::sync::RwLock;
-
-    use crate::services::data_stores::HashsetBannedTokenStore;
+    use crate::services::data_stores::{
+        HashmapOrgStore, HashmapPersonalAccessTokenStore, HashmapRoleStore, HashmapUserStore,
+        HashsetBannedTokenStore,
+    };
 
     use super::*;
 
+    fn password_login() -> Authentication {
+        Authentication::now(vec![AuthMethod::Password])
+    }
+
+    fn org_store() -> Arc<HashmapOrgStore> {
+        Arc::new(HashmapOrgStore::default())
+    }
+
+    async fn user_store() -> (Arc<HashmapUserStore>, UserId) {
+        let store = Arc::new(HashmapUserStore::default());
+        let user = User::new(
+            Email::parse("test@example.com".to_owned().into()).unwrap(),
+            Password::parse("password123".to_owned().into()).unwrap(),
+            false,
+        );
+        let id = user.id;
+        store.add_user(user).await.unwrap();
+        (store, id)
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(
+            &UserId::default(),
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -125,18 +448,41 @@ mod tests {
 
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(
+            &UserId::default(),
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
         assert_eq!(result.expose_secret().split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_tokens = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_tokens).await.unwrap();
-        assert_eq!(result.sub, "test@example.com");
+        let (users, user_id) = user_store().await;
+        let token = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(
+            &token,
+            banned_tokens,
+            users,
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.sub, user_id.to_string());
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -146,25 +492,527 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_scoped_to_an_org_the_user_left() {
+        let (users, user_id) = user_store().await;
+        let orgs = org_store();
+        let org = Organization::new("Acme".to_owned());
+        orgs.create_org(&org, &UserId::default()).await.unwrap();
+        orgs.add_member(&org.id, &user_id, OrgRole::Member)
+            .await
+            .unwrap();
+        let token = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            Some(&org.id),
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+
+        let claims = validate_token(
+            &token,
+            banned_tokens.clone(),
+            users.clone(),
+            orgs.clone(),
+            &TokenSettings::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.org_id, Some(org.id.to_string()));
+
+        orgs.remove_member(&org.id, &user_id).await.unwrap();
+        let result = validate_token(
+            &token,
+            banned_tokens,
+            users,
+            orgs,
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_valid_but_banned_token() {
-        let email = Email::parse("test@example.com".to_owned().into()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_tokens = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let (users, user_id) = user_store().await;
+        let token = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+        let claims = validate_token(
+            &token,
+            banned_tokens.clone(),
+            users.clone(),
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await
+        .unwrap();
         banned_tokens
-            .write()
-            .await
-            .add_token(token.clone())
+            .add_token(&claims.jti, claims.ban_ttl(&TokenSettings::default()))
             .await
             .expect("Must have added a token");
-        assert!(validate_token(&token, banned_tokens).await.is_err());
+        assert!(matches!(
+            validate_token(
+                &token,
+                banned_tokens,
+                users,
+                org_store(),
+                &TokenSettings::default()
+            )
+            .await,
+            Err(AuthAPIError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tokens_have_unique_jti() {
+        let (users, user_id) = user_store().await;
+        let banned_tokens: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+        let first = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let second = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+
+        let first = validate_token(
+            &first,
+            banned_tokens.clone(),
+            users.clone(),
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await
+        .unwrap();
+        let second = validate_token(
+            &second,
+            banned_tokens,
+            users,
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await
+        .unwrap();
+        assert_ne!(first.jti, second.jti);
+    }
+
+    #[test]
+    fn test_ban_ttl_covers_remaining_lifetime_and_leeway() {
+        let now = Utc::now().timestamp() as usize;
+        let claims = |exp| Claims {
+            iss: DEFAULT_TOKEN_ISSUER.to_owned(),
+            sub: UserId::default().to_string(),
+            aud: vec![DEFAULT_TOKEN_AUDIENCE.to_owned()],
+            exp,
+            nbf: now,
+            iat: now,
+            jti: "jti".to_owned(),
+            auth_time: now,
+            amr: Vec::new(),
+            acr: Authentication::NONE.to_owned(),
+            roles: Vec::new(),
+            scope: String::new(),
+            org_id: None,
+        };
+        let settings = TokenSettings::default();
+        let leeway = settings.leeway_seconds;
+
+        let ttl = claims(now + 120).ban_ttl(&settings).as_secs();
+        assert!((119 + leeway..=120 + leeway).contains(&ttl));
+
+        // expired longer ago than the leeway: nothing left to ban
+        assert_eq!(
+            claims(now - leeway as usize - 10).ban_ttl(&settings),
+            Duration::ZERO
+        );
     }
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
+        let (users, _) = user_store().await;
         let token = "invalid_token".to_owned().into();
-        let banned_tokens = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_tokens).await;
-        assert!(result.is_err());
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(
+            &token,
+            banned_tokens,
+            users,
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_checks_issuer_and_audience() {
+        let (users, user_id) = user_store().await;
+        let banned_tokens: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+        let settings = TokenSettings {
+            audiences: vec![DEFAULT_TOKEN_AUDIENCE.to_owned(), "billing".to_owned()],
+            ..Default::default()
+        };
+        let token = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            None,
+            "billing",
+            &password_login(),
+            &settings,
+        )
+        .unwrap();
+
+        let claims = validate_token(
+            &token,
+            banned_tokens.clone(),
+            users.clone(),
+            org_store(),
+            &settings,
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.iss, DEFAULT_TOKEN_ISSUER);
+        assert!(claims.has_audience("billing"));
+        assert!(!claims.has_audience(DEFAULT_TOKEN_AUDIENCE));
+
+        // not one of the audiences accepted by the default settings
+        let result = validate_token(
+            &token,
+            banned_tokens.clone(),
+            users.clone(),
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+
+        let other_issuer = TokenSettings {
+            issuer: "other-issuer".to_owned(),
+            ..settings
+        };
+        let result = validate_token(&token, banned_tokens, users, org_store(), &other_issuer).await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_not_valid_before_nbf_beyond_leeway() {
+        let (users, user_id) = user_store().await;
+        let banned_tokens: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+        let settings = TokenSettings::default();
+        let now = Utc::now().timestamp() as usize;
+        let token = |nbf| {
+            create_token(&Claims {
+                iss: DEFAULT_TOKEN_ISSUER.to_owned(),
+                sub: user_id.to_string(),
+                aud: vec![DEFAULT_TOKEN_AUDIENCE.to_owned()],
+                exp: now + 600,
+                nbf,
+                iat: now,
+                jti: "jti".to_owned(),
+                auth_time: now,
+                amr: password_login().amr(),
+                acr: Authentication::SINGLE_FACTOR.to_owned(),
+                roles: Vec::new(),
+                scope: String::new(),
+                org_id: None,
+            })
+            .unwrap()
+        };
+
+        // a clock slightly behind the issuer's is tolerated
+        let skewed = token(now + settings.leeway_seconds as usize / 2);
+        assert!(validate_token(
+            &skewed,
+            banned_tokens.clone(),
+            users.clone(),
+            org_store(),
+            &settings
+        )
+        .await
+        .is_ok());
+
+        let early = token(now + settings.leeway_seconds as usize + 60);
+        let result = validate_token(&early, banned_tokens, users, org_store(), &settings).await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
+    #[test]
+    fn test_token_settings_pick_a_configured_audience() {
+        let settings = TokenSettings {
+            audiences: TokenSettings::parse_audiences("app-service, billing").unwrap(),
+            ..Default::default()
+        };
+
+        assert_eq!(settings.audience(None).unwrap(), "app-service");
+        assert_eq!(settings.audience(Some("billing")).unwrap(), "billing");
+        assert!(matches!(
+            settings.audience(Some("reports")),
+            Err(AuthAPIError::InvalidAudience)
+        ));
+        assert!(TokenSettings::parse_audiences("app-service,").is_err());
+    }
+
+    #[test]
+    fn test_token_settings_require_the_audience_when_there_are_several() {
+        let claims = Claims {
+            iss: DEFAULT_TOKEN_ISSUER.to_owned(),
+            sub: UserId::default().to_string(),
+            aud: vec!["billing".to_owned()],
+            exp: 0,
+            nbf: 0,
+            iat: 0,
+            jti: "jti".to_owned(),
+            auth_time: 0,
+            amr: Vec::new(),
+            acr: Authentication::NONE.to_owned(),
+            roles: Vec::new(),
+            scope: String::new(),
+            org_id: None,
+        };
+        let single = TokenSettings::default();
+        let several = TokenSettings {
+            audiences: TokenSettings::parse_audiences("app-service, billing").unwrap(),
+            ..Default::default()
+        };
+
+        assert!(single.check_audience(&claims, None).is_ok());
+        assert!(several.check_audience(&claims, Some("billing")).is_ok());
+        assert!(matches!(
+            several.check_audience(&claims, Some("app-service")),
+            Err(AuthAPIError::InvalidToken)
+        ));
+        assert!(matches!(
+            several.check_audience(&claims, None),
+            Err(AuthAPIError::MissingAudience)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_of_unknown_user() {
+        let (users, _) = user_store().await;
+        let token = generate_auth_token(
+            &UserId::default(),
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(
+            &token,
+            banned_tokens,
+            users,
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_of_locked_user() {
+        let (users, user_id) = user_store().await;
+        let token = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+        let lock = StatusChange {
+            status: AccountStatus::Locked,
+            reason: "suspicious activity".to_owned(),
+            actor: "admin".to_owned(),
+        };
+        users.set_status(&user_id, lock).await.unwrap();
+
+        let result = validate_token(
+            &token,
+            banned_tokens,
+            users,
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::AccountLocked)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_carries_grants() {
+        let (users, user_id) = user_store().await;
+        let grants = Grants {
+            roles: vec![RoleName::admin()],
+            permissions: vec![
+                Permission::parse("reports:read").unwrap(),
+                Permission::parse("reports:write").unwrap(),
+            ],
+        };
+        let orgs = org_store();
+        let org = Organization::new("Acme".to_owned());
+        orgs.create_org(&org, &user_id).await.unwrap();
+        let org_id = org.id;
+        let token = generate_auth_token(
+            &user_id,
+            &grants,
+            Some(&org_id),
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+        let claims = validate_token(
+            &token,
+            banned_tokens,
+            users,
+            orgs,
+            &TokenSettings::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(claims.org_id, Some(org_id.to_string()));
+        assert_eq!(claims.roles, ["admin"]);
+        assert_eq!(claims.scope, "reports:read reports:write");
+        assert!(claims.has_role(RoleName::ADMIN));
+        assert!(claims.has_permission("reports:write"));
+        assert!(!claims.has_permission("reports"));
+        assert_eq!(claims.amr, ["password"]);
+        assert_eq!(claims.acr, Authentication::SINGLE_FACTOR);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_issued_before_sessions_were_revoked() {
+        let (users, user_id) = user_store().await;
+        let token = generate_auth_token(
+            &user_id,
+            &Grants::default(),
+            None,
+            DEFAULT_TOKEN_AUDIENCE,
+            &password_login(),
+            &TokenSettings::default(),
+        )
+        .unwrap();
+        let banned_tokens = Arc::new(HashsetBannedTokenStore::default());
+        let revoke = UserUpdate {
+            sessions_revoked_at: Some(Utc::now()),
+            ..Default::default()
+        };
+        users.update_user(&user_id, revoke).await.unwrap();
+
+        let result = validate_token(
+            &token,
+            banned_tokens,
+            users,
+            org_store(),
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_personal_access_token_keeps_held_scopes_and_records_use() {
+        let (users, user_id) = user_store().await;
+        let roles = Arc::new(HashmapRoleStore::default());
+        let (read, write) = (
+            Permission::parse("reports:read").unwrap(),
+            Permission::parse("reports:write").unwrap(),
+        );
+        let analyst = RoleName::parse("analyst").unwrap();
+        roles.add_role(&analyst, "").await.unwrap();
+        roles.add_permission(&read, "").await.unwrap();
+        roles.grant_permission(&analyst, &read).await.unwrap();
+        roles.assign_role(&user_id, &analyst).await.unwrap();
+
+        // the owner has since lost `reports:write`
+        let tokens = Arc::new(HashmapPersonalAccessTokenStore::default());
+        let (pat, secret) = PersonalAccessToken::new(
+            user_id,
+            "ci".to_owned(),
+            vec![read, write],
+            chrono::Duration::days(1),
+        );
+        tokens.add_token(&pat).await.unwrap();
+
+        let claims = validate_personal_access_token(
+            secret.as_ref(),
+            tokens.clone(),
+            users,
+            roles,
+            &TokenSettings::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.jti, pat.id.to_string());
+        assert_eq!(claims.scope, "reports:read");
+        assert!(claims.roles.is_empty());
+        assert_eq!(claims.acr, Authentication::NONE);
+
+        let used = tokens.get_token_by_hash(&pat.token_hash).await.unwrap();
+        assert!(used.last_used_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_validate_expired_or_unknown_personal_access_token() {
+        let (users, user_id) = user_store().await;
+        let roles: RoleStoreType = Arc::new(HashmapRoleStore::default());
+        let tokens = Arc::new(HashmapPersonalAccessTokenStore::default());
+        let (mut pat, secret) = PersonalAccessToken::new(
+            user_id,
+            "ci".to_owned(),
+            Vec::new(),
+            chrono::Duration::days(1),
+        );
+        pat.expires_at = Utc::now();
+        tokens.add_token(&pat).await.unwrap();
+
+        let result = validate_personal_access_token(
+            secret.as_ref(),
+            tokens.clone(),
+            users.clone(),
+            roles.clone(),
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+
+        let unknown = PersonalAccessTokenSecret::default();
+        let result = validate_personal_access_token(
+            unknown.as_ref(),
+            tokens,
+            users,
+            roles,
+            &TokenSettings::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
     }
 }
@@ -0,0 +1,288 @@
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{FromRef, FromRequest, Request},
+    http::{header::AUTHORIZATION, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{app_state::AppState, domain::AuthAPIError};
+
+use super::constants::JWT_COOKIE_NAME;
+
+// Where a request may carry its auth token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    // An `Authorization: Bearer` header
+    Bearer,
+    // The cookie set at login
+    Cookie,
+    // A `token` field in a JSON body
+    Body,
+}
+
+impl TokenSource {
+    pub fn parse(source: &str) -> Result<Self> {
+        match source.trim() {
+            "bearer" => Ok(Self::Bearer),
+            "cookie" => Ok(Self::Cookie),
+            "body" => Ok(Self::Body),
+            source => Err(eyre!("Invalid token source: {source}")),
+        }
+    }
+}
+
+// The sources tried, in order, until one has a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSources(Vec<TokenSource>);
+
+impl TokenSources {
+    pub fn new(sources: Vec<TokenSource>) -> Result<Self> {
+        if sources.is_empty() {
+            return Err(eyre!("At least one token source is required"));
+        }
+        if sources
+            .iter()
+            .enumerate()
+            .any(|(i, source)| sources[..i].contains(source))
+        {
+            return Err(eyre!("Token sources must not repeat"));
+        }
+
+        Ok(Self(sources))
+    }
+
+    // Comma-separated, as in the environment variable
+    pub fn parse(sources: &str) -> Result<Self> {
+        Self::new(
+            sources
+                .split(',')
+                .map(TokenSource::parse)
+                .collect::<Result<_>>()?,
+        )
+    }
+}
+
+// Tokens a request names explicitly win over the cookie a browser sends along anyway
+impl Default for TokenSources {
+    fn default() -> Self {
+        Self(vec![
+            TokenSource::Bearer,
+            TokenSource::Body,
+            TokenSource::Cookie,
+        ])
+    }
+}
+
+// The request's auth token, from the first of the app's token sources that has one. Reading
+// the body consumes it, so handlers taking an `AuthToken` can't extract anything else from it
+#[derive(Debug, Clone)]
+pub struct AuthToken(pub Secret<String>);
+
+impl AuthToken {
+    // For middleware, which leaves the body to the handler: the body source is skipped
+    pub fn from_headers(headers: &HeaderMap, sources: &TokenSources) -> Result<Self, AuthAPIError> {
+        Self::from_headers_or_body(headers, None, sources)
+    }
+
+    // For handlers with JSON bodies of their own, which pass on the body's `token` field
+    pub fn from_headers_or_body(
+        headers: &HeaderMap,
+        mut body_token: Option<Secret<String>>,
+        sources: &TokenSources,
+    ) -> Result<Self, AuthAPIError> {
+        sources
+            .0
+            .iter()
+            .find_map(|source| match source {
+                TokenSource::Body => body_token.take(),
+                source => header_token(headers, *source),
+            })
+            .map(Self)
+            .ok_or(AuthAPIError::MissingToken)
+    }
+
+    async fn extract(request: Request, sources: &TokenSources) -> Result<Self, Response> {
+        let (parts, body) = request.into_parts();
+        let mut body = Some(body);
+        // a body without a token is only reported if no other source has one
+        let mut body_rejection = None;
+
+        for source in &sources.0 {
+            if let Some(token) = header_token(&parts.headers, *source) {
+                return Ok(Self(token));
+            }
+            if *source != TokenSource::Body {
+                continue;
+            }
+
+            let Some(body) = body.take() else {
+                continue;
+            };
+            let bytes = Bytes::from_request(Request::new(body), &())
+                .await
+                .map_err(IntoResponse::into_response)?;
+            if bytes.is_empty() {
+                continue;
+            }
+            match Json::<TokenBody>::from_bytes(&bytes) {
+                Ok(Json(body)) => return Ok(Self(body.token.into())),
+                Err(rejection) => body_rejection = Some(rejection.into_response()),
+            }
+        }
+
+        Err(body_rejection.unwrap_or_else(|| AuthAPIError::MissingToken.into_response()))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequest<S> for AuthToken
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Self::extract(request, &AppState::from_ref(state).token_sources).await
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenBody {
+    token: String,
+}
+
+fn header_token(headers: &HeaderMap, source: TokenSource) -> Option<Secret<String>> {
+    match source {
+        TokenSource::Bearer => headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+            .map(|token| Secret::new(token.trim().to_owned())),
+        TokenSource::Cookie => CookieJar::from_headers(headers)
+            .get(JWT_COOKIE_NAME)
+            .map(|cookie| Secret::new(cookie.value().to_owned())),
+        TokenSource::Body => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header::COOKIE, StatusCode},
+    };
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    fn request(bearer: Option<&str>, cookie: Option<&str>, body: &str) -> Request {
+        let mut builder = Request::builder();
+        if let Some(token) = bearer {
+            builder = builder.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        if let Some(token) = cookie {
+            builder = builder.header(COOKIE, format!("{JWT_COOKIE_NAME}={token}"));
+        }
+        builder.body(Body::from(body.to_owned())).unwrap()
+    }
+
+    async fn token(request: Request, sources: &str) -> Result<String, StatusCode> {
+        AuthToken::extract(request, &TokenSources::parse(sources).unwrap())
+            .await
+            .map(|token| token.0.expose_secret().clone())
+            .map_err(|response| response.status())
+    }
+
+    #[tokio::test]
+    async fn sources_are_tried_in_order() {
+        let all = || request(Some("bearer"), Some("cookie"), r#"{"token": "body"}"#);
+
+        assert_eq!(token(all(), "bearer,cookie,body").await.unwrap(), "bearer");
+        assert_eq!(token(all(), "cookie,bearer").await.unwrap(), "cookie");
+        assert_eq!(token(all(), "body,bearer").await.unwrap(), "body");
+        assert_eq!(
+            token(
+                request(None, None, r#"{"token": "body"}"#),
+                "bearer,cookie,body"
+            )
+            .await
+            .unwrap(),
+            "body"
+        );
+        assert_eq!(
+            token(request(Some("bearer"), None, ""), "cookie").await,
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_are_rejected_unless_another_source_has_a_token() {
+        assert_eq!(
+            token(request(None, None, "{}"), "body").await,
+            Err(StatusCode::UNPROCESSABLE_ENTITY)
+        );
+        assert_eq!(
+            token(request(None, Some("cookie"), "{}"), "body,cookie")
+                .await
+                .unwrap(),
+            "cookie"
+        );
+        // no body at all just means there's no token in it
+        assert_eq!(
+            token(request(None, None, ""), "body").await,
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[test]
+    fn sources_parse_from_a_comma_separated_list() {
+        assert_eq!(
+            TokenSources::parse("cookie, bearer").unwrap(),
+            TokenSources(vec![TokenSource::Cookie, TokenSource::Bearer])
+        );
+        assert!(TokenSources::parse("").is_err());
+        assert!(TokenSources::parse("bearer,header").is_err());
+        assert!(TokenSources::parse("cookie,cookie").is_err());
+    }
+
+    #[test]
+    fn middleware_skips_the_body() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, "Bearer bearer".parse().unwrap());
+        let sources = TokenSources::parse("body,bearer").unwrap();
+
+        let token = AuthToken::from_headers(&headers, &sources).unwrap();
+        assert_eq!(token.0.expose_secret(), "bearer");
+        assert!(matches!(
+            AuthToken::from_headers(&HeaderMap::new(), &sources),
+            Err(AuthAPIError::MissingToken)
+        ));
+    }
+
+    #[test]
+    fn handlers_pass_on_the_token_in_their_body() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, "Bearer bearer".parse().unwrap());
+        let body = || Some(Secret::new("body".to_owned()));
+        let token = |headers, body, sources| {
+            AuthToken::from_headers_or_body(headers, body, &TokenSources::parse(sources).unwrap())
+                .map(|token| token.0.expose_secret().clone())
+        };
+
+        assert_eq!(token(&headers, body(), "bearer,body").unwrap(), "bearer");
+        assert_eq!(token(&headers, body(), "body,bearer").unwrap(), "body");
+        assert_eq!(token(&headers, None, "body,bearer").unwrap(), "bearer");
+        assert!(matches!(
+            token(&HeaderMap::new(), body(), "bearer,cookie"),
+            Err(AuthAPIError::MissingToken)
+        ));
+    }
+}
@@ -2,6 +2,7 @@ use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use secrecy::Secret;
 use std::env as std_env;
+use std::str::FromStr;
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
@@ -10,8 +11,59 @@ lazy_static! {
     pub static ref JWT_SECRET: Secret<String> = set_token();
     pub static ref DATABASE_URL: Secret<String> = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
+    pub static ref REDIS_URL: Secret<String> = set_redis_url();
+    pub static ref REDIS_MODE: Option<String> = parse_optional(env::REDIS_MODE_ENV_VAR);
+    pub static ref REDIS_SENTINEL_MASTER: Option<String> =
+        parse_optional(env::REDIS_SENTINEL_MASTER_ENV_VAR);
+    pub static ref REDIS_NAMESPACE: Option<String> = parse_optional(env::REDIS_NAMESPACE_ENV_VAR);
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
     pub static ref RESEND_AUTH_TOKEN: Secret<String> = set_resend_auth_token();
+    pub static ref PASSWORD_MIN_LENGTH: Option<usize> =
+        parse_optional(env::PASSWORD_MIN_LENGTH_ENV_VAR);
+    pub static ref PASSWORD_MAX_LENGTH: Option<usize> =
+        parse_optional(env::PASSWORD_MAX_LENGTH_ENV_VAR);
+    pub static ref PASSWORD_MIN_STRENGTH: Option<u8> =
+        parse_optional(env::PASSWORD_MIN_STRENGTH_ENV_VAR);
+    pub static ref PASSWORD_REJECT_COMMON: Option<bool> =
+        parse_optional(env::PASSWORD_REJECT_COMMON_ENV_VAR);
+    pub static ref PASSWORD_REJECT_EMAIL: Option<bool> =
+        parse_optional(env::PASSWORD_REJECT_EMAIL_ENV_VAR);
+    pub static ref HIBP_DATASET_PATH: Option<String> =
+        parse_optional(env::HIBP_DATASET_PATH_ENV_VAR);
+    pub static ref ARGON2_MEMORY_COST: Option<u32> =
+        parse_optional(env::ARGON2_MEMORY_COST_ENV_VAR);
+    pub static ref ARGON2_TIME_COST: Option<u32> = parse_optional(env::ARGON2_TIME_COST_ENV_VAR);
+    pub static ref ARGON2_PARALLELISM: Option<u32> =
+        parse_optional(env::ARGON2_PARALLELISM_ENV_VAR);
+    pub static ref HASHING_WORKERS: Option<usize> = parse_optional(env::HASHING_WORKERS_ENV_VAR);
+    pub static ref HASHING_QUEUE_DEPTH: Option<usize> =
+        parse_optional(env::HASHING_QUEUE_DEPTH_ENV_VAR);
+    pub static ref TOKEN_STORE_BACKEND: Option<String> =
+        parse_optional(env::TOKEN_STORE_BACKEND_ENV_VAR);
+    pub static ref EMAIL_LOWERCASE_LOCAL_PART: Option<bool> =
+        parse_optional(env::EMAIL_LOWERCASE_LOCAL_PART_ENV_VAR);
+    pub static ref SIGNUP_MODE: Option<String> = parse_optional(env::SIGNUP_MODE_ENV_VAR);
+    pub static ref SIGNUP_INVITATION_TTL_HOURS: Option<i64> =
+        parse_optional(env::SIGNUP_INVITATION_TTL_HOURS_ENV_VAR);
+    pub static ref PASSWORD_RESET_TTL_MINUTES: Option<i64> =
+        parse_optional(env::PASSWORD_RESET_TTL_MINUTES_ENV_VAR);
+    pub static ref EMAIL_ALLOWED_DOMAINS: Option<String> =
+        parse_optional(env::EMAIL_ALLOWED_DOMAINS_ENV_VAR);
+    pub static ref EMAIL_BLOCKED_DOMAINS: Option<String> =
+        parse_optional(env::EMAIL_BLOCKED_DOMAINS_ENV_VAR);
+    pub static ref EMAIL_REJECT_DISPOSABLE: Option<bool> =
+        parse_optional(env::EMAIL_REJECT_DISPOSABLE_ENV_VAR);
+    pub static ref DISPOSABLE_EMAIL_DOMAINS_PATH: Option<String> =
+        parse_optional(env::DISPOSABLE_EMAIL_DOMAINS_PATH_ENV_VAR);
+    pub static ref PERSONAL_ACCESS_TOKEN_MAX_TTL_DAYS: Option<i64> =
+        parse_optional(env::PERSONAL_ACCESS_TOKEN_MAX_TTL_DAYS_ENV_VAR);
+    pub static ref TOKEN_SOURCES: Option<String> = parse_optional(env::TOKEN_SOURCES_ENV_VAR);
+    pub static ref JWT_ISSUER: Option<String> = parse_optional(env::JWT_ISSUER_ENV_VAR);
+    pub static ref JWT_AUDIENCES: Option<String> = parse_optional(env::JWT_AUDIENCES_ENV_VAR);
+    pub static ref JWT_LEEWAY_SECONDS: Option<u64> =
+        parse_optional(env::JWT_LEEWAY_SECONDS_ENV_VAR);
+    pub static ref STEP_UP_MAX_AGE_SECONDS: Option<i64> =
+        parse_optional(env::STEP_UP_MAX_AGE_SECONDS_ENV_VAR);
 }
 
 fn set_token() -> Secret<String> {
@@ -37,6 +89,15 @@ fn set_redis_host() -> String {
     std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
 }
 
+// Falls back to REDIS_HOST_NAME, for deployments that predate REDIS_URL
+fn set_redis_url() -> Secret<String> {
+    dotenv().ok();
+    Secret::new(
+        std_env::var(env::REDIS_URL_ENV_VAR)
+            .unwrap_or_else(|_| format!("redis://{}/", *REDIS_HOST_NAME)),
+    )
+}
+
 fn set_postmark_auth_token() -> Secret<String> {
     dotenv().ok();
     let token =
@@ -56,16 +117,76 @@ fn set_resend_auth_token() -> Secret<String> {
     }
     Secret::new(token)
 }
+fn parse_optional<T: FromStr>(name: &str) -> Option<T> {
+    dotenv().ok();
+    std_env::var(name).ok().map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("{name} has an invalid value: {value}"))
+    })
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    // Comma-separated in sentinel and cluster modes
+    pub const REDIS_URL_ENV_VAR: &str = "REDIS_URL";
+    // "standalone" (default), "sentinel" or "cluster"
+    pub const REDIS_MODE_ENV_VAR: &str = "REDIS_MODE";
+    pub const REDIS_SENTINEL_MASTER_ENV_VAR: &str = "REDIS_SENTINEL_MASTER";
+    pub const REDIS_NAMESPACE_ENV_VAR: &str = "REDIS_NAMESPACE";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
     pub const RESEND_AUTH_TOKEN_ENV_VAR: &str = "RESEND_AUTH_TOKEN";
+    pub const PASSWORD_MIN_LENGTH_ENV_VAR: &str = "PASSWORD_MIN_LENGTH";
+    pub const PASSWORD_MAX_LENGTH_ENV_VAR: &str = "PASSWORD_MAX_LENGTH";
+    pub const PASSWORD_MIN_STRENGTH_ENV_VAR: &str = "PASSWORD_MIN_STRENGTH";
+    pub const PASSWORD_REJECT_COMMON_ENV_VAR: &str = "PASSWORD_REJECT_COMMON";
+    pub const PASSWORD_REJECT_EMAIL_ENV_VAR: &str = "PASSWORD_REJECT_EMAIL";
+    pub const HIBP_DATASET_PATH_ENV_VAR: &str = "HIBP_DATASET_PATH";
+    pub const ARGON2_MEMORY_COST_ENV_VAR: &str = "ARGON2_MEMORY_COST";
+    pub const ARGON2_TIME_COST_ENV_VAR: &str = "ARGON2_TIME_COST";
+    pub const ARGON2_PARALLELISM_ENV_VAR: &str = "ARGON2_PARALLELISM";
+    pub const HASHING_WORKERS_ENV_VAR: &str = "HASHING_WORKERS";
+    pub const HASHING_QUEUE_DEPTH_ENV_VAR: &str = "HASHING_QUEUE_DEPTH";
+    // "redis" (default) or "postgres", for banned tokens and 2FA codes
+    pub const TOKEN_STORE_BACKEND_ENV_VAR: &str = "TOKEN_STORE_BACKEND";
+    // Defaults to true; stored emails are recanonicalized at startup when it changes
+    pub const EMAIL_LOWERCASE_LOCAL_PART_ENV_VAR: &str = "EMAIL_LOWERCASE_LOCAL_PART";
+    // "open" (default) or "invite"
+    pub const SIGNUP_MODE_ENV_VAR: &str = "SIGNUP_MODE";
+    // Defaults to a week
+    pub const SIGNUP_INVITATION_TTL_HOURS_ENV_VAR: &str = "SIGNUP_INVITATION_TTL_HOURS";
+    // Defaults to an hour
+    pub const PASSWORD_RESET_TTL_MINUTES_ENV_VAR: &str = "PASSWORD_RESET_TTL_MINUTES";
+    // Comma-separated domains, where `*` matches any run of characters
+    pub const EMAIL_ALLOWED_DOMAINS_ENV_VAR: &str = "EMAIL_ALLOWED_DOMAINS";
+    pub const EMAIL_BLOCKED_DOMAINS_ENV_VAR: &str = "EMAIL_BLOCKED_DOMAINS";
+    // Defaults to true
+    pub const EMAIL_REJECT_DISPOSABLE_ENV_VAR: &str = "EMAIL_REJECT_DISPOSABLE";
+    // Replaces the bundled list; one domain per line
+    pub const DISPOSABLE_EMAIL_DOMAINS_PATH_ENV_VAR: &str = "DISPOSABLE_EMAIL_DOMAINS_PATH";
+    // Defaults to a year
+    pub const PERSONAL_ACCESS_TOKEN_MAX_TTL_DAYS_ENV_VAR: &str =
+        "PERSONAL_ACCESS_TOKEN_MAX_TTL_DAYS";
+    // Comma-separated, in order of precedence; defaults to "bearer,body,cookie"
+    pub const TOKEN_SOURCES_ENV_VAR: &str = "TOKEN_SOURCES";
+    // Defaults to "auth-service"
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    // Comma-separated; tokens are issued for the first unless another is asked for.
+    // Defaults to "app-service"
+    pub const JWT_AUDIENCES_ENV_VAR: &str = "JWT_AUDIENCES";
+    // Clock skew allowed when checking `exp` and `nbf`; defaults to 60
+    pub const JWT_LEEWAY_SECONDS_ENV_VAR: &str = "JWT_LEEWAY_SECONDS";
+    // How recent an authentication sensitive operations need; defaults to 300
+    pub const STEP_UP_MAX_AGE_SECONDS_ENV_VAR: &str = "STEP_UP_MAX_AGE_SECONDS";
 }
 
 pub mod prod {
+    use std::time::Duration;
+
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+    pub const EXPIRED_ENTRIES_PURGE_INTERVAL: Duration = Duration::from_secs(300);
     pub mod email_client {
         use std::time::Duration;
 
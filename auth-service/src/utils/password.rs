@@ -0,0 +1,44 @@
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Password, PasswordPolicyViolation},
+};
+
+// Shared by every route that sets a new password
+#[tracing::instrument(name = "Validating new password", skip_all)]
+pub async fn validate_new_password(
+    state: &AppState,
+    password: Secret<String>,
+    email: &Email,
+) -> Result<Password, AuthAPIError> {
+    let password = state
+        .password_policy
+        .parse(password, email)
+        .map_err(AuthAPIError::WeakPassword)?;
+
+    if is_breached(state, &password).await {
+        return Err(AuthAPIError::WeakPassword(vec![
+            PasswordPolicyViolation::Breached,
+        ]));
+    }
+
+    Ok(password)
+}
+
+// A failing lookup is logged and treated as "not breached",
+// so an unavailable dataset doesn't lock users out
+#[tracing::instrument(name = "Checking if password is breached", skip_all)]
+pub async fn is_breached(state: &AppState, password: &Password) -> bool {
+    let Some(checker) = &state.breached_password_checker else {
+        return false;
+    };
+
+    match checker.breach_count(password.as_ref()).await {
+        Ok(count) => count > 0,
+        Err(e) => {
+            tracing::error!(error = ?e, "failed to check password against breach dataset");
+            false
+        }
+    }
+}
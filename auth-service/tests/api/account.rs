@@ -0,0 +1,205 @@
+use auth_service::{
+    domain::{AuthMethod, Authentication, Grants},
+    utils::{
+        auth::{generate_auth_cookie, TokenSettings, DEFAULT_TOKEN_AUDIENCE},
+        constants::JWT_COOKIE_NAME,
+    },
+    ErrorResponse,
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use test_helpers::api_test;
+
+use crate::helpers::{TestApp, TEST_PASSWORD};
+
+const NEW_PASSWORD: &str = "Qm4$zR8nW2!t";
+
+// A token for a password login of an hour ago
+async fn stale_token(app: &TestApp, token: &str) -> String {
+    let authentication = Authentication {
+        time: Utc::now() - Duration::hours(1),
+        methods: vec![AuthMethod::Password],
+    };
+
+    generate_auth_cookie(
+        &app.user_id(token).await,
+        &Grants::default(),
+        None,
+        DEFAULT_TOKEN_AUDIENCE,
+        &authentication,
+        &TokenSettings::default(),
+    )
+    .expect("Failed to generate auth cookie")
+    .value()
+    .to_owned()
+}
+
+// Sends a request as the holder of `token` only, without the test app's cookies
+fn as_holder_of(
+    app: &TestApp,
+    method: reqwest::Method,
+    path: &str,
+    token: &str,
+) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .request(method, format!("{}{}", app.address, path))
+        .bearer_auth(token)
+}
+
+#[api_test]
+async fn should_record_how_the_user_authenticated() {
+    let token = app.sign_up_and_log_in().await;
+    let claims = app.claims(&token).await;
+
+    assert_eq!(claims.amr, ["password"]);
+    assert_eq!(claims.acr, Authentication::SINGLE_FACTOR);
+    assert!(claims.auth_time >= claims.iat - 1);
+}
+
+#[api_test]
+async fn should_require_a_recent_authentication_to_disable_2fa() {
+    let token = app.sign_up_and_log_in().await;
+    let stale = stale_token(&app, &token).await;
+
+    let response = as_holder_of(&app, reqwest::Method::DELETE, "/account/2fa", &stale)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Reauthentication required"
+    );
+
+    let response = as_holder_of(&app, reqwest::Method::DELETE, "/account/2fa", &token)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 204);
+}
+
+#[api_test]
+async fn should_upgrade_the_session_once_reauthenticated() {
+    let token = app.sign_up_and_log_in().await;
+    let stale = stale_token(&app, &token).await;
+
+    let response = as_holder_of(
+        &app,
+        reqwest::Method::POST,
+        "/account/reauthenticate",
+        &stale,
+    )
+    .json(&json!({ "password": "wrong-password-123" }))
+    .send()
+    .await
+    .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = as_holder_of(
+        &app,
+        reqwest::Method::POST,
+        "/account/reauthenticate",
+        &stale,
+    )
+    .json(&json!({ "password": TEST_PASSWORD }))
+    .send()
+    .await
+    .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+    let upgraded = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let claims = app.claims(&upgraded).await;
+    assert!(claims.auth_time > Utc::now().timestamp() as usize - 60);
+    assert_eq!(claims.amr, ["password"]);
+
+    // the token it replaces is banned
+    let response = app.post_verify_token(&json!({ "token": stale })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = as_holder_of(&app, reqwest::Method::DELETE, "/account/2fa", &upgraded)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 204);
+}
+
+#[api_test]
+async fn should_change_the_password_of_a_recently_authenticated_caller() {
+    let email = app.sign_up().await;
+    let token = app.log_in(&email).await;
+    let stale = stale_token(&app, &token).await;
+    let body = json!({ "newPassword": NEW_PASSWORD });
+
+    let response = as_holder_of(&app, reqwest::Method::POST, "/account/password", &stale)
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post("/account/password", &json!({ "newPassword": "short" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post("/account/password", &body).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // the session that changed it stays valid
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_delete_the_account_of_a_recently_authenticated_caller() {
+    let email = app.sign_up().await;
+    let token = app.log_in(&email).await;
+    let stale = stale_token(&app, &token).await;
+
+    let response = as_holder_of(&app, reqwest::Method::DELETE, "/account", &stale)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.delete("/account").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_not_delete_the_last_owner_of_an_organization() {
+    app.sign_up_and_log_in().await;
+    let response = app.post("/orgs", &json!({ "name": "Acme" })).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.delete("/account").await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let response = app.get("/orgs").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,249 @@
+use auth_service::{
+    domain::{RoleName, UserId},
+    routes::{AdminUserResponse, UserListResponse},
+    ErrorResponse,
+};
+use serde_json::json;
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+async fn get_user(app: &TestApp, user_id: &UserId) -> AdminUserResponse {
+    let response = app.get_admin(&format!("/users/{user_id}")).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to AdminUserResponse")
+}
+
+#[api_test]
+async fn should_return_400_if_token_missing() {
+    let response = app.get_admin("/users").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_message(response).await, "Missing auth token");
+}
+
+#[api_test]
+async fn should_return_403_if_not_admin() {
+    app.sign_up_and_log_in().await;
+
+    let response = app.get_admin("/users").await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Forbidden");
+}
+
+#[api_test]
+async fn should_check_the_admin_role_on_every_request() {
+    let token = app.sign_up_and_log_in().await;
+    let user_id = app.user_id(&token).await;
+
+    // honoured without logging in again, though the token doesn't carry the role
+    app.role_store
+        .assign_role(&user_id, &RoleName::admin())
+        .await
+        .expect("Failed to promote user to admin");
+    let response = app.get_admin("/users").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.role_store
+        .unassign_role(&user_id, &RoleName::admin())
+        .await
+        .expect("Failed to demote admin");
+    let response = app.get_admin("/users").await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_list_and_search_users() {
+    let user_token = app.sign_up_and_log_in().await;
+    let user_id = app.user_id(&user_token).await;
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app.get_admin("/users?page=1&perPage=1").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page: UserListResponse = response.json().await.expect("Invalid user list");
+    assert_eq!(page.total, 2);
+    assert_eq!(page.per_page, 1);
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].id, user_id.to_string());
+
+    let user = get_user(&app, &user_id).await;
+    let local_part = user.email.split('@').next().unwrap().to_uppercase();
+    let response = app.get_admin(&format!("/users?email={local_part}")).await;
+    let page: UserListResponse = response.json().await.expect("Invalid user list");
+    assert_eq!(page.total, 1);
+    assert_eq!(page.users[0], user);
+    assert_eq!(user.status, "active");
+}
+
+#[api_test]
+async fn should_return_404_if_user_not_found() {
+    app.sign_up_admin_and_log_in().await;
+
+    for path in [
+        format!("/users/{}", UserId::default()),
+        "/users/not-an-id".to_owned(),
+    ] {
+        let response = app.get_admin(&path).await;
+
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(error_message(response).await, "User not found");
+    }
+}
+
+#[api_test]
+async fn should_disable_and_enable_user() {
+    let user_token = app.sign_up_and_log_in().await;
+    let user_id = app.user_id(&user_token).await;
+    let admin_token = app.sign_up_admin_and_log_in().await;
+    let admin_id = app.user_id(&admin_token).await;
+
+    let response = app
+        .post_admin(
+            &format!("/users/{user_id}/disable"),
+            &json!({ "reason": "terms of service violation" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": user_token })).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Account disabled");
+
+    let response = app
+        .post_admin(
+            &format!("/users/{user_id}/enable"),
+            &json!({ "reason": "appeal accepted" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": user_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let history = app
+        .user_store
+        .get_status_history(&user_id)
+        .await
+        .expect("Failed to get status history");
+    assert_eq!(history.len(), 2);
+    assert!(history
+        .iter()
+        .all(|transition| transition.actor == admin_id.to_string()));
+}
+
+#[api_test]
+async fn should_revoke_all_sessions() {
+    let user_token = app.sign_up_and_log_in().await;
+    let user_id = app.user_id(&user_token).await;
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app
+        .post_admin(&format!("/users/{user_id}/revoke-sessions"), &json!({}))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": user_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(error_message(response).await, "Invalid auth token");
+}
+
+#[api_test]
+async fn should_force_password_reset() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let user_token = app.sign_up_and_log_in().await;
+    let user_id = app.user_id(&user_token).await;
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app
+        .post_admin(
+            &format!("/users/{user_id}/force-password-reset"),
+            &json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let user = get_user(&app, &user_id).await;
+    assert!(user.password_reset_required);
+
+    let response = app.post_verify_token(&json!({ "token": user_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({
+            "email": user.email,
+            "password": "x7Kp#2vLq9!w",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Password reset required");
+
+    let response = app
+        .post(
+            "/password-reset/confirm",
+            &json!({
+                "email": user.email,
+                "token": app.sent_token().await,
+                "newPassword": "Qm4$zR8nW2!t",
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(!get_user(&app, &user_id).await.password_reset_required);
+
+    let response = app
+        .post_login(&json!({
+            "email": user.email,
+            "password": "Qm4$zR8nW2!t",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_reset_2fa() {
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "x7Kp#2vLq9!w",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app.get_admin(&format!("/users?email={email}")).await;
+    let page: UserListResponse = response.json().await.expect("Invalid user list");
+    let user = &page.users[0];
+    assert!(user.requires_2fa);
+
+    let response = app
+        .post_admin(&format!("/users/{}/reset-2fa", user.id), &json!({}))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let user_id = UserId::parse(&user.id).expect("Invalid user id");
+    assert!(!get_user(&app, &user_id).await.requires_2fa);
+}
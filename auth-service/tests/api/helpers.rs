@@ -1,10 +1,10 @@
 use std::{str::FromStr, sync::Arc};
 
 use auth_service::{
-    Application, app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType}, domain::Email, get_postgres_pool, get_redis_client, services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore}, postmark_email_client::PostmarkEmailClient}, utils::{
-        auth::generate_auth_cookie,
-        constants::{DATABASE_URL, DEFAULT_REDIS_HOSTNAME, test},
+    Application, app_state::{AppState, BannedTokenStoreType, OrgStoreType, RoleStoreType, TwoFACodeStoreType, UserStoreType}, domain::{AuthMethod, Authentication, Email, Grants, RoleName, SignupMode, UserId}, get_postgres_pool, get_redis_connection, services::{
+        data_stores::{PostgresOrgStore, PostgresPersonalAccessTokenStore, PostgresRoleStore, PostgresSignupInvitationStore, PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore}, hibp_breached_password_checker::HibpBreachedPasswordChecker, postmark_email_client::PostmarkEmailClient, redis_connection::{RedisConfig, RedisConnection, RedisTopology}}, utils::{
+        auth::{generate_auth_cookie, validate_token, Claims, TokenSettings, DEFAULT_TOKEN_AUDIENCE},
+        constants::{DATABASE_URL, DEFAULT_REDIS_HOSTNAME, JWT_COOKIE_NAME, test},
     }
 };
 use reqwest::{Client, cookie::Jar};
@@ -14,7 +14,6 @@ use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     Connection, Executor, PgConnection, PgPool,
 };
-use tokio::sync::RwLock;
 use uuid::Uuid;
 use wiremock::MockServer;
 
@@ -23,8 +22,12 @@ pub struct TestApp {
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
     pub email_server: MockServer,
+    pub user_store: UserStoreType,
+    pub role_store: RoleStoreType,
+    pub org_store: OrgStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub pg_pool: PgPool,
 
     pub db_name: String,
     // TODO: cleanup after every test via proc macro
@@ -32,18 +35,27 @@ pub struct TestApp {
 }
 
 const FAILED_TO_EXECUTE_REQUEST: &str = "Failed to execute request";
+pub const TEST_PASSWORD: &str = "x7Kp#2vLq9!w";
+// Range files in the HIBP format; contains "Tr0ub4dor&3"
+const PWNED_PASSWORDS_FIXTURE: &str = "tests/fixtures/pwned_passwords";
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_signup_mode(SignupMode::Open).await
+    }
+
+    pub async fn with_signup_mode(signup_mode: SignupMode) -> Self {
         let db_name = Uuid::new_v4().to_string();
         let pg_pool = configure_postgresql(&db_name).await;
-        let redis_connection = Arc::new(RwLock::new(configure_redis()));
+        let redis_connection = configure_redis().await;
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
-            redis_connection.clone(),
-        )));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+        let user_store = Arc::new(PostgresUserStore::new(pg_pool.clone()));
+        let role_store = Arc::new(PostgresRoleStore::new(pg_pool.clone()));
+        let org_store = Arc::new(PostgresOrgStore::new(pg_pool.clone()));
+        let signup_invitation_store = Arc::new(PostgresSignupInvitationStore::new(pg_pool.clone()));
+        let personal_access_token_store = Arc::new(PostgresPersonalAccessTokenStore::new(pg_pool.clone()));
+        let banned_token_store = Arc::new(RedisBannedTokenStore::new(redis_connection.clone()));
+        let two_fa_code_store = Arc::new(RedisTwoFACodeStore::new(redis_connection));
 
         // Set up a mock email server
         let email_server = MockServer::start().await; // New!
@@ -52,10 +64,18 @@ impl TestApp {
 
         let app_state = AppState::new(
             user_store.clone(),
+            role_store.clone(),
+            org_store.clone(),
+            signup_invitation_store,
+            personal_access_token_store,
             banned_token_store.clone(),
             two_fa_code_store.clone(),
             email_client,
-        );
+        )
+        .with_breached_password_checker(Arc::new(HibpBreachedPasswordChecker::new(
+            PWNED_PASSWORDS_FIXTURE,
+        )))
+        .with_signup_mode(signup_mode);
 
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -76,8 +96,12 @@ impl TestApp {
             cookie_jar,
             http_client,
             email_server,
+            user_store,
+            role_store,
+            org_store,
             banned_token_store,
             two_fa_code_store,
+            pg_pool,
 
             db_name,
             clean_up_called: false,
@@ -128,6 +152,91 @@ impl TestApp {
             .expect(FAILED_TO_EXECUTE_REQUEST)
     }
 
+    // Signs up a user without 2FA and logs them in; returns their auth token, which the
+    // cookie jar also holds from then on
+    pub async fn sign_up_and_log_in(&self) -> String {
+        let email = self.sign_up().await;
+        self.log_in(&email).await
+    }
+
+    // Same as `sign_up_and_log_in`, for a user promoted to admin
+    pub async fn sign_up_admin_and_log_in(&self) -> String {
+        let email = self.sign_up().await;
+        let token = self.log_in(&email).await;
+
+        self.role_store
+            .assign_role(&self.user_id(&token).await, &RoleName::admin())
+            .await
+            .expect("Failed to promote user to admin");
+
+        self.log_in(&email).await
+    }
+
+    pub async fn user_id(&self, token: &str) -> UserId {
+        let claims = self.claims(token).await;
+        UserId::parse(&claims.sub).expect("Invalid user id")
+    }
+
+    pub async fn claims(&self, token: &str) -> Claims {
+        validate_token(
+            &token.to_owned().into(),
+            self.banned_token_store.clone(),
+            self.user_store.clone(),
+            self.org_store.clone(),
+            &TokenSettings::default(),
+        )
+        .await
+        .expect("Failed to validate token")
+    }
+
+    // Signs up a user without 2FA, without logging them in; returns their email
+    pub async fn sign_up(&self) -> String {
+        let email = get_random_email();
+
+        let response = self
+            .post_signup(&serde_json::json!({
+                "email": email,
+                "password": TEST_PASSWORD,
+                "requires2FA": false
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        email
+    }
+
+    // Logs in a user created by `sign_up_and_log_in`, e.g. to get a token with fresh grants
+    pub async fn log_in(&self, email: &str) -> String {
+        self.log_in_with(&serde_json::json!({
+            "email": email,
+            "password": TEST_PASSWORD,
+        }))
+        .await
+    }
+
+    // Same as `log_in`, with the token scoped to one of the user's organizations
+    pub async fn log_in_to_org(&self, email: &str, org_id: &str) -> String {
+        self.log_in_with(&serde_json::json!({
+            "email": email,
+            "password": TEST_PASSWORD,
+            "orgId": org_id,
+        }))
+        .await
+    }
+
+    async fn log_in_with(&self, body: &serde_json::Value) -> String {
+        let response = self.post_login(body).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let token = response
+            .cookies()
+            .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+            .expect("No auth cookie found")
+            .value()
+            .to_owned();
+        token
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
             .post(format!("{}/logout", &self.address))
@@ -136,6 +245,84 @@ impl TestApp {
             .expect(FAILED_TO_EXECUTE_REQUEST)
     }
 
+    pub async fn get_admin(&self, path: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin{}", &self.address, path))
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
+    pub async fn post_admin<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin{}", &self.address, path))
+            .json(body)
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
+    pub async fn delete_admin(&self, path: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin{}", &self.address, path))
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
+    // For the routes acting on behalf of the logged-in user, e.g. under `/orgs`
+    pub async fn get(&self, path: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}{}", &self.address, path))
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
+    pub async fn post<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.http_client
+            .post(format!("{}{}", &self.address, path))
+            .json(body)
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
+    pub async fn delete(&self, path: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}{}", &self.address, path))
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
+    // The token from the most recent email, e.g. a signup invitation or a password reset
+    pub async fn sent_token(&self) -> String {
+        let requests = self
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled");
+        let email: serde_json::Value = requests
+            .last()
+            .expect("No email was sent")
+            .body_json()
+            .expect("Invalid email request");
+
+        email["TextBody"]
+            .as_str()
+            .and_then(|body| body.split("token is ").nth(1))
+            .and_then(|rest| rest.split(';').next())
+            .expect("No token in the email")
+            .to_owned()
+    }
+
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -148,6 +335,28 @@ impl TestApp {
             .expect(FAILED_TO_EXECUTE_REQUEST)
     }
 
+    // Sends `token` in an `Authorization: Bearer` header instead of a body
+    pub async fn post_bearer(&self, path: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}{}", &self.address, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
+    pub async fn post_verify_token_permission<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-token/permission", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect(FAILED_TO_EXECUTE_REQUEST)
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
@@ -186,11 +395,18 @@ pub fn get_random_email() -> String {
 }
 
 #[allow(dead_code)]
-pub fn get_valid_auth_token(email: &Email) -> String {
-    generate_auth_cookie(email)
-        .expect("Failed to generate auth cookie")
-        .value()
-        .to_string()
+pub fn get_valid_auth_token(user_id: &UserId) -> String {
+    generate_auth_cookie(
+        user_id,
+        &Grants::default(),
+        None,
+        DEFAULT_TOKEN_AUDIENCE,
+        &Authentication::now(vec![AuthMethod::Password]),
+        &TokenSettings::default(),
+    )
+    .expect("Failed to generate auth cookie")
+    .value()
+    .to_string()
 }
 
 async fn configure_database(db_conn_string: &Secret<String>, db_name: &str) {
@@ -255,12 +471,14 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
-    let redis_hostname = DEFAULT_REDIS_HOSTNAME.to_owned();
+async fn configure_redis() -> RedisConnection {
+    let redis_url = format!("redis://{}/", DEFAULT_REDIS_HOSTNAME);
+    let config = RedisConfig::new(RedisTopology::Standalone {
+        url: Secret::new(redis_url),
+    });
 
-    get_redis_client(redis_hostname)
-        .expect("Failed to get Redis client")
-        .get_connection()
+    get_redis_connection(&config)
+        .await
         .expect("Failed to get Redis connection")
 }
 
@@ -1,7 +1,11 @@
 use auth_service::{
-    domain::Email, routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse,
+    domain::{Email, Password, UserStore},
+    routes::TwoFactorAuthResponse,
+    services::data_stores::PostgresUserStore,
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
 };
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, Secret};
 use serde_json::json;
 use test_helpers::api_test;
 use wiremock::{
@@ -9,7 +13,7 @@ use wiremock::{
     Mock, ResponseTemplate,
 };
 
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
 
 #[api_test]
 async fn should_return_422_if_malformed_credentials() {
@@ -20,7 +24,7 @@ async fn should_return_422_if_malformed_credentials() {
             "email": random_email,
         }),
         json!({
-           "password": "password123",
+           "password": "x7Kp#2vLq9!w",
         }),
     ];
 
@@ -40,19 +44,19 @@ async fn should_return_400_if_invalid_input() {
     let test_cases = [
         json!({
             "email": "",
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
         }),
         json!({
             "email": "malformed",
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
         }),
         json!({
             "email": "@example.com",
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
         }),
         json!({
             "email": "me@",
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
         }),
         json!({
             "email": get_random_email(),
@@ -89,7 +93,7 @@ async fn should_return_401_if_incorrect_credentials() {
     let response = app
         .post_login(&json!({
             "email": "me@example.com",
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
         }))
         .await;
 
@@ -99,7 +103,7 @@ async fn should_return_401_if_incorrect_credentials() {
         "Failed for input: {:?}",
         json!({
             "email": "me@example.com",
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
         })
     );
     assert_eq!(
@@ -112,13 +116,36 @@ async fn should_return_401_if_incorrect_credentials() {
     );
 }
 
+#[api_test]
+async fn should_return_400_if_audience_is_not_configured() {
+    let email = app.sign_up().await;
+
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": TEST_PASSWORD,
+            "audience": "billing",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Unknown token audience".to_owned()
+    );
+}
+
 #[api_test]
 async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     let random_email = get_random_email();
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
         "requires2FA": false
     });
 
@@ -128,7 +155,7 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
     });
 
     let response = app.post_login(&login_body).await;
@@ -149,7 +176,7 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
         "requires2FA": true
     });
 
@@ -167,7 +194,7 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
     });
 
     let response = app.post_login(&login_body).await;
@@ -182,8 +209,6 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     assert_eq!(json_body.message, "2FA required".to_owned());
     assert_eq!(
         app.two_fa_code_store
-            .read()
-            .await
             .get_code(&Email::parse(random_email.into()).expect("Must be valid email"))
             .await
             .expect("Login attempt ID must be present in store")
@@ -193,3 +218,31 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
         &json_body.login_attempt_id
     );
 }
+
+#[api_test]
+async fn should_keep_a_password_changed_while_rehashing() {
+    let store = PostgresUserStore::new(app.pg_pool.clone());
+    let email = app.sign_up().await;
+    let user_id = app.user_id(&app.log_in(&email).await).await;
+    let email = Email::parse(email.into()).unwrap();
+    let verified_hash: String =
+        sqlx::query_scalar("SELECT password_hash FROM users WHERE canonical_email = $1")
+            .bind(email.canonical().expose_secret())
+            .fetch_one(&app.pg_pool)
+            .await
+            .unwrap();
+
+    let old_password = Password::parse(TEST_PASSWORD.to_owned().into()).unwrap();
+    let new_password = Password::parse("Qm4$zR8nW2!t".to_owned().into()).unwrap();
+    store
+        .set_password(&user_id, new_password.clone())
+        .await
+        .unwrap();
+    store
+        .rehash_password(&email, &Secret::new(verified_hash), &old_password)
+        .await
+        .unwrap();
+
+    assert!(store.validate_user(&email, &new_password).await.is_ok());
+    assert!(store.validate_user(&email, &old_password).await.is_err());
+}
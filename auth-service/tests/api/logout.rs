@@ -1,7 +1,10 @@
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::TestApp;
 use auth_service::{
-    domain::Email,
-    utils::{auth::generate_auth_cookie, constants::JWT_COOKIE_NAME},
+    domain::{AccountStatus, StatusChange},
+    utils::{
+        auth::{validate_token, TokenSettings},
+        constants::JWT_COOKIE_NAME,
+    },
     ErrorResponse,
 };
 use reqwest::Url;
@@ -9,20 +12,21 @@ use test_helpers::api_test;
 
 #[api_test]
 async fn should_return_200_if_valid_jwt_cookie() {
-    let random_email = get_random_email();
-    let auth_cookie = generate_auth_cookie(&Email::parse(random_email.into()).expect("Invalid email"))
-        .expect("Failed to generate auth cookie");
-    let token = auth_cookie.value().to_owned().into();
-    app.cookie_jar.add_cookie_str(
-        &auth_cookie.to_string(),
-        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
-    );
+    let token = app.sign_up_and_log_in().await;
+    let jti = validate_token(
+        &token.into(),
+        app.banned_token_store.clone(),
+        app.user_store.clone(),
+        app.org_store.clone(),
+        &TokenSettings::default(),
+    )
+    .await
+    .expect("Failed to validate token")
+    .jti;
 
     assert!(
         !app.banned_token_store
-            .read()
-            .await
-            .contains_token(&token)
+            .contains_token(&jti)
             .await
             .expect("Failed to check token existence")
     );
@@ -32,9 +36,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
     assert_eq!(response.status().as_u16(), 200);
     assert!(
         app.banned_token_store
-            .read()
-            .await
-            .contains_token(&token)
+            .contains_token(&jti)
             .await
             .expect("Failed to check token existence")
     );
@@ -48,14 +50,17 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
 #[api_test]
 async fn should_return_400_if_logout_called_twice_in_a_row() {
-    let random_email = get_random_email();
-    let auth_cookie = generate_auth_cookie(&Email::parse(random_email.into()).expect("Invalid email"))
-        .expect("Failed to generate auth cookie");
-    let token = auth_cookie.value().to_owned().into();
-    app.cookie_jar.add_cookie_str(
-        &auth_cookie.to_string(),
-        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
-    );
+    let token = app.sign_up_and_log_in().await;
+    let jti = validate_token(
+        &token.into(),
+        app.banned_token_store.clone(),
+        app.user_store.clone(),
+        app.org_store.clone(),
+        &TokenSettings::default(),
+    )
+    .await
+    .expect("Failed to validate token")
+    .jti;
 
     let response = app.post_logout().await;
 
@@ -63,9 +68,7 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     assert!(
         app.banned_token_store
-            .read()
-            .await
-            .contains_token(&token)
+            .contains_token(&jti)
             .await
             .expect("Failed to check token existence")
     );
@@ -131,3 +134,46 @@ async fn should_return_401_if_invalid_token() {
         "Invalid auth token".to_owned()
     );
 }
+
+#[api_test]
+async fn should_accept_a_bearer_token_instead_of_a_cookie() {
+    let token = app.sign_up_and_log_in().await;
+
+    // a client without the login cookie
+    let response = reqwest::Client::new()
+        .post(format!("{}/logout", app.address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_log_out_users_who_can_no_longer_use_their_token() {
+    let token = app.sign_up_and_log_in().await;
+    app.user_store
+        .set_status(
+            &app.user_id(&token).await,
+            StatusChange {
+                status: AccountStatus::Locked,
+                reason: "suspicious activity".to_owned(),
+                actor: "test".to_owned(),
+            },
+        )
+        .await
+        .expect("Failed to lock account");
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+}
@@ -6,3 +6,10 @@ mod login;
 mod logout;
 mod verify_2fa;
 mod verify_token;
+mod admin;
+mod rbac;
+mod orgs;
+mod signup_invitations;
+mod personal_access_tokens;
+mod account;
+mod password_reset;
@@ -0,0 +1,238 @@
+use auth_service::{
+    domain::{OrgId, OrgRole},
+    routes::{InvitationResponse, MemberResponse, OrgResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use serde_json::json;
+use test_helpers::api_test;
+
+use crate::helpers::{TestApp, TEST_PASSWORD};
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+// Creates an organization owned by the logged-in user
+async fn create_org(app: &TestApp, name: &str) -> OrgResponse {
+    let response = app.post("/orgs", &json!({ "name": name })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    response.json().await.expect("Invalid organization")
+}
+
+#[api_test]
+async fn should_create_and_list_organizations() {
+    app.sign_up_and_log_in().await;
+
+    let acme = create_org(&app, "Acme").await;
+    assert_eq!(acme.role, "owner");
+    let globex = create_org(&app, "  Globex ").await;
+    assert_eq!(globex.name, "Globex");
+
+    let response = app.get("/orgs").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let orgs: Vec<OrgResponse> = response.json().await.expect("Invalid organization list");
+    assert_eq!(orgs, [acme, globex]);
+
+    let response = app.post("/orgs", &json!({ "name": " " })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        error_message(response).await,
+        "Invalid organization name or role"
+    );
+}
+
+#[api_test]
+async fn should_require_auth_for_organization_routes() {
+    let response = app.get("/orgs").await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_accept_a_bearer_token_for_organization_routes() {
+    let token = app.sign_up_and_log_in().await;
+    let acme = create_org(&app, "Acme").await;
+
+    // a client without the login cookie
+    let response = reqwest::Client::new()
+        .get(format!("{}/orgs", app.address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+    let orgs: Vec<OrgResponse> = response.json().await.expect("Invalid organization list");
+    assert_eq!(orgs, [acme]);
+}
+
+#[api_test]
+async fn should_add_invited_user_as_member_once_accepted() {
+    let invitee = app.sign_up().await;
+    app.sign_up_and_log_in().await;
+    let org = create_org(&app, "Acme").await;
+
+    let path = format!("/orgs/{}/invitations", org.id);
+    let response = app
+        .post(&path, &json!({ "email": invitee.to_uppercase() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let invitation: InvitationResponse = response.json().await.expect("Invalid invitation");
+    assert_eq!(invitation.role, "member");
+
+    let response = app.post(&path, &json!({ "email": invitee })).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let invitee_token = app.log_in(&invitee).await;
+    let response = app.get("/invitations").await;
+    let invitations: Vec<InvitationResponse> =
+        response.json().await.expect("Invalid invitation list");
+    assert_eq!(invitations, [invitation]);
+    let invitation = &invitations[0];
+
+    // invitations are only visible to the organization's managers
+    let response = app.get(&path).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post(
+            &format!("/invitations/{}/accept", invitation.id),
+            &json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let joined: OrgResponse = response.json().await.expect("Invalid organization");
+    assert_eq!(
+        (joined.id.as_str(), joined.role.as_str()),
+        (org.id.as_str(), "member")
+    );
+
+    let response = app.get(&format!("/orgs/{}/members", org.id)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let members: Vec<MemberResponse> = response.json().await.expect("Invalid member list");
+    assert_eq!(members.len(), 2);
+    let invitee_id = app.user_id(&invitee_token).await.to_string();
+    assert!(members
+        .iter()
+        .any(|member| member.user_id == invitee_id && member.role == "member"));
+
+    let response = app
+        .post(
+            &format!("/invitations/{}/accept", invitation.id),
+            &json!({}),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[api_test]
+async fn should_scope_tokens_to_an_organization() {
+    let email = app.sign_up().await;
+    let token = app.log_in(&email).await;
+    assert_eq!(app.claims(&token).await.org_id, None);
+
+    let acme = create_org(&app, "Acme").await;
+    let globex = create_org(&app, "Globex").await;
+
+    let token = app.log_in_to_org(&email, &acme.id).await;
+    assert_eq!(app.claims(&token).await.org_id, Some(acme.id));
+
+    let response = app
+        .post("/orgs/switch", &json!({ "orgId": globex.id }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let switched = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    assert_eq!(app.claims(&switched).await.org_id, Some(globex.id));
+
+    // the token it replaces can't be used anymore
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_reject_organization_tokens_of_removed_members() {
+    let owner = app.sign_up().await;
+    app.log_in(&owner).await;
+    let org = create_org(&app, "Acme").await;
+    let member = app.sign_up().await;
+    let member_id = app.user_id(&app.log_in(&member).await).await;
+    app.org_store
+        .add_member(
+            &OrgId::parse(&org.id).expect("Invalid organization id"),
+            &member_id,
+            OrgRole::Member,
+        )
+        .await
+        .expect("Failed to add member");
+
+    let token = app.log_in_to_org(&member, &org.id).await;
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.log_in(&owner).await;
+    let response = app
+        .delete(&format!("/orgs/{}/members/{member_id}", org.id))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_keep_non_members_out_of_an_organization() {
+    let outsider = app.sign_up().await;
+    app.sign_up_and_log_in().await;
+    let org = create_org(&app, "Acme").await;
+
+    app.log_in(&outsider).await;
+    for path in [
+        format!("/orgs/{}/members", org.id),
+        format!("/orgs/{}/invitations", org.id),
+    ] {
+        let response = app.get(&path).await;
+        assert_eq!(response.status().as_u16(), 403);
+        assert_eq!(
+            error_message(response).await,
+            "Not a member of the organization"
+        );
+    }
+
+    let response = app.post("/orgs/switch", &json!({ "orgId": org.id })).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_login(&json!({
+            "email": outsider,
+            "password": TEST_PASSWORD,
+            "orgId": org.id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_not_let_the_last_owner_leave() {
+    let token = app.sign_up_and_log_in().await;
+    let owner_id = app.user_id(&token).await;
+    let org = create_org(&app, "Acme").await;
+
+    let path = format!("/orgs/{}/members/{owner_id}", org.id);
+    let response = app.delete(&path).await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        error_message(response).await,
+        "The organization needs at least one owner"
+    );
+
+    let response = app.post(&path, &json!({ "role": "admin" })).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
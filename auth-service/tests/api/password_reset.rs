@@ -0,0 +1,116 @@
+use auth_service::ErrorResponse;
+use serde_json::json;
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
+
+const NEW_PASSWORD: &str = "Qm4$zR8nW2!t";
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[api_test]
+async fn should_reset_password_once_with_the_emailed_token() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let email = app.sign_up().await;
+
+    let response = app
+        .post("/password-reset", &json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let body = json!({
+        "email": email,
+        "token": app.sent_token().await,
+        "newPassword": NEW_PASSWORD,
+    });
+    let response = app.post("/password-reset/confirm", &body).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post("/password-reset/confirm", &body).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        error_message(response).await,
+        "Invalid or expired password reset token"
+    );
+}
+
+#[api_test]
+async fn should_not_reveal_unknown_emails() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post("/password-reset", &json!({ "email": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+}
+
+#[api_test]
+async fn should_reject_unknown_tokens_and_weak_passwords() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let email = app.sign_up().await;
+    app.post("/password-reset", &json!({ "email": email }))
+        .await;
+
+    for token in ["not-a-token", "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"] {
+        let response = app
+            .post(
+                "/password-reset/confirm",
+                &json!({ "email": email, "token": token, "newPassword": NEW_PASSWORD }),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(
+            error_message(response).await,
+            "Invalid or expired password reset token"
+        );
+    }
+
+    let response = app
+        .post(
+            "/password-reset/confirm",
+            &json!({
+                "email": email,
+                "token": app.sent_token().await,
+                "newPassword": "short",
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        error_message(response).await,
+        "Password does not meet the password policy"
+    );
+}
@@ -0,0 +1,150 @@
+use auth_service::{
+    domain::{Permission, RoleName},
+    routes::{
+        CreatePersonalAccessTokenResponse, IntrospectionResponse, PersonalAccessTokenResponse,
+    },
+};
+use serde_json::json;
+use test_helpers::api_test;
+
+use crate::helpers::TestApp;
+
+// Gives the user behind `token` the `reports:read` permission
+async fn grant_reports_read(app: &TestApp, token: &str) {
+    let role = RoleName::parse("analyst").unwrap();
+    let permission = Permission::parse("reports:read").unwrap();
+    app.role_store.add_role(&role, "").await.unwrap();
+    app.role_store
+        .add_permission(&permission, "")
+        .await
+        .unwrap();
+    app.role_store
+        .grant_permission(&role, &permission)
+        .await
+        .unwrap();
+    app.role_store
+        .assign_role(&app.user_id(token).await, &role)
+        .await
+        .unwrap();
+}
+
+#[api_test]
+async fn should_require_a_logged_in_user() {
+    let response = app
+        .post("/personal-access-tokens", &json!({ "name": "ci" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_create_use_and_revoke_personal_access_tokens() {
+    app.sign_up_and_log_in().await;
+
+    let response = app
+        .post(
+            "/personal-access-tokens",
+            &json!({ "name": "ci", "expiresInDays": 7 }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let created: CreatePersonalAccessTokenResponse = response
+        .json()
+        .await
+        .expect("Invalid personal access token");
+    assert!(created.token.starts_with("pat_"));
+    assert_eq!(created.details.last_used_at, None);
+
+    let response = app.post_bearer("/verify-token", &created.token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get("/personal-access-tokens").await;
+    let tokens: Vec<PersonalAccessTokenResponse> =
+        response.json().await.expect("Invalid token list");
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].id, created.details.id);
+    assert!(tokens[0].last_used_at.is_some());
+
+    let path = format!("/personal-access-tokens/{}", created.details.id);
+    let response = app.delete(&path).await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.delete(&path).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.post_bearer("/verify-token", &created.token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_reject_invalid_names_expiries_and_scopes() {
+    let token = app.sign_up_and_log_in().await;
+    grant_reports_read(&app, &token).await;
+
+    for body in [
+        json!({ "name": " " }),
+        json!({ "name": "ci", "expiresInDays": 0 }),
+        json!({ "name": "ci", "expiresInDays": 366 }),
+        json!({ "name": "ci", "expiresInDays": i64::MAX }),
+        json!({ "name": "ci", "scopes": ["Reports"] }),
+    ] {
+        let response = app.post("/personal-access-tokens", &body).await;
+        assert_eq!(response.status().as_u16(), 400, "{body}");
+    }
+
+    // only permissions the user holds can be granted
+    let response = app
+        .post(
+            "/personal-access-tokens",
+            &json!({ "name": "ci", "scopes": ["reports:write"] }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_introspect_personal_access_tokens() {
+    let token = app.sign_up_and_log_in().await;
+    grant_reports_read(&app, &token).await;
+
+    let response = app
+        .post(
+            "/personal-access-tokens",
+            &json!({ "name": "reports", "scopes": ["reports:read"] }),
+        )
+        .await;
+    let created: CreatePersonalAccessTokenResponse = response
+        .json()
+        .await
+        .expect("Invalid personal access token");
+
+    let response = app.post_bearer("/introspect", &created.token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let introspection: IntrospectionResponse =
+        response.json().await.expect("Invalid introspection");
+    assert!(introspection.active);
+    assert_eq!(
+        introspection.sub,
+        Some(app.user_id(&token).await.to_string())
+    );
+    assert_eq!(introspection.scope.as_deref(), Some("reports:read"));
+    assert_eq!(
+        introspection.token_type.as_deref(),
+        Some("personal_access_token")
+    );
+
+    let response = app
+        .post_verify_token_permission(&json!({
+            "token": created.token,
+            "permission": "reports:read",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post("/introspect", &json!({ "token": "pat_unknown" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let introspection: IntrospectionResponse =
+        response.json().await.expect("Invalid introspection");
+    assert_eq!(introspection, IntrospectionResponse::default());
+}
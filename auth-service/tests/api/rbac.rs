@@ -0,0 +1,145 @@
+use auth_service::{
+    routes::{AdminUserResponse, GrantsResponse, RoleResponse},
+    ErrorResponse,
+};
+use serde_json::json;
+use test_helpers::api_test;
+
+use crate::helpers::TestApp;
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[api_test]
+async fn should_list_built_in_admin_role() {
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app.get_admin("/roles").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let roles: Vec<RoleResponse> = response.json().await.expect("Invalid role list");
+    assert!(roles.iter().any(|role| role.name == "admin"));
+}
+
+#[api_test]
+async fn should_issue_tokens_with_assigned_roles_and_permissions() {
+    let user_token = app.sign_up_and_log_in().await;
+    let user_id = app.user_id(&user_token).await;
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app
+        .post_admin(
+            "/permissions",
+            &json!({ "name": "reports:read", "description": "Reads reports" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let response = app
+        .post_admin("/roles", &json!({ "name": "analyst" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let response = app
+        .post_admin(
+            "/roles/analyst/permissions",
+            &json!({ "permission": "reports:read" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app
+        .post_admin(
+            &format!("/users/{user_id}/roles"),
+            &json!({ "role": "analyst" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_admin(&format!("/users/{user_id}/roles")).await;
+    let grants: GrantsResponse = response.json().await.expect("Invalid grants");
+    assert_eq!(grants.roles, ["analyst"]);
+    assert_eq!(grants.permissions, ["reports:read"]);
+
+    // grants only show up in tokens issued after the change
+    let response = app
+        .post_verify_token_permission(&json!({
+            "token": user_token,
+            "permission": "reports:read",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Missing permission");
+
+    let response = app.get_admin(&format!("/users/{user_id}")).await;
+    let user: AdminUserResponse = response.json().await.expect("Invalid user");
+    let user_token = app.log_in(&user.email).await;
+
+    let response = app
+        .post_verify_token_permission(&json!({
+            "token": user_token,
+            "permission": "reports:read",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // like on the other routes, the token may come in the Authorization header instead
+    let response = reqwest::Client::new()
+        .post(format!("{}/verify-token/permission", app.address))
+        .bearer_auth(&user_token)
+        .json(&json!({ "permission": "reports:read" }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token_permission(&json!({
+            "token": user_token,
+            "permission": "reports:write",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_reject_unknown_and_invalid_names() {
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app
+        .post_admin("/roles", &json!({ "name": "Not Valid" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        error_message(response).await,
+        "Invalid role or permission name"
+    );
+
+    let response = app
+        .post_admin("/roles/ghost/permissions", &json!({ "permission": "x" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_message(response).await, "Role not found");
+
+    let response = app.post_admin("/roles", &json!({ "name": "admin" })).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[api_test]
+async fn should_not_delete_admin_role() {
+    app.sign_up_admin_and_log_in().await;
+
+    let response = app.delete_admin("/roles/admin").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_admin("/roles", &json!({ "name": "auditor" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let response = app.delete_admin("/roles/auditor").await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.delete_admin("/roles/auditor").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -1,4 +1,4 @@
-use auth_service::{routes::SignupResponse, ErrorResponse};
+use auth_service::{routes::SignupResponse, ErrorReason, ErrorResponse};
 use serde_json::json;
 use test_helpers::api_test;
 
@@ -10,7 +10,7 @@ async fn should_return_422_if_malformed_input() {
 
     let test_cases = [
         json!({
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
             "requires2FA": true
         }),
         json!({
@@ -19,7 +19,7 @@ async fn should_return_422_if_malformed_input() {
         }),
         json!({
             "email": random_email,
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
         }),
     ];
 
@@ -39,7 +39,7 @@ async fn should_return_201_if_valid_input() {
     let response = app
         .post_signup(&json!({
             "email": get_random_email(),
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
             "requires2FA": false
         }))
         .await;
@@ -64,17 +64,12 @@ async fn should_return_400_if_invalid_input() {
     let test_cases = [
         json!({
             "email": "",
-            "password": "password123",
+            "password": "x7Kp#2vLq9!w",
             "requires2FA": true
         }),
         json!({
             "email": "malformed",
-            "password": "password123",
-            "requires2FA": true
-        }),
-        json!({
-            "email": get_random_email(),
-            "password": "short",
+            "password": "x7Kp#2vLq9!w",
             "requires2FA": true
         }),
     ];
@@ -99,10 +94,131 @@ async fn should_return_400_if_invalid_input() {
     }
 }
 
+#[api_test]
+async fn should_return_400_with_reasons_if_password_violates_policy() {
+    let test_cases = [
+        ("short", "password_too_short"),
+        ("password123", "password_too_common"),
+        ("aaaaaaaaaaaa", "password_too_weak"),
+    ];
+
+    for (password, expected_code) in test_cases {
+        let response = app
+            .post_signup(&json!({
+                "email": get_random_email(),
+                "password": password,
+                "requires2FA": false
+            }))
+            .await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for password: {}",
+            password
+        );
+
+        let error_response = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse");
+
+        assert_eq!(
+            error_response.error,
+            "Password does not meet the password policy".to_owned()
+        );
+        assert!(
+            error_response
+                .reasons
+                .iter()
+                .any(|reason| reason.code == expected_code),
+            "Missing {} reason for password: {}",
+            expected_code,
+            password
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_password_contains_email() {
+    let response = app
+        .post_signup(&json!({
+            "email": "winston.churchill@example.com",
+            "password": "Xq9!Winston.Churchill",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+
+    assert!(error_response
+        .reasons
+        .iter()
+        .any(|reason| reason.code == "password_contains_email"));
+}
+
+#[api_test]
+async fn should_return_400_if_password_is_breached() {
+    let response = app
+        .post_signup(&json!({
+            "email": get_random_email(),
+            "password": "Tr0ub4dor&3",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+
+    assert_eq!(
+        error_response.reasons,
+        vec![ErrorReason {
+            code: "password_breached".to_owned(),
+            message: "Password has appeared in a known data breach".to_owned(),
+        }]
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_email_domain_is_disposable() {
+    let response = app
+        .post_signup(&json!({
+            "email": "someone@eu.mailinator.com",
+            "password": "x7Kp#2vLq9!w",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+
+    assert_eq!(error_response.error, "Email domain is not accepted");
+    assert_eq!(
+        error_response.reasons,
+        vec![ErrorReason {
+            code: "email_domain_disposable".to_owned(),
+            message: "Email domain belongs to a disposable email provider".to_owned(),
+        }]
+    );
+}
+
 #[api_test]
 async fn should_return_409_if_email_already_exists() {
     let email = get_random_email();
-    let password = "password123";
+    let password = "x7Kp#2vLq9!w";
 
     let response = app
         .post_signup(&json!({
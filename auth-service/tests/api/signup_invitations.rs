@@ -0,0 +1,157 @@
+use auth_service::{
+    domain::SignupMode,
+    routes::{OrgResponse, SignupInvitationResponse},
+    ErrorResponse,
+};
+use serde_json::json;
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, TEST_PASSWORD};
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+async fn accept_emails(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+fn signup_body(email: &str, token: Option<&str>) -> serde_json::Value {
+    json!({
+        "email": email,
+        "password": TEST_PASSWORD,
+        "requires2FA": false,
+        "invitationToken": token,
+    })
+}
+
+#[tokio::test]
+async fn should_require_an_invitation_in_invite_only_mode() {
+    let mut app = TestApp::with_signup_mode(SignupMode::InviteOnly).await;
+    accept_emails(&app).await;
+
+    let email = get_random_email();
+    let response = app.post_signup(&signup_body(&email, None)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        error_message(response).await,
+        "An invitation is required to sign up"
+    );
+
+    let response = app
+        .post_signup(&signup_body(&email, Some("not-a-token")))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        error_message(response).await,
+        "Invalid or expired invitation"
+    );
+
+    app.clean_up().await;
+}
+
+#[api_test]
+async fn should_sign_up_once_with_an_admin_invitation() {
+    accept_emails(&app).await;
+    app.sign_up_admin_and_log_in().await;
+
+    let email = get_random_email();
+    let response = app
+        .post_admin("/signup-invitations", &json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let invitation: SignupInvitationResponse = response.json().await.expect("Invalid invitation");
+    assert_eq!(invitation.org_id, None);
+    let token = app.sent_token().await;
+
+    // the invitation is bound to the invited email
+    let response = app
+        .post_signup(&signup_body(&get_random_email(), Some(&token)))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_signup(&signup_body(&email, Some(&token))).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_signup(&signup_body(&email, Some(&token))).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_admin("/signup-invitations").await;
+    let invitations: Vec<SignupInvitationResponse> =
+        response.json().await.expect("Invalid invitation list");
+    assert!(invitations.is_empty());
+}
+
+#[api_test]
+async fn should_not_accept_revoked_invitations() {
+    accept_emails(&app).await;
+    app.sign_up_admin_and_log_in().await;
+
+    let email = get_random_email();
+    let response = app
+        .post_admin("/signup-invitations", &json!({ "email": email }))
+        .await;
+    let invitation: SignupInvitationResponse = response.json().await.expect("Invalid invitation");
+    let token = app.sent_token().await;
+
+    let path = format!("/signup-invitations/{}", invitation.id);
+    let response = app.delete_admin(&path).await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.delete_admin(&path).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.post_signup(&signup_body(&email, Some(&token))).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_add_users_invited_by_an_organization_as_members() {
+    accept_emails(&app).await;
+    let owner_email = app.sign_up().await;
+    app.log_in(&owner_email).await;
+    let response = app.post("/orgs", &json!({ "name": "Acme" })).await;
+    let org: OrgResponse = response.json().await.expect("Invalid organization");
+
+    let path = format!("/orgs/{}/signup-invitations", org.id);
+    let email = get_random_email();
+    let response = app.post(&path, &json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let invitation: SignupInvitationResponse = response.json().await.expect("Invalid invitation");
+    assert_eq!(invitation.org_id.as_deref(), Some(org.id.as_str()));
+    let token = app.sent_token().await;
+
+    let response = app.get(&path).await;
+    let invitations: Vec<SignupInvitationResponse> =
+        response.json().await.expect("Invalid invitation list");
+    assert_eq!(invitations, [invitation]);
+
+    let response = app.post_signup(&signup_body(&email, Some(&token))).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.log_in(&email).await;
+    let response = app.get("/orgs").await;
+    let orgs: Vec<OrgResponse> = response.json().await.expect("Invalid organization list");
+    assert_eq!(orgs.len(), 1);
+    assert_eq!(
+        (orgs[0].id.as_str(), orgs[0].role.as_str()),
+        (org.id.as_str(), "member")
+    );
+
+    // only owners invite on behalf of the organization
+    let response = app
+        .post(&path, &json!({ "email": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
@@ -21,7 +21,7 @@ async fn should_return_200_if_correct_code() {
 
     let signup_body = serde_json::json!({
         "email": email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
         "requires2FA": true
     });
 
@@ -37,7 +37,7 @@ async fn should_return_200_if_correct_code() {
 
     let login_body = serde_json::json!({
         "email": email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
     });
 
     let TwoFactorAuthResponse {
@@ -51,8 +51,6 @@ async fn should_return_200_if_correct_code() {
 
     let (_, two_fa_code) = app
         .two_fa_code_store
-        .read()
-        .await
         .get_code(&email_value)
         .await
         .expect("2FA code must be present for email");
@@ -116,7 +114,7 @@ async fn should_return_400_if_invalid_input() {
 async fn should_return_401_if_incorrect_credentials() {
     let email = get_random_email();
     let email_value = Email::parse(email.clone().into()).unwrap();
-    let password = "password".to_owned();
+    let password = "x7Kp#2vLq9!w".to_owned();
 
     // not checking the response status because we explicitly deserialize the response body to SignupResponse
     app.post_signup(&json!({
@@ -148,8 +146,6 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let (login_attempt_id, two_fa_code) = app
         .two_fa_code_store
-        .read()
-        .await
         .get_code(&email_value)
         .await
         .unwrap_or_else(|_| panic!("2FA code for {email} must be in store"));
@@ -186,7 +182,7 @@ async fn should_return_401_if_incorrect_credentials() {
 async fn should_return_401_if_old_code() {
     let email = get_random_email();
     let email_value = Email::parse(email.clone().into()).unwrap();
-    let password = "password".to_owned();
+    let password = "x7Kp#2vLq9!w".to_owned();
 
     // not checking the status code because we explicitly deserialize the response body into SignupResponse
     app.post_signup(&json!({
@@ -224,8 +220,6 @@ async fn should_return_401_if_old_code() {
 
     let (login_attempt_id, two_fa_code) = app
         .two_fa_code_store
-        .read()
-        .await
         .get_code(&email_value)
         .await
         .unwrap_or_else(|_| panic!("2FA code for {email} must be in store"));
@@ -256,7 +250,7 @@ async fn should_return_401_if_same_code_twice() {
 
     let email = get_random_email();
     let email_value = Email::parse(email.clone().into()).unwrap();
-    let password = "password".to_owned();
+    let password = "x7Kp#2vLq9!w".to_owned();
 
     app.post_signup(&json!({
         "email": email,
@@ -287,8 +281,6 @@ async fn should_return_401_if_same_code_twice() {
 
     let (login_attempt_id, two_fa_code) = app
         .two_fa_code_store
-        .read()
-        .await
         .get_code(&email_value)
         .await
         .unwrap_or_else(|_| panic!("2FA code for {email} must be in store"));
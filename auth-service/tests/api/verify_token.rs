@@ -1,4 +1,11 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    domain::{AccountStatus, StatusChange, UserId},
+    utils::{
+        auth::{validate_token, TokenSettings, DEFAULT_TOKEN_AUDIENCE, DEFAULT_TOKEN_ISSUER},
+        constants::JWT_COOKIE_NAME,
+    },
+    ErrorResponse,
+};
 use serde_json::json;
 use test_helpers::api_test;
 
@@ -17,7 +24,7 @@ async fn should_return_200_valid_token() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
         "requires2FA": false
     });
 
@@ -27,7 +34,7 @@ async fn should_return_200_valid_token() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
     });
 
     let response = app.post_login(&login_body).await;
@@ -52,6 +59,17 @@ async fn should_return_200_valid_token() {
     assert_eq!(response.status().as_u16(), 200);
 }
 
+#[api_test]
+async fn should_accept_a_bearer_token_instead_of_a_body() {
+    let token = app.sign_up_and_log_in().await;
+
+    let response = app.post_bearer("/verify-token", &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_bearer("/verify-token", "foobar").await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
 #[api_test]
 async fn should_return_401_if_invalid_token() {
     let response = app
@@ -77,7 +95,7 @@ async fn should_return_401_if_banned_token() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
         "requires2FA": false
     });
 
@@ -87,7 +105,7 @@ async fn should_return_401_if_banned_token() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "x7Kp#2vLq9!w",
     });
 
     let response = app.post_login(&login_body).await;
@@ -126,3 +144,67 @@ async fn should_return_401_if_banned_token() {
         "Invalid auth token".to_owned()
     );
 }
+
+#[api_test]
+async fn should_return_423_if_account_locked() {
+    let token = app.sign_up_and_log_in().await;
+    let claims = validate_token(
+        &token.clone().into(),
+        app.banned_token_store.clone(),
+        app.user_store.clone(),
+        app.org_store.clone(),
+        &TokenSettings::default(),
+    )
+    .await
+    .expect("Failed to validate token");
+    let user_id = UserId::parse(&claims.sub).expect("Invalid user id");
+
+    app.user_store
+        .set_status(
+            &user_id,
+            StatusChange {
+                status: AccountStatus::Locked,
+                reason: "suspicious activity".to_owned(),
+                actor: "test".to_owned(),
+            },
+        )
+        .await
+        .expect("Failed to lock account");
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+
+    assert_eq!(response.status().as_u16(), 423);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Account locked".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_token_is_for_another_audience() {
+    let token = app.sign_up_and_log_in().await;
+    let claims = app.claims(&token).await;
+    assert_eq!(claims.iss, DEFAULT_TOKEN_ISSUER);
+    assert_eq!(claims.aud, [DEFAULT_TOKEN_AUDIENCE]);
+
+    let verify_for = |audience: &str| {
+        app.http_client
+            .post(format!("{}/verify-token?audience={audience}", app.address))
+            .json(&json!({ "token": token }))
+            .send()
+    };
+
+    let response = verify_for(DEFAULT_TOKEN_AUDIENCE)
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = verify_for("billing")
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 401);
+}